@@ -117,6 +117,7 @@ pub struct AdminControllerMsg {
     pub event_id: AdminControllerEventID,
     pub workdir_idx: Option<WorkdirIdx>,
     pub data_string: Option<String>,
+    pub data_json: Option<serde_json::Value>,
     // Channel to send a one-time response.
     pub resp_channel: Option<tokio::sync::oneshot::Sender<String>>,
 }
@@ -127,6 +128,7 @@ impl AdminControllerMsg {
             event_id: 0,
             workdir_idx: None,
             data_string: None,
+            data_json: None,
             resp_channel: None,
         }
     }
@@ -150,6 +152,7 @@ pub const EVENT_NOTIF_CONFIG_FILE_CHANGE: u8 = 128;
 pub const EVENT_DEBUG_PRINT: u8 = 129;
 pub const EVENT_SHELL_EXEC: u8 = 130;
 pub const EVENT_POST_PUBLISH: u8 = 131;
+pub const EVENT_EVENTS_EXEC: u8 = 132; // Command (data_string) for the events worker.
 
 pub type AdminControllerTx = tokio::sync::mpsc::Sender<AdminControllerMsg>;
 pub type AdminControllerRx = tokio::sync::mpsc::Receiver<AdminControllerMsg>;
@@ -159,6 +159,71 @@ impl WorkdirPaths {
     }
 }
 
+// A secret value in a suibase.yaml file (e.g. an API key).
+//
+// Can be specified in three ways:
+//    "the-secret"                  (inline)
+//    { env: "ENV_VAR_NAME" }       (read from the suibase-daemon environment)
+//    { file: "~/path/to/secret" }  (read from a file, surrounding whitespaces removed)
+//
+// The value is never shown by Debug (so never in logs or debug API outputs).
+#[derive(Eq, PartialEq, Clone)]
+pub struct LinkSecret(String);
+
+impl LinkSecret {
+    pub fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    fn from_yaml(value: &serde_yaml::Value, context: &str) -> Option<Self> {
+        if let Some(inline) = value.as_str() {
+            return Some(Self(inline.to_string()));
+        }
+        if let Some(env_var) = value["env"].as_str() {
+            return match std::env::var(env_var) {
+                Ok(secret) => Some(Self(secret)),
+                Err(e) => {
+                    log::warn!("{}: env variable {} not usable: {}", context, env_var, e);
+                    None
+                }
+            };
+        }
+        if let Some(file) = value["file"].as_str() {
+            let path = match file.strip_prefix("~/") {
+                Some(relative) => get_home_path().join(relative),
+                None => PathBuf::from(file),
+            };
+            return match std::fs::read_to_string(&path) {
+                Ok(secret) => Some(Self(secret.trim().to_string())),
+                Err(e) => {
+                    log::warn!("{}: file {} not readable: {}", context, path.display(), e);
+                    None
+                }
+            };
+        }
+        None
+    }
+}
+
+impl std::fmt::Debug for LinkSecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("LinkSecret(***)")
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum LinkAuth {
+    Bearer(LinkSecret),
+    Basic {
+        username: String,
+        password: LinkSecret,
+    },
+}
+
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct Link {
     // A link in a suibase.yaml file.
@@ -169,6 +234,9 @@ pub struct Link {
     pub metrics: Option<String>,
     pub ws: Option<String>,
     pub priority: u8,
+    // Added to every request toward this link. Values are treated as secrets.
+    pub headers: Vec<(String, LinkSecret)>,
+    pub auth: Option<LinkAuth>,
 }
 
 impl Link {
@@ -181,6 +249,159 @@ impl Link {
             metrics: None,
             ws: None,
             priority: u8::MAX,
+            headers: Vec::new(),
+            auth: None,
+        }
+    }
+
+    // Parse the optional "headers" and "auth" of a link.
+    //
+    // Example:
+    //   - alias: "my-provider"
+    //     rpc: "https://my-provider.com/sui"
+    //     headers:
+    //       x-api-key: { env: "MY_PROVIDER_KEY" }
+    //     auth:
+    //       bearer: { file: "~/.secrets/my-provider-token" }
+    //
+    // or, for basic authentication:
+    //     auth:
+    //       basic:
+    //         username: "me"
+    //         password: { env: "MY_PROVIDER_PASSWORD" }
+    //
+    // A header or auth with an unresolvable secret is ignored (with a warning).
+    fn load_headers_and_auth(&mut self, link: &serde_yaml::Value) {
+        let context = format!("link {}", self.alias);
+        if let Some(headers) = link["headers"].as_mapping() {
+            for (name, value) in headers {
+                if let Some(name) = name.as_str() {
+                    if let Some(secret) = LinkSecret::from_yaml(value, &context) {
+                        self.headers.push((name.to_string(), secret));
+                    }
+                }
+            }
+        }
+
+        let auth = &link["auth"];
+        if !auth["bearer"].is_null() {
+            self.auth = LinkSecret::from_yaml(&auth["bearer"], &context).map(LinkAuth::Bearer);
+        } else if let Some(username) = auth["basic"]["username"].as_str() {
+            self.auth =
+                LinkSecret::from_yaml(&auth["basic"]["password"], &context).map(|password| {
+                    LinkAuth::Basic {
+                        username: username.to_string(),
+                        password,
+                    }
+                });
+        }
+    }
+}
+
+// Sui event filter of a configured subscription (subset of the Sui EventFilter).
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum EventFilter {
+    Package(String), // "0x" package id
+    MoveEventType(String),
+    MoveModule { package: String, module: String },
+    Sender(String),
+}
+
+impl EventFilter {
+    // The filter as expected by "suix_subscribeEvent" and "suix_queryEvents".
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            EventFilter::Package(package) => serde_json::json!({ "Package": package }),
+            EventFilter::MoveEventType(event_type) => {
+                serde_json::json!({ "MoveEventType": event_type })
+            }
+            EventFilter::MoveModule { package, module } => {
+                serde_json::json!({ "MoveModule": { "package": package, "module": module } })
+            }
+            EventFilter::Sender(sender) => serde_json::json!({ "Sender": sender }),
+        }
+    }
+
+    fn from_yaml(subscription: &serde_yaml::Value) -> Option<Self> {
+        let mut filters = Vec::new();
+        if let Some(package) = subscription["package"].as_str() {
+            filters.push(EventFilter::Package(package.to_string()));
+        }
+        if let Some(event_type) = subscription["move_event_type"].as_str() {
+            filters.push(EventFilter::MoveEventType(event_type.to_string()));
+        }
+        let move_module = &subscription["move_module"];
+        if let (Some(package), Some(module)) = (
+            move_module["package"].as_str(),
+            move_module["module"].as_str(),
+        ) {
+            filters.push(EventFilter::MoveModule {
+                package: package.to_string(),
+                module: module.to_string(),
+            });
+        }
+        if let Some(sender) = subscription["sender"].as_str() {
+            filters.push(EventFilter::Sender(sender.to_string()));
+        }
+        // Exactly one filter expected.
+        if filters.len() != 1 {
+            return None;
+        }
+        filters.pop()
+    }
+}
+
+// An "event_subscriptions" entry in a suibase.yaml file.
+//
+// The label identifies the subscription (and its events in the DB).
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct EventSubscription {
+    pub label: String,
+    pub filter: EventFilter,
+}
+
+// A "webhooks" entry in a suibase.yaml file.
+//
+// Every stored event matching the (optional) filters is POSTed to the url.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Webhook {
+    pub name: String,
+    pub url: String,
+    pub package: Option<String>,    // Package name or package id.
+    pub event_type: Option<String>, // Move event type prefix.
+    pub secret: Option<LinkSecret>, // HMAC-SHA256 key for the signature header.
+}
+
+// TLS settings of a listening port (proxy or API).
+//
+// When enabled without cert/key paths, a self-signed certificate
+// is generated (once) and re-used. Only one of the two paths is an error.
+#[derive(Debug, Eq, PartialEq, Clone, Default)]
+pub struct TlsConfig {
+    pub enabled: bool,
+    pub cert_path: Option<String>, // PEM file
+    pub key_path: Option<String>,  // PEM file
+}
+
+impl TlsConfig {
+    pub fn is_self_signed(&self) -> bool {
+        self.cert_path.is_none() && self.key_path.is_none()
+    }
+
+    // Merge the "<prefix>_tls_enabled", "<prefix>_tls_cert" and "<prefix>_tls_key"
+    // variables (when present in the yaml).
+    fn load_and_merge_from_yaml(&mut self, yaml: &serde_yaml::Value, prefix: &str) {
+        let enabled = &yaml[format!("{}_tls_enabled", prefix).as_str()];
+        if let Some(enabled) = enabled.as_bool() {
+            self.enabled = enabled;
+        } else if let Some(enabled) = enabled.as_str() {
+            self.enabled = enabled != "false";
+        }
+        if let Some(cert_path) = yaml[format!("{}_tls_cert", prefix).as_str()].as_str() {
+            self.cert_path = Some(cert_path.to_string());
+        }
+        if let Some(key_path) = yaml[format!("{}_tls_key", prefix).as_str()].as_str() {
+            self.key_path = Some(key_path.to_string());
         }
     }
 }
@@ -275,9 +496,43 @@ pub struct WorkdirUserConfig {
     user_request_start: bool, // true when user_request == "start"
     proxy_enabled: bool,
     proxy_port_number: u16,
+    proxy_bind_address: String,
+    proxy_tls: TlsConfig,
+    proxy_access_log: bool,
     links_overrides: bool,
     links: HashMap<String, Link>,
-    dtp_package_id: Option<String>, // Package ID of the DTP package for this workdir.
+    event_subscriptions: HashMap<String, EventSubscription>, // Key is the label.
+    webhooks: HashMap<String, Webhook>,                      // Key is the name.
+    // Events retention (zero means no limit). Max rows is per package instance and table.
+    events_max_age_days: u32,
+    events_max_rows: u64,
+    // Most recent events fetched for a package without stored events (see events_backfill.rs).
+    events_initial_backfill: u64,
+    // Console log files (see log_worker.rs in suibase-daemon).
+    console_log_level: String, // Most verbose level written ("error" ... "trace").
+    console_log_max_size_mb: u64,
+    console_log_max_files: u32, // Rotated files kept per package.
+    // Package instances retention (see packages_poller.rs in suibase-daemon).
+    packages_keep_last: u32, // Per package uuid, zero means no limit. Tagged are always kept.
+    packages_prune: String,  // "none", "archive" or "delete" of the expired published-data.
+    // Balances tracking (see balance_poller.rs in suibase-daemon).
+    balance_addresses: Vec<String>, // Tracked in addition to the keystore addresses.
+    low_gas_threshold_mist: u64,    // Zero disables the low gas warning.
+    balance_poll_interval_secs: u64,
+    // Faucet manager (see faucet_poller.rs in suibase-daemon). Localnet and devnet only.
+    sui_faucet_enabled: bool, // Local sui-faucet process (localnet).
+    sui_faucet_host_ip: String,
+    sui_faucet_port: u16,
+    faucet_addresses: Vec<String>, // Auto-refilled up to faucet_target_mist.
+    faucet_target_mist: u64,
+    faucet_funder_address: Option<String>, // Keystore address paying instead of the faucet.
+    faucet_min_interval_secs: u64,         // Per address.
+    faucet_max_requests_per_hour: u32,     // Per workdir, zero means no limit.
+    // Local services supervision (see supervisor_poller.rs in suibase-daemon).
+    supervisor_restart_policy: String, // "always" or "never".
+    supervisor_max_backoff_secs: u64,
+    supervisor_crash_loop_restarts: u32, // Within 10 minutes. Zero disables the detection.
+    dtp_package_id: Option<String>,      // Package ID of the DTP package for this workdir.
     dtp_services: LinkedList<DTPService>, // Each configured service.
     dtp_default_gas_address: Option<String>, // Pays gas when txn not related to a service.
     autocoins_enabled: bool,
@@ -291,8 +546,35 @@ impl WorkdirUserConfig {
             user_request_start: false,
             proxy_enabled: false,
             proxy_port_number: 0,
+            proxy_bind_address: "0.0.0.0".to_string(),
+            proxy_tls: TlsConfig::default(),
+            proxy_access_log: false,
             links_overrides: false,
             links: HashMap::new(),
+            event_subscriptions: HashMap::new(),
+            webhooks: HashMap::new(),
+            events_max_age_days: 30,
+            events_max_rows: 100_000,
+            events_initial_backfill: 100,
+            console_log_level: "trace".to_string(),
+            console_log_max_size_mb: 10,
+            console_log_max_files: 5,
+            packages_keep_last: 0, // No pruning unless configured.
+            packages_prune: "none".to_string(),
+            balance_addresses: Vec::new(),
+            low_gas_threshold_mist: 100_000_000,
+            balance_poll_interval_secs: 30,
+            sui_faucet_enabled: true,
+            sui_faucet_host_ip: "localhost".to_string(),
+            sui_faucet_port: 9123,
+            faucet_addresses: Vec::new(),
+            faucet_target_mist: 10_000_000_000,
+            faucet_funder_address: None,
+            faucet_min_interval_secs: 60,
+            faucet_max_requests_per_hour: 30,
+            supervisor_restart_policy: "always".to_string(),
+            supervisor_max_backoff_secs: 300,
+            supervisor_crash_loop_restarts: 5,
             dtp_package_id: None,
             dtp_services: LinkedList::new(),
             dtp_default_gas_address: None,
@@ -317,6 +599,18 @@ impl WorkdirUserConfig {
         self.proxy_port_number
     }
 
+    pub fn proxy_bind_address(&self) -> &str {
+        &self.proxy_bind_address
+    }
+
+    pub fn proxy_tls(&self) -> &TlsConfig {
+        &self.proxy_tls
+    }
+
+    pub fn is_proxy_access_log_enabled(&self) -> bool {
+        self.proxy_access_log
+    }
+
     pub fn links_overrides(&self) -> bool {
         self.links_overrides
     }
@@ -325,6 +619,102 @@ impl WorkdirUserConfig {
         &self.links
     }
 
+    pub fn event_subscriptions(&self) -> &HashMap<String, EventSubscription> {
+        &self.event_subscriptions
+    }
+
+    pub fn webhooks(&self) -> &HashMap<String, Webhook> {
+        &self.webhooks
+    }
+
+    pub fn events_max_age_days(&self) -> u32 {
+        self.events_max_age_days
+    }
+
+    pub fn events_max_rows(&self) -> u64 {
+        self.events_max_rows
+    }
+
+    pub fn events_initial_backfill(&self) -> u64 {
+        self.events_initial_backfill
+    }
+
+    pub fn console_log_level(&self) -> &str {
+        &self.console_log_level
+    }
+
+    pub fn console_log_max_size_mb(&self) -> u64 {
+        self.console_log_max_size_mb
+    }
+
+    pub fn console_log_max_files(&self) -> u32 {
+        self.console_log_max_files
+    }
+
+    pub fn packages_keep_last(&self) -> u32 {
+        self.packages_keep_last
+    }
+
+    pub fn packages_prune(&self) -> &str {
+        &self.packages_prune
+    }
+
+    pub fn balance_addresses(&self) -> &[String] {
+        &self.balance_addresses
+    }
+
+    pub fn low_gas_threshold_mist(&self) -> u64 {
+        self.low_gas_threshold_mist
+    }
+
+    pub fn balance_poll_interval_secs(&self) -> u64 {
+        self.balance_poll_interval_secs
+    }
+
+    pub fn is_sui_faucet_enabled(&self) -> bool {
+        self.sui_faucet_enabled
+    }
+
+    pub fn sui_faucet_host_ip(&self) -> &str {
+        &self.sui_faucet_host_ip
+    }
+
+    pub fn sui_faucet_port(&self) -> u16 {
+        self.sui_faucet_port
+    }
+
+    pub fn faucet_addresses(&self) -> &[String] {
+        &self.faucet_addresses
+    }
+
+    pub fn faucet_target_mist(&self) -> u64 {
+        self.faucet_target_mist
+    }
+
+    pub fn faucet_funder_address(&self) -> Option<&String> {
+        self.faucet_funder_address.as_ref()
+    }
+
+    pub fn faucet_min_interval_secs(&self) -> u64 {
+        self.faucet_min_interval_secs
+    }
+
+    pub fn faucet_max_requests_per_hour(&self) -> u32 {
+        self.faucet_max_requests_per_hour
+    }
+
+    pub fn supervisor_restart_policy(&self) -> &str {
+        &self.supervisor_restart_policy
+    }
+
+    pub fn supervisor_max_backoff_secs(&self) -> u64 {
+        self.supervisor_max_backoff_secs
+    }
+
+    pub fn supervisor_crash_loop_restarts(&self) -> u32 {
+        self.supervisor_crash_loop_restarts
+    }
+
     pub fn is_autocoins_enabled(&self) -> bool {
         self.autocoins_enabled
     }
@@ -409,16 +799,64 @@ impl WorkdirUserConfig {
         // Example of suibase.yaml:
         //
         // proxy_enabled: false
+        // proxy_access_log: true
+        // events_max_age_days: 30     (0 to keep forever)
+        // events_max_rows: 100000     (per package instance, 0 for no limit)
+        // events_initial_backfill: 100  (most recent events of a new package, 0 for none)
+        // console_log_level: "debug"  (error, warn, info, debug or trace)
+        // console_log_max_size_mb: 10
+        // console_log_max_files: 5
+        // packages_keep_last: 20      (per package, default 0 for no limit)
+        // packages_prune: "archive"   (none, archive or delete)
+        // low_gas_threshold_mist: 100000000  (0 to disable the warning)
+        // balance_addresses:          (tracked in addition to the keystore)
+        //   - "0xf7ae..."
+        // balance_poll_interval_secs: 30
+        // faucet_addresses:           (localnet/devnet, topped up to faucet_target_mist)
+        //   - "0xf7ae..."
+        // faucet_target_mist: 10000000000
+        // faucet_funder_address: "0xc729..."  (pays instead of the faucet)
+        // faucet_min_interval_secs: 60
+        // faucet_max_requests_per_hour: 30
+        // supervisor_restart_policy: "always"  (always or never)
+        // supervisor_max_backoff_secs: 300
+        // supervisor_crash_loop_restarts: 5    (within 10 minutes, 0 to disable)
+        // proxy_bind_address: "0.0.0.0"
+        // proxy_tls_enabled: true
+        // proxy_tls_cert: "/path/to/cert.pem"   (self-signed generated when cert/key not specified)
+        // proxy_tls_key: "/path/to/key.pem"
         //
         // links:
         //   - alias: "localnet"
         //     rpc: "http://localhost:9000"
         //     ws: "ws://localhost:9000"
         //     priority: 10
+        //     headers:                 (optional, see Link::load_headers_and_auth)
+        //       x-api-key: { env: "MY_KEY" }
         //   - alias: "localnet"
         //     enabled: false
         //     rpc: "http://localhost:9000"
         //
+        // event_subscriptions:        (exactly one filter per entry)
+        //   - label: "deepbook"
+        //     package: "0xdee9"
+        //   - label: "swaps"
+        //     move_event_type: "0x2c8d...::pool::SwapEvent"
+        //   - label: "pool"
+        //     move_module: { package: "0x2c8d...", module: "pool" }
+        //   - label: "my-txns"
+        //     sender: "0xf7ae..."
+        //     enabled: false
+        //
+        // webhooks:                   (filters are optional)
+        //   - name: "backend"
+        //     url: "http://localhost:3000/sui-events"
+        //     package: "counter"        (package name or id)
+        //     move_event_type: "0xe065...::counter::CounterChanged"
+        //     secret: { env: "WEBHOOK_SECRET" }   (see LinkSecret)
+        //   - name: "old-backend"
+        //     enabled: false
+        //
         // dtp_package_id: "0x9c0c8..."
         //
         // dtp_services:
@@ -448,6 +886,113 @@ impl WorkdirUserConfig {
             self.proxy_enabled = proxy_enabled != "false";
         }
 
+        // proxy_access_log can be "true" or "false".
+        //
+        // When true, every proxied request is logged (JSON lines) in
+        // workdirs/<workdir>/logs/proxy-access.jsonl
+        if let Some(proxy_access_log) = yaml["proxy_access_log"].as_bool() {
+            self.proxy_access_log = proxy_access_log;
+        } else if let Some(proxy_access_log) = yaml["proxy_access_log"].as_str() {
+            self.proxy_access_log = proxy_access_log != "false";
+        }
+
+        // Retention of the events stored in workdirs/<workdir>/indexer/sqlite.db
+        if let Some(events_max_age_days) = yaml["events_max_age_days"].as_u64() {
+            self.events_max_age_days = events_max_age_days as u32;
+        }
+        if let Some(events_max_rows) = yaml["events_max_rows"].as_u64() {
+            self.events_max_rows = events_max_rows;
+        }
+        if let Some(events_initial_backfill) = yaml["events_initial_backfill"].as_u64() {
+            self.events_initial_backfill = events_initial_backfill;
+        }
+
+        // Console log files in workdirs/<workdir>/logs/console
+        if let Some(console_log_level) = yaml["console_log_level"].as_str() {
+            self.console_log_level = console_log_level.to_string();
+        }
+        if let Some(max_size_mb) = yaml["console_log_max_size_mb"].as_u64() {
+            self.console_log_max_size_mb = max_size_mb;
+        }
+        if let Some(max_files) = yaml["console_log_max_files"].as_u64() {
+            self.console_log_max_files = max_files as u32;
+        }
+
+        // Package instances in workdirs/<workdir>/published-data
+        if let Some(keep_last) = yaml["packages_keep_last"].as_u64() {
+            self.packages_keep_last = keep_last as u32;
+        }
+        if let Some(prune) = yaml["packages_prune"].as_str() {
+            if ["none", "archive", "delete"].contains(&prune) {
+                self.packages_prune = prune.to_string();
+            } else {
+                log::warn!("Invalid packages_prune {:?} in {}", prune, path);
+            }
+        }
+
+        // Balances tracking.
+        if let Some(threshold) = yaml["low_gas_threshold_mist"].as_u64() {
+            self.low_gas_threshold_mist = threshold;
+        }
+        if let Some(interval) = yaml["balance_poll_interval_secs"].as_u64() {
+            self.balance_poll_interval_secs = interval.max(1);
+        }
+        if let Some(addresses) = yaml["balance_addresses"].as_sequence() {
+            self.balance_addresses = addresses
+                .iter()
+                .filter_map(|address| address.as_str())
+                .map(|address| address.trim().to_lowercase())
+                .filter(|address| address.starts_with("0x"))
+                .collect();
+        }
+
+        // Faucet manager. The sui_faucet_* are shared with the scripts (localnet).
+        if let Some(enabled) = yaml["sui_faucet_enabled"].as_bool() {
+            self.sui_faucet_enabled = enabled;
+        }
+        if let Some(host_ip) = yaml["sui_faucet_host_ip"].as_str() {
+            self.sui_faucet_host_ip = host_ip.to_string();
+        }
+        if let Some(port) = yaml["sui_faucet_port"].as_u64() {
+            self.sui_faucet_port = port as u16;
+        }
+        if let Some(addresses) = yaml["faucet_addresses"].as_sequence() {
+            self.faucet_addresses = addresses
+                .iter()
+                .filter_map(|address| address.as_str())
+                .map(|address| address.trim().to_lowercase())
+                .filter(|address| address.starts_with("0x"))
+                .collect();
+        }
+        if let Some(target) = yaml["faucet_target_mist"].as_u64() {
+            self.faucet_target_mist = target;
+        }
+        if let Some(funder) = yaml["faucet_funder_address"].as_str() {
+            let funder = funder.trim().to_lowercase();
+            self.faucet_funder_address = funder.starts_with("0x").then_some(funder);
+        }
+        if let Some(secs) = yaml["faucet_min_interval_secs"].as_u64() {
+            self.faucet_min_interval_secs = secs;
+        }
+        if let Some(max) = yaml["faucet_max_requests_per_hour"].as_u64() {
+            self.faucet_max_requests_per_hour = max as u32;
+        }
+
+        // Local services supervision.
+        if let Some(policy) = yaml["supervisor_restart_policy"].as_str() {
+            if ["always", "never"].contains(&policy) {
+                self.supervisor_restart_policy = policy.to_string();
+            } else {
+                log::warn!("Invalid supervisor_restart_policy {:?} in {}", policy, path);
+            }
+        }
+        if let Some(secs) = yaml["supervisor_max_backoff_secs"].as_u64() {
+            self.supervisor_max_backoff_secs = secs;
+        }
+        if let Some(restarts) = yaml["supervisor_crash_loop_restarts"].as_u64() {
+            self.supervisor_crash_loop_restarts = restarts as u32;
+        }
+
         // autocoins_enabled can be "true" or "false".
         if let Some(autocoins_enabled) = yaml["autocoins_enabled"].as_bool() {
             self.autocoins_enabled = autocoins_enabled;
@@ -521,6 +1066,12 @@ impl WorkdirUserConfig {
             self.proxy_port_number = proxy_port_number as u16;
         }
 
+        if let Some(proxy_bind_address) = yaml["proxy_bind_address"].as_str() {
+            self.proxy_bind_address = proxy_bind_address.to_string();
+        }
+
+        self.proxy_tls.load_and_merge_from_yaml(&yaml, "proxy");
+
         if let Some(dtp_package_id) = yaml["dtp_package_id"].as_str() {
             self.dtp_package_id = Some(dtp_package_id.to_string());
         }
@@ -541,7 +1092,7 @@ impl WorkdirUserConfig {
                     let metrics = link["metrics"].as_str().map(|s| s.to_string()); // Optional
                     let ws = link["ws"].as_str().map(|s| s.to_string()); // Optional
                     let priority = link["priority"].as_u64().unwrap_or(u64::MAX) as u8;
-                    let link = Link {
+                    let mut new_link = Link {
                         alias: alias.to_string(),
                         selectable,
                         monitored,
@@ -549,9 +1100,70 @@ impl WorkdirUserConfig {
                         metrics,
                         ws,
                         priority,
+                        headers: Vec::new(),
+                        auth: None,
+                    };
+                    new_link.load_headers_and_auth(link);
+                    // Replace if already present.
+                    self.links.insert(alias.to_string(), new_link);
+                }
+            }
+        }
+
+        if let Some(subscriptions) = yaml["event_subscriptions"].as_sequence() {
+            for subscription in subscriptions {
+                if let Some(label) = subscription["label"].as_str() {
+                    // Allow the user to disable a subscription defined in another file.
+                    if !subscription["enabled"].as_bool().unwrap_or(true) {
+                        self.event_subscriptions.remove(label);
+                        continue;
+                    }
+                    if let Some(filter) = EventFilter::from_yaml(subscription) {
+                        // Replace if already present.
+                        self.event_subscriptions.insert(
+                            label.to_string(),
+                            EventSubscription {
+                                label: label.to_string(),
+                                filter,
+                            },
+                        );
+                    } else {
+                        log::warn!(
+                            "event_subscriptions {}: expecting exactly one filter",
+                            label
+                        );
+                    }
+                }
+            }
+        }
+
+        if let Some(webhooks) = yaml["webhooks"].as_sequence() {
+            for webhook in webhooks {
+                if let Some(name) = webhook["name"].as_str() {
+                    // Allow the user to disable a webhook defined in another file.
+                    if !webhook["enabled"].as_bool().unwrap_or(true) {
+                        self.webhooks.remove(name);
+                        continue;
+                    }
+                    let url = match webhook["url"].as_str() {
+                        Some(url) => url.to_string(),
+                        None => {
+                            log::warn!("webhooks {}: missing url", name);
+                            continue;
+                        }
                     };
+                    let context = format!("webhooks {}", name);
                     // Replace if already present.
-                    self.links.insert(alias.to_string(), link);
+                    self.webhooks.insert(
+                        name.to_string(),
+                        Webhook {
+                            name: name.to_string(),
+                            url,
+                            package: webhook["package"].as_str().map(|s| s.to_string()),
+                            event_type: webhook["move_event_type"].as_str().map(|s| s.to_string()),
+                            secret: LinkSecret::from_yaml(&webhook["secret"], &context),
+                        },
+                    );
                 }
             }
         }
@@ -713,7 +1325,7 @@ impl GlobalsWorkdirsST {
             suibase_web_port: 44380,
 
             suibase_api_ip: "localhost".to_string(),
-            suibase_api_port: 44399,
+            suibase_api_port: SUIBASE_API_PORT,
 
             dtp_api_ip: "localhost".to_string(),
             dtp_api_port: 44398,
@@ -819,6 +1431,61 @@ impl ManagedElement for Workdir {
 }
 */
 
+// Configuration of the suibase-daemon API port.
+//
+// Only from ~/suibase/workdirs/common/suibase.yaml (not workdir specific).
+//
+// The port is always SUIBASE_API_PORT.
+//
+// Example:
+//
+// api_bind_address: "0.0.0.0"
+// api_tls_enabled: true
+// api_tls_cert: "/path/to/cert.pem"
+// api_tls_key: "/path/to/key.pem"
+//
+pub const SUIBASE_API_PORT: u16 = 44399;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ApiUserConfig {
+    bind_address: String,
+    tls: TlsConfig,
+}
+
+impl ApiUserConfig {
+    pub fn new() -> Self {
+        Self {
+            bind_address: "127.0.0.1".to_string(),
+            tls: TlsConfig::default(),
+        }
+    }
+
+    pub fn bind_address(&self) -> &str {
+        &self.bind_address
+    }
+
+    pub fn tls(&self) -> &TlsConfig {
+        &self.tls
+    }
+
+    pub fn load_and_merge_from_file(&mut self, path: &Path) -> Result<()> {
+        let contents = std::fs::read_to_string(path)?;
+        let yaml: serde_yaml::Value = serde_yaml::from_str(&contents)?;
+
+        if let Some(bind_address) = yaml["api_bind_address"].as_str() {
+            self.bind_address = bind_address.to_string();
+        }
+        self.tls.load_and_merge_from_yaml(&yaml, "api");
+        Ok(())
+    }
+}
+
+impl Default for ApiUserConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 // User configuration for every workdir (mostly from suibase.yaml).
 #[derive(Debug)]
 pub struct GlobalsWorkdirConfigST {
@@ -850,7 +1517,7 @@ impl GlobalsWorkdirConfigST {
             suibase_web_port: 44380,
 
             suibase_api_ip: "localhost".to_string(),
-            suibase_api_port: 44399,
+            suibase_api_port: SUIBASE_API_PORT,
 
             dtp_api_ip: "localhost".to_string(),
             dtp_api_port: 44398,
@@ -872,3 +1539,35 @@ impl GlobalsWorkdirConfigST {
         false
     }*/
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_link_secret() {
+        let context = "link test";
+        let inline: serde_yaml::Value = serde_yaml::from_str("\"the-secret\"").unwrap();
+        let secret = LinkSecret::from_yaml(&inline, context).unwrap();
+        assert_eq!(secret.expose(), "the-secret");
+        assert_eq!(format!("{:?}", secret), "LinkSecret(***)");
+
+        std::env::set_var("SUIBASE_TEST_LINK_SECRET", "from-env");
+        let env: serde_yaml::Value =
+            serde_yaml::from_str("{ env: \"SUIBASE_TEST_LINK_SECRET\" }").unwrap();
+        let secret = LinkSecret::from_yaml(&env, context).unwrap();
+        assert_eq!(secret.expose(), "from-env");
+
+        let path = std::env::temp_dir().join(format!("link_secret_{}", std::process::id()));
+        std::fs::write(&path, "  from-file\n").unwrap();
+        let file: serde_yaml::Value =
+            serde_yaml::from_str(&format!("{{ file: \"{}\" }}", path.display())).unwrap();
+        let secret = LinkSecret::from_yaml(&file, context).unwrap();
+        assert_eq!(secret.expose(), "from-file");
+        std::fs::remove_file(&path).unwrap();
+
+        let missing: serde_yaml::Value =
+            serde_yaml::from_str("{ env: \"SUIBASE_TEST_NONE\" }").unwrap();
+        assert!(LinkSecret::from_yaml(&missing, context).is_none());
+    }
+}
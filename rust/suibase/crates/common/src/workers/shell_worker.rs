@@ -10,6 +10,7 @@ use tokio::time::{self, Duration};
 
 use anyhow::Result;
 use tokio_graceful_shutdown::{FutureExt, SubsystemHandle};
+use tracing::Instrument;
 
 use crate::basic_types::{GenericChannelMsg, GenericRx, WorkdirIdx};
 use crate::mpsc_q_check;
@@ -189,7 +190,12 @@ impl ShellWorker {
             if let Some(msg) = self.event_rx.recv().await {
                 mpsc_q_check!(self.event_rx);
                 // Process the message.
-                self.do_exec(msg).await;
+                let span = tracing::info_span!(
+                    "shell_exec",
+                    workdir_idx = msg.workdir_idx,
+                    command = msg.command.as_deref().unwrap_or(""),
+                );
+                self.do_exec(msg).instrument(span).await;
             } else {
                 // Channel closed or shutdown requested.
                 return;
@@ -62,6 +62,10 @@ pub struct SubscriptionTracking {
     sender_filter: Option<String>, // Sender address ("0x" string)
     src_addr_filter: Option<String>, // For "src_addr" field in msg.
 
+    // Set for a subscription configured in suibase.yaml (see EventSubscription).
+    label: Option<String>,
+    event_filter: Option<String>, // Sui EventFilter (JSON string).
+
     // State machine.
     state: SubscriptionTrackingState,
 
@@ -105,6 +109,8 @@ impl SubscriptionTracking {
             package_filter: Some(package_id),
             sender_filter: sender_addr,
             src_addr_filter: src_addr,
+            label: None,
+            event_filter: None,
             state: SubscriptionTrackingState::Disconnected,
             state_change_timestamp: Some(now),
             request_sent_timestamp: None,
@@ -134,6 +140,33 @@ impl SubscriptionTracking {
             package_filter: Some(id),
             sender_filter: None,
             src_addr_filter: None,
+            label: None,
+            event_filter: None,
+            state: SubscriptionTrackingState::Disconnected,
+            state_change_timestamp: Some(now),
+            request_sent_timestamp: None,
+            request_retry: 0,
+            unsubscribed_id: None,
+            subscription_number: u64::MAX,
+            subscribe_seq_numbers: Vec::new(),
+            unsubscribe_seq_numbers: Vec::new(),
+            remove_request: false,
+        }
+    }
+
+    // The label is used as the name, and the uuid is derived from it (stable across restarts).
+    pub fn new_for_event_subscription(label: String, event_filter: String) -> Self {
+        let now = tokio::time::Instant::now();
+        Self {
+            name: label.clone(),
+            uuid: format!("subscription:{}", label),
+            timestamp: String::new(),
+            is_managed_package: false,
+            package_filter: None,
+            sender_filter: None,
+            src_addr_filter: None,
+            label: Some(label),
+            event_filter: Some(event_filter),
             state: SubscriptionTrackingState::Disconnected,
             state_change_timestamp: Some(now),
             request_sent_timestamp: None,
@@ -182,6 +215,14 @@ impl SubscriptionTracking {
         self.src_addr_filter.as_ref()
     }
 
+    pub fn label(&self) -> Option<&String> {
+        self.label.as_ref()
+    }
+
+    pub fn event_filter(&self) -> Option<&String> {
+        self.event_filter.as_ref()
+    }
+
     pub fn request_retry(&self) -> u8 {
         self.request_retry
     }
@@ -247,12 +288,13 @@ impl SubscriptionTracking {
             return false; // Nothing to do.
         }
         log::info!(
-            "package_tracking: state change {:?} -> {:?} for filters package {:?} sender {:?} src_addr {:?}",
+            "package_tracking: state change {:?} -> {:?} for filters package {:?} sender {:?} src_addr {:?} event {:?}",
             self.state,
             new_state,
             self.package_filter,
             self.sender_filter,
-            self.src_addr_filter
+            self.src_addr_filter,
+            self.event_filter
         );
         if new_state == SubscriptionTrackingState::Disconnected {
             self.request_sent_timestamp = None;
@@ -0,0 +1,281 @@
+// Child task of a ProxyServer.
+//
+// One instance per InputPort (so one per workdir).
+//
+// Responsible to:
+//  - Append one JSON line per proxied request to ~/suibase/workdirs/<workdir>/logs/proxy-access.jsonl
+//  - Rotate the file (proxy-access.jsonl.1 ... proxy-access.jsonl.N) when it gets too large.
+//
+// Design:
+//  - The proxy_handler (hot path) only does a try_send of a small AccessLogMsg. Nothing is
+//    ever awaited there and the message is silently dropped when the queue is full.
+//  - All the costly work (JSON-RPC method extraction, alias lookup, serialization and
+//    file I/O) is done here.
+//  - The log is enabled/disabled with "proxy_access_log: true" in suibase.yaml. When disabled,
+//    the proxy_handler does not even build an AccessLogMsg.
+//  - The task exits when all AccessLogTx are dropped (that is, when the ProxyServer exits).
+//
+// Example of a line:
+//  {"ts":"2024-05-01T12:00:00.123Z","method":"sui_getObject","server":"sui.io","retry":0,
+//   "latency_ms":35.2,"req_size":170,"resp_size":512,"result":"ok","reason":null}
+use std::path::PathBuf;
+
+use common::basic_types::*;
+use hyper::body::Bytes;
+use serde::Serialize;
+use tokio::io::AsyncWriteExt;
+
+use crate::shared_types::{request_failed_reason_str, GlobalsProxyMT, RequestFailedReason};
+
+const ACCESS_LOG_Q_SIZE: usize = 1000;
+const ACCESS_LOG_FILENAME: &str = "proxy-access.jsonl";
+const ACCESS_LOG_MAX_FILE_SIZE: u64 = 10 * 1024 * 1024; // 10 MiB
+const ACCESS_LOG_MAX_ROTATED_FILES: u32 = 4;
+
+pub type AccessLogTx = tokio::sync::mpsc::Sender<AccessLogMsg>;
+pub type AccessLogRx = tokio::sync::mpsc::Receiver<AccessLogMsg>;
+
+pub fn access_log_channel() -> (AccessLogTx, AccessLogRx) {
+    tokio::sync::mpsc::channel(ACCESS_LOG_Q_SIZE)
+}
+
+// Outcome of a request, in the same terms as the ProxyHandlerReport.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessLogResult {
+    Ok,   // req_resp_ok
+    Err,  // req_resp_err
+    Fail, // req_fail
+}
+
+impl AccessLogResult {
+    fn as_str(&self) -> &'static str {
+        match self {
+            AccessLogResult::Ok => "ok",
+            AccessLogResult::Err => "err",
+            AccessLogResult::Fail => "fail",
+        }
+    }
+}
+
+// What the proxy_handler sends. Keep this cheap to build.
+#[derive(Debug)]
+pub struct AccessLogMsg {
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub server_idx: Option<TargetServerIdx>,
+    pub retry_count: u8,
+    pub latency_micros: u32,
+    pub req_body: Option<Bytes>, // Cheap clone (ref counted). Parsed by the writer.
+    pub resp_size: Option<usize>,
+    pub result: AccessLogResult,
+    pub reason: Option<RequestFailedReason>,
+}
+
+// What gets serialized as one line in the file.
+#[derive(Serialize)]
+struct AccessLogRecord<'a> {
+    ts: String,
+    method: Option<String>,
+    server: Option<String>,
+    retry: u8,
+    latency_ms: f64,
+    req_size: Option<usize>,
+    resp_size: Option<usize>,
+    result: &'a str,
+    reason: Option<&'a str>,
+}
+
+pub struct AccessLogWriter {
+    globals: GlobalsProxyMT,
+    port_idx: InputPortIdx,
+    rx: AccessLogRx,
+    path: PathBuf,
+    file: Option<tokio::io::BufWriter<tokio::fs::File>>,
+    file_size: u64,
+    max_file_size: u64,
+    max_rotated_files: u32,
+}
+
+impl AccessLogWriter {
+    pub fn new(
+        globals: GlobalsProxyMT,
+        port_idx: InputPortIdx,
+        workdir_idx: WorkdirIdx,
+        rx: AccessLogRx,
+    ) -> Self {
+        let path = common::shared_types::get_workdir_paths(workdir_idx)
+            .workdir_root_path()
+            .join("logs")
+            .join(ACCESS_LOG_FILENAME);
+        Self {
+            globals,
+            port_idx,
+            rx,
+            path,
+            file: None,
+            file_size: 0,
+            max_file_size: ACCESS_LOG_MAX_FILE_SIZE,
+            max_rotated_files: ACCESS_LOG_MAX_ROTATED_FILES,
+        }
+    }
+
+    // Extract the JSON-RPC "method" from a request body.
+    //
+    // A batch request is logged as "batch(<n>)".
+    fn extract_method(req_body: &Bytes) -> Option<String> {
+        let json_req = serde_json::from_slice::<serde_json::Value>(req_body).ok()?;
+        if let Some(batch) = json_req.as_array() {
+            return Some(format!("batch({})", batch.len()));
+        }
+        json_req
+            .get("method")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+    }
+
+    async fn open_file(&mut self) -> std::io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        self.file_size = file.metadata().await.map(|m| m.len()).unwrap_or(0);
+        self.file = Some(tokio::io::BufWriter::new(file));
+        Ok(())
+    }
+
+    async fn rotate(&mut self) -> std::io::Result<()> {
+        if let Some(mut file) = self.file.take() {
+            file.flush().await?;
+        }
+
+        // Shift proxy-access.jsonl.N-1 -> .N ... proxy-access.jsonl -> .1
+        // (the oldest is overwritten).
+        let rotated_path = |n: u32| PathBuf::from(format!("{}.{}", self.path.display(), n));
+        for n in (1..self.max_rotated_files).rev() {
+            let from = rotated_path(n);
+            if tokio::fs::metadata(&from).await.is_ok() {
+                tokio::fs::rename(&from, rotated_path(n + 1)).await?;
+            }
+        }
+        tokio::fs::rename(&self.path, rotated_path(1)).await?;
+
+        self.open_file().await
+    }
+
+    async fn write_line(&mut self, line: &[u8]) -> std::io::Result<()> {
+        if self.file.is_none() {
+            self.open_file().await?;
+        } else if self.file_size >= self.max_file_size {
+            self.rotate().await?;
+        }
+        if let Some(file) = &mut self.file {
+            file.write_all(line).await?;
+            file.write_all(b"\n").await?;
+            self.file_size += line.len() as u64 + 1;
+        }
+        Ok(())
+    }
+
+    async fn process_batch(&mut self, batch: Vec<AccessLogMsg>) {
+        // Resolve all the server aliases with a single read lock.
+        let aliases: Vec<Option<String>> = {
+            let globals_read_guard = self.globals.read().await;
+            let globals = &*globals_read_guard;
+            let input_port = globals.input_ports.get(self.port_idx);
+            batch
+                .iter()
+                .map(|msg| {
+                    let server_idx = msg.server_idx?;
+                    let input_port = input_port?;
+                    input_port
+                        .target_servers
+                        .get(server_idx)
+                        .map(|ts| ts.alias())
+                })
+                .collect()
+        };
+
+        for (msg, server) in batch.into_iter().zip(aliases) {
+            let record = AccessLogRecord {
+                ts: msg
+                    .timestamp
+                    .to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+                method: msg.req_body.as_ref().and_then(Self::extract_method),
+                server,
+                retry: msg.retry_count,
+                latency_ms: msg.latency_micros as f64 / 1000.0,
+                req_size: msg.req_body.as_ref().map(|b| b.len()),
+                resp_size: msg.resp_size,
+                result: msg.result.as_str(),
+                reason: msg.reason.map(request_failed_reason_str),
+            };
+            let line = match serde_json::to_vec(&record) {
+                Ok(line) => line,
+                Err(e) => {
+                    log::error!("access log serialization failed: {}", e);
+                    continue;
+                }
+            };
+            if let Err(e) = self.write_line(&line).await {
+                log::error!("access log write to {} failed: {}", self.path.display(), e);
+                // Retry opening the file on next request.
+                self.file = None;
+                return;
+            }
+        }
+
+        if let Some(file) = &mut self.file {
+            if let Err(e) = file.flush().await {
+                log::error!("access log flush of {} failed: {}", self.path.display(), e);
+                self.file = None;
+            }
+        }
+    }
+
+    pub async fn run(mut self) {
+        // Process whatever is available at once (one lock and one flush per batch).
+        let mut batch: Vec<AccessLogMsg> = Vec::new();
+        while self.rx.recv_many(&mut batch, ACCESS_LOG_Q_SIZE).await > 0 {
+            let to_process = std::mem::take(&mut batch);
+            self.process_batch(to_process).await;
+        }
+        // Channel closed (ProxyServer exited).
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_access_log_rotation() {
+        let dir = std::env::temp_dir().join(format!("suibase_access_log_{}", std::process::id()));
+        let (_tx, rx) = access_log_channel();
+        let globals = GlobalsProxyMT::default();
+        let mut writer =
+            AccessLogWriter::new(globals, 0, common::shared_types::WORKDIR_IDX_LOCALNET, rx);
+        writer.path = dir.join(ACCESS_LOG_FILENAME);
+        writer.max_file_size = 100;
+        writer.max_rotated_files = 2;
+
+        // 49 bytes per line (with the newline), so rotated every 3 lines.
+        let line = [b'x'; 48];
+        for _ in 0..12 {
+            writer.write_line(&line).await.unwrap();
+        }
+        writer.file.take().unwrap().flush().await.unwrap();
+
+        let rotated_path = |n: u32| PathBuf::from(format!("{}.{}", writer.path.display(), n));
+        let file_len = |path: &PathBuf| std::fs::metadata(path).unwrap().len();
+        assert_eq!(file_len(&writer.path), 3 * 49);
+        assert_eq!(file_len(&rotated_path(1)), 3 * 49);
+        assert_eq!(file_len(&rotated_path(2)), 3 * 49);
+        // The oldest file is dropped beyond max_rotated_files.
+        assert!(!rotated_path(3).exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
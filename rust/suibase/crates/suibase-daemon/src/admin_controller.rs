@@ -1,7 +1,9 @@
 use std::error::Error;
 use std::time::Duration;
 
-use common::shared_types::{WorkdirUserConfig, WORKDIRS_KEYS, WORKDIR_IDX_LOCALNET};
+use common::shared_types::{
+    TlsConfig, WorkdirUserConfig, WORKDIRS_KEYS, WORKDIR_IDX_DEVNET, WORKDIR_IDX_LOCALNET,
+};
 use common::{basic_types::*, log_safe};
 
 use crate::acoins_monitor::ACoinsMonTx;
@@ -9,12 +11,17 @@ use crate::network_monitor::NetMonTx;
 use crate::proxy_server::ProxyServer;
 use crate::shared_types::{Globals, InputPort};
 use crate::workdirs_watcher::WorkdirsWatcher;
-use crate::workers::{CliPoller, CliPollerParams, PackagesPoller, PackagesPollerParams};
+use crate::workers::{
+    supervised_services, BalancePoller, BalancePollerParams, CliPoller, CliPollerParams,
+    FaucetPoller, FaucetPollerParams, PackagesPoller, PackagesPollerParams, SupervisorPoller,
+    SupervisorPollerParams,
+};
 use common::workers::ShellWorker;
 
 use anyhow::{anyhow, Result};
 
 use tokio_graceful_shutdown::{FutureExt, NestedSubsystem, SubsystemBuilder, SubsystemHandle};
+use tracing::Instrument;
 
 // Design
 //
@@ -62,8 +69,11 @@ struct WorkdirTracking {
 
     packages_poller: Option<PackagesPoller>,
 
-    process_watchdog_last_check_timestamp: Option<tokio::time::Instant>,
-    process_watchdog_last_recovery_timestamp: Option<tokio::time::Instant>,
+    balance_poller: Option<BalancePoller>,
+
+    faucet_poller: Option<FaucetPoller>, // Only for localnet and devnet.
+
+    supervisor_poller: Option<SupervisorPoller>, // Only for workdirs with local services.
 }
 
 impl std::fmt::Debug for WorkdirTracking {
@@ -79,6 +89,8 @@ impl std::fmt::Debug for WorkdirTracking {
 struct InputPortTracking {
     proxy_server_handle: Option<NestedSubsystem<Box<dyn Error + Send + Sync>>>, // Set when the proxy_server is started.
     port_number: u16, // port number used when the proxy_server was started.
+    bind_address: String, // bind address used when the proxy_server was started.
+    tls: TlsConfig,       // TLS settings used when the proxy_server was started.
 }
 
 impl std::fmt::Debug for InputPortTracking {
@@ -86,6 +98,8 @@ impl std::fmt::Debug for InputPortTracking {
         f.debug_struct("WorkdirTracking")
             // NestedSubsystem does not implement Debug
             .field("port_number", &self.port_number)
+            .field("bind_address", &self.bind_address)
+            .field("tls", &self.tls)
             .finish()
     }
 }
@@ -169,6 +183,44 @@ impl AdminController {
         Err(anyhow!("send_shell_exec failed"))
     }
 
+    // Request a command to the events worker of a workdir (e.g. "export_events").
+    //
+    // The data_json is the command parameters. The response is a JSON string.
+    pub async fn send_events_exec(
+        tx_channel: &AdminControllerTx,
+        workdir_idx: WorkdirIdx,
+        command: String,
+        data_json: serde_json::Value,
+    ) -> Result<String> {
+        let mut msg = AdminControllerMsg::new();
+        msg.event_id = EVENT_EVENTS_EXEC;
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        msg.resp_channel = Some(tx);
+        msg.workdir_idx = Some(workdir_idx);
+        msg.data_string = Some(command.clone());
+        msg.data_json = Some(data_json);
+        const TIMEOUT: Duration = Duration::from_secs(3600);
+        if (tx_channel.send(msg).await).is_ok() {
+            match tokio::time::timeout(TIMEOUT, rx).await {
+                Ok(Ok(resp_str)) => {
+                    return Ok(resp_str);
+                }
+                Ok(Err(e)) => {
+                    return Err(anyhow!(
+                        "send_events_exec internal error: {}",
+                        e.to_string()
+                    ));
+                }
+                Err(_) => {
+                    let timeout_err = format!("send_events_exec timeout {}", command);
+                    log::error!("{}", timeout_err);
+                    return Err(anyhow!(timeout_err));
+                }
+            }
+        }
+        Err(anyhow!("send_events_exec failed"))
+    }
+
     async fn process_audit_msg(&mut self, msg: AdminControllerMsg) {
         if msg.event_id != EVENT_AUDIT {
             log::error!("Unexpected event_id {:?}", msg.event_id);
@@ -197,10 +249,10 @@ impl AdminController {
 
             Self::send_msg_to_cli_poller(wd_tracking, worker_msg.clone()).await;
             Self::send_msg_to_packages_poller(wd_tracking, worker_msg.clone()).await;
+            Self::send_msg_to_balance_poller(wd_tracking, worker_msg.clone()).await;
+            Self::send_msg_to_faucet_poller(wd_tracking, worker_msg.clone()).await;
+            Self::send_msg_to_supervisor_poller(wd_tracking, worker_msg.clone()).await;
         }
-
-        // Check for potential need for local process restart/recovery.
-        self.watchdog_local_processes().await;
     }
 
     async fn send_msg_to_cli_poller(wd_tracking: &WorkdirTracking, msg: GenericChannelMsg) {
@@ -235,6 +287,54 @@ impl AdminController {
         }
     }
 
+    async fn send_msg_to_balance_poller(wd_tracking: &WorkdirTracking, msg: GenericChannelMsg) {
+        if let Some(poller) = wd_tracking.balance_poller.as_ref() {
+            let workdir_idx = msg.workdir_idx;
+            let event_id = msg.event_id;
+            match poller.get_tx_channel().try_send(msg) {
+                Ok(()) => {}
+                Err(e) => {
+                    log_safe!(format!(
+                        "try_send event id={:?} to {:?} balance poller failed: {}",
+                        event_id, workdir_idx, e
+                    ));
+                }
+            }
+        }
+    }
+
+    async fn send_msg_to_faucet_poller(wd_tracking: &WorkdirTracking, msg: GenericChannelMsg) {
+        if let Some(poller) = wd_tracking.faucet_poller.as_ref() {
+            let workdir_idx = msg.workdir_idx;
+            let event_id = msg.event_id;
+            match poller.get_tx_channel().try_send(msg) {
+                Ok(()) => {}
+                Err(e) => {
+                    log_safe!(format!(
+                        "try_send event id={:?} to {:?} faucet poller failed: {}",
+                        event_id, workdir_idx, e
+                    ));
+                }
+            }
+        }
+    }
+
+    async fn send_msg_to_supervisor_poller(wd_tracking: &WorkdirTracking, msg: GenericChannelMsg) {
+        if let Some(poller) = wd_tracking.supervisor_poller.as_ref() {
+            let workdir_idx = msg.workdir_idx;
+            let event_id = msg.event_id;
+            match poller.get_tx_channel().try_send(msg) {
+                Ok(()) => {}
+                Err(e) => {
+                    log_safe!(format!(
+                        "try_send event id={:?} to {:?} supervisor poller failed: {}",
+                        event_id, workdir_idx, e
+                    ));
+                }
+            }
+        }
+    }
+
     async fn process_update_msg(&mut self, msg: AdminControllerMsg) {
         if msg.event_id != EVENT_UPDATE {
             log::error!("Unexpected event_id {:?}", msg.event_id);
@@ -255,104 +355,22 @@ impl AdminController {
                 worker_msg.workdir_idx = Some(workdir_idx);
                 Self::send_msg_to_cli_poller(wd_tracking, worker_msg.clone()).await;
                 Self::send_msg_to_packages_poller(wd_tracking, worker_msg.clone()).await;
+                Self::send_msg_to_balance_poller(wd_tracking, worker_msg.clone()).await;
+                Self::send_msg_to_faucet_poller(wd_tracking, worker_msg.clone()).await;
+                Self::send_msg_to_supervisor_poller(wd_tracking, worker_msg.clone()).await;
             }
         } else {
             for (workdir_idx, wd_tracking) in self.wd_tracking.iter() {
                 worker_msg.workdir_idx = Some(workdir_idx);
                 Self::send_msg_to_cli_poller(wd_tracking, worker_msg.clone()).await;
                 Self::send_msg_to_packages_poller(wd_tracking, worker_msg.clone()).await;
+                Self::send_msg_to_balance_poller(wd_tracking, worker_msg.clone()).await;
+                Self::send_msg_to_faucet_poller(wd_tracking, worker_msg.clone()).await;
+                Self::send_msg_to_supervisor_poller(wd_tracking, worker_msg.clone()).await;
             }
         }
     }
 
-    async fn watchdog_local_processes(&mut self) {
-        let (last_check_timestamp, last_recovery_timestamp) = {
-            if let Some(workdir_tracking) = self.wd_tracking.get_if_some(WORKDIR_IDX_LOCALNET) {
-                (
-                    workdir_tracking.process_watchdog_last_check_timestamp,
-                    workdir_tracking.process_watchdog_last_recovery_timestamp,
-                )
-            } else {
-                return;
-            }
-        };
-
-        // Prevent burst of checks.
-        if let Some(last_check_timestamp) = last_check_timestamp {
-            if last_check_timestamp.elapsed().as_secs() < 5 {
-                return;
-            }
-        }
-
-        // Using read access to globals, detect if a local processes need to be re-started.
-        //
-        // Uses the services status stored in the localnet workdir globals.
-        //
-        // If the status is not "OK", and one of the service is shown as "NOT RUNNING" then
-        // call "localnet start" to attempt recovery.
-        let need_restart = {
-            let globals_guard = self.globals.get_status(WORKDIR_IDX_LOCALNET).read().await;
-            let globals = &*globals_guard;
-            let mut need_restart = false;
-            if let Some(ui) = &globals.ui {
-                let ui = ui.get_data();
-                if let Some(status) = &ui.status {
-                    if status != "OK" {
-                        if let Some(services) = &ui.services {
-                            for service in services {
-                                if let Some(service_status) = &service.status {
-                                    if (service.label == "Localnet process"
-                                        || service.label == "Faucet process")
-                                        && service_status == "NOT RUNNING"
-                                    {
-                                        need_restart = true;
-                                        break;
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-
-            need_restart
-        };
-
-        let mut update_recovery_timestamp = false;
-        if need_restart {
-            // Do nothing if last recovery attempt was less than 2 minutes ago.
-            let mut send_restart_msg = true;
-
-            if let Some(last_recovery_timestamp) = last_recovery_timestamp {
-                if last_recovery_timestamp.elapsed().as_secs() < 120 {
-                    send_restart_msg = false;
-                }
-            }
-
-            // Restart the localnet service.
-            if send_restart_msg {
-                let mut msg = AdminControllerMsg::new();
-                msg.event_id = EVENT_SHELL_EXEC;
-                msg.workdir_idx = Some(WORKDIR_IDX_LOCALNET);
-                msg.data_string = Some("localnet start --daemoncall".to_string());
-                if let Err(e) = self.admctrl_tx.try_send(msg) {
-                    log_safe!(format!(
-                        "try_send EVENT_SHELL_EXEC localnet start failed: {}",
-                        e
-                    ));
-                }
-                update_recovery_timestamp = true;
-            }
-        }
-
-        let workdir_tracking = self.wd_tracking.get_mut(WORKDIR_IDX_LOCALNET);
-        let now = tokio::time::Instant::now();
-        if update_recovery_timestamp {
-            workdir_tracking.process_watchdog_last_recovery_timestamp = Some(now);
-        }
-        workdir_tracking.process_watchdog_last_check_timestamp = Some(now);
-    }
-
     pub async fn send_event_post_publish(
         tx_channel: &AdminControllerTx,
         workdir_idx: WorkdirIdx,
@@ -450,6 +468,46 @@ impl AdminController {
         }
     }
 
+    async fn process_events_exec_msg(&mut self, msg: AdminControllerMsg) {
+        // Simply forward to the events worker of the workdir.
+        if msg.event_id != EVENT_EVENTS_EXEC {
+            log::error!("Unexpected event_id {:?}", msg.event_id);
+            // Do nothing. Consume the message.
+            return;
+        }
+
+        let workdir_idx = match msg.workdir_idx {
+            Some(workdir_idx) => workdir_idx,
+            None => {
+                log::error!("EVENT_EVENTS_EXEC missing workdir_idx");
+                return;
+            }
+        };
+
+        let wd_tracking = self.wd_tracking.get_mut(workdir_idx);
+        let worker_tx = match wd_tracking.events_worker_tx.as_ref() {
+            Some(worker_tx) => worker_tx,
+            None => {
+                if let Some(resp_channel) = msg.resp_channel {
+                    let resp = serde_json::json!({ "error": "events worker not running" });
+                    let _ = resp_channel.send(resp.to_string());
+                }
+                return;
+            }
+        };
+
+        let mut worker_msg = GenericChannelMsg::new();
+        worker_msg.event_id = EVENT_EXEC;
+        worker_msg.command = msg.data_string;
+        worker_msg.data_json = msg.data_json;
+        worker_msg.workdir_idx = msg.workdir_idx;
+        worker_msg.resp_channel = msg.resp_channel;
+        if let Err(e) = worker_tx.try_send(worker_msg) {
+            let err_msg = format!("try_send EVENT_EVENTS_EXEC to worker failed: {}", e);
+            log_safe!(err_msg);
+        }
+    }
+
     async fn process_debug_print_msg(&mut self, msg: AdminControllerMsg) {
         // Send a response to the return channel with the debug print of a few
         // relevant internal states, particularly the configuration tracking.
@@ -474,6 +532,10 @@ impl AdminController {
             input_port.set_proxy_enabled(workdir_config.is_proxy_enabled());
             at_least_one_change = true;
         }
+        if input_port.is_access_log_enabled() != workdir_config.is_proxy_access_log_enabled() {
+            input_port.set_access_log_enabled(workdir_config.is_proxy_access_log_enabled());
+            at_least_one_change = true;
+        }
         if input_port.is_user_request_start() != workdir_config.is_user_request_start() {
             input_port.set_user_request_start(workdir_config.is_user_request_start());
             at_least_one_change = true;
@@ -653,6 +715,8 @@ impl AdminController {
 
                 port_tracking.proxy_server_handle = Some(nested);
                 port_tracking.port_number = port_number;
+                port_tracking.bind_address = workdir_config.proxy_bind_address().to_string();
+                port_tracking.tls = workdir_config.proxy_tls().clone();
             } else {
                 // Monitor a port number change. This is a rare "fundamental" configuration change that
                 // is simpler to handle by exiting the process (and let it be restarted automatically
@@ -669,6 +733,16 @@ impl AdminController {
                     // Sleep a bit in case of a "restart loop" bug.
                     tokio::time::sleep(std::time::Duration::from_secs(4)).await;
                     subsys.request_shutdown();
+                } else if workdir_config.proxy_bind_address() != port_tracking.bind_address
+                    || workdir_config.proxy_tls() != &port_tracking.tls
+                {
+                    // Same reasoning for the listening address and TLS settings.
+                    log::info!(
+                        "Port {} listening settings changed (bind address or TLS)",
+                        port_number
+                    );
+                    tokio::time::sleep(std::time::Duration::from_secs(4)).await;
+                    subsys.request_shutdown();
                 }
             }
         }
@@ -682,29 +756,17 @@ impl AdminController {
             // Wait for a message.
             if let Some(msg) = self.admctrl_rx.recv().await {
                 common::mpsc_q_check!(self.admctrl_rx);
-                match msg.event_id {
-                    EVENT_AUDIT => {
-                        self.process_audit_msg(msg).await;
-                    }
-                    EVENT_DEBUG_PRINT => {
-                        self.process_debug_print_msg(msg).await;
-                    }
-                    EVENT_NOTIF_CONFIG_FILE_CHANGE => {
-                        self.process_config_msg(msg, subsys).await;
-                    }
-                    EVENT_SHELL_EXEC => {
-                        self.process_shell_exec_msg(msg, subsys).await;
-                    }
-                    EVENT_UPDATE => {
-                        self.process_update_msg(msg).await;
-                    }
-                    EVENT_POST_PUBLISH => {
-                        self.process_post_publish_msg(msg).await;
-                    }
-                    _ => {
-                        log::error!("Unknown event_id {}", msg.event_id);
-                    }
-                }
+                // Periodic audits are too frequent to be worth tracing.
+                let span = if msg.event_id == EVENT_AUDIT {
+                    tracing::Span::none()
+                } else {
+                    tracing::info_span!(
+                        "admctrl_msg",
+                        event_id = msg.event_id,
+                        workdir_idx = msg.workdir_idx,
+                    )
+                };
+                self.process_msg(msg, subsys).instrument(span).await;
             } else {
                 // Channel closed or shutdown requested.
                 return;
@@ -712,6 +774,35 @@ impl AdminController {
         }
     }
 
+    async fn process_msg(&mut self, msg: AdminControllerMsg, subsys: &SubsystemHandle) {
+        match msg.event_id {
+            EVENT_AUDIT => {
+                self.process_audit_msg(msg).await;
+            }
+            EVENT_DEBUG_PRINT => {
+                self.process_debug_print_msg(msg).await;
+            }
+            EVENT_NOTIF_CONFIG_FILE_CHANGE => {
+                self.process_config_msg(msg, subsys).await;
+            }
+            EVENT_SHELL_EXEC => {
+                self.process_shell_exec_msg(msg, subsys).await;
+            }
+            EVENT_UPDATE => {
+                self.process_update_msg(msg).await;
+            }
+            EVENT_POST_PUBLISH => {
+                self.process_post_publish_msg(msg).await;
+            }
+            EVENT_EVENTS_EXEC => {
+                self.process_events_exec_msg(msg).await;
+            }
+            _ => {
+                log::error!("Unknown event_id {}", msg.event_id);
+            }
+        }
+    }
+
     pub async fn run(mut self, subsys: SubsystemHandle) -> Result<()> {
         // This is the "master" thread that controls the changes to the
         // configuration. It is responsible to start/stop other subsystems.
@@ -777,6 +868,39 @@ impl AdminController {
                 let poller = PackagesPoller::new(params, &subsys);
                 wd_tracking.packages_poller = Some(poller);
             }
+
+            // Start a balance poller.
+            if wd_tracking.balance_poller.is_none() {
+                let params = BalancePollerParams::new(self.globals.clone(), workdir_idx);
+                let poller = BalancePoller::new(params, &subsys);
+                wd_tracking.balance_poller = Some(poller);
+            }
+
+            // Start a faucet poller (auto-refill of faucet_addresses).
+            if wd_tracking.faucet_poller.is_none()
+                && (workdir_idx == WORKDIR_IDX_LOCALNET || workdir_idx == WORKDIR_IDX_DEVNET)
+            {
+                let params = FaucetPollerParams::new(
+                    self.globals.clone(),
+                    self.admctrl_tx.clone(),
+                    workdir_idx,
+                );
+                let poller = FaucetPoller::new(params, &subsys);
+                wd_tracking.faucet_poller = Some(poller);
+            }
+
+            // Start a supervisor poller (restart of the local services).
+            if wd_tracking.supervisor_poller.is_none()
+                && !supervised_services(workdir_idx).is_empty()
+            {
+                let params = SupervisorPollerParams::new(
+                    self.globals.clone(),
+                    self.admctrl_tx.clone(),
+                    workdir_idx,
+                );
+                let poller = SupervisorPoller::new(params, &subsys);
+                wd_tracking.supervisor_poller = Some(poller);
+            }
         }
 
         match self.event_loop(&subsys).cancel_on_shutdown(&subsys).await {
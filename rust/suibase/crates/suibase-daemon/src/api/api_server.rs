@@ -7,6 +7,10 @@
 //
 // A JSONRPCServer owns a jsonrpsee Server to handle the JSON-RPC requests.
 // ( https://github.com/paritytech/jsonrpsee )
+//
+// The bind address and optional TLS are configured in ~/suibase/workdirs/common/suibase.yaml
+// (see ApiUserConfig). With TLS, the connections are accepted here and each is served
+// by a jsonrpsee tower service.
 
 use axum::async_trait;
 
@@ -19,6 +23,7 @@ use crate::shared_types::Globals;
 use common::{
     basic_types::{AdminControllerTx, AutoThread, Runnable},
     log_safe,
+    shared_types::{get_workdir_common_path, ApiUserConfig, SUIBASE_API_PORT},
 };
 
 use super::GeneralApiServer;
@@ -30,8 +35,12 @@ use crate::api::impl_proxy_api::ProxyApiImpl;
 use super::PackagesApiServer;
 use crate::api::impl_packages_api::PackagesApiImpl;
 
-use jsonrpsee::{core::server::Methods, server::ServerBuilder};
+use jsonrpsee::{
+    core::server::Methods,
+    server::{serve_with_graceful_shutdown, stop_channel, ServerBuilder},
+};
 use std::net::SocketAddr;
+use std::sync::Arc;
 use tower_http::cors::AllowOrigin;
 
 #[derive(Clone)]
@@ -105,10 +114,12 @@ impl APIServerThread {
 
         let service = ServiceBuilder::new().layer(cors);
 
-        let server = ServerBuilder::default()
-            .set_http_middleware(service)
-            .build(SocketAddr::from(([127, 0, 0, 1], 44399)))
-            .await?;
+        // Optional, so no error if does not exists.
+        let mut api_config = ApiUserConfig::new();
+        let _ = api_config.load_and_merge_from_file(&get_workdir_common_path().join("suibase.yaml"));
+
+        let bind_ip = crate::tls::resolve_bind_ip(api_config.bind_address())?;
+        let bind_address = SocketAddr::new(bind_ip, SUIBASE_API_PORT);
 
         let mut all_methods = Methods::new();
 
@@ -141,9 +152,65 @@ impl APIServerThread {
             }
         }
 
+        if api_config.tls().enabled {
+            let self_signed_dir = get_workdir_common_path().join("tls");
+            let rustls_config =
+                crate::tls::load_server_config(api_config.tls(), &self_signed_dir, bind_ip)?;
+            log::info!("listening on {} (TLS)", bind_address);
+            return Self::serve_tls(bind_address, rustls_config, service, all_methods).await;
+        }
+
+        let server = ServerBuilder::default()
+            .set_http_middleware(service)
+            .build(bind_address)
+            .await?;
+
         let handle = server.start(all_methods);
         handle.stopped().await;
 
         Ok(())
     }
+
+    async fn serve_tls(
+        bind_address: SocketAddr,
+        rustls_config: Arc<rustls::ServerConfig>,
+        service: ServiceBuilder<
+            tower::layer::util::Stack<tower_http::cors::CorsLayer, tower::layer::util::Identity>,
+        >,
+        all_methods: Methods,
+    ) -> Result<()> {
+        let listener = tokio::net::TcpListener::bind(bind_address).await?;
+        let acceptor = tokio_rustls::TlsAcceptor::from(rustls_config);
+        let (stop_handle, _server_handle) = stop_channel();
+        let svc_builder = ServerBuilder::default()
+            .set_http_middleware(service)
+            .to_service_builder();
+
+        loop {
+            let (socket, remote_addr) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    log::warn!("accept failed: {}", e);
+                    continue;
+                }
+            };
+            let acceptor = acceptor.clone();
+            let svc = svc_builder.build(all_methods.clone(), stop_handle.clone());
+            let stopped = stop_handle.clone().shutdown();
+
+            tokio::spawn(async move {
+                match acceptor.accept(socket).await {
+                    Ok(tls_stream) => {
+                        if let Err(e) = serve_with_graceful_shutdown(tls_stream, svc, stopped).await
+                        {
+                            log::debug!("connection from {} ended: {}", remote_addr, e);
+                        }
+                    }
+                    Err(e) => {
+                        log::debug!("TLS handshake with {} failed: {}", remote_addr, e);
+                    }
+                }
+            });
+        }
+    }
 }
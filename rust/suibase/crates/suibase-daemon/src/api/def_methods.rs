@@ -124,6 +124,68 @@ impl LinksResponse {
     }
 }
 
+#[serde_as]
+#[derive(Clone, Default, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct MethodStatsInfo {
+    // The JSON-RPC method (e.g. "sui_getObject").
+    //
+    // Special values are "batch" (all batch requests), "unknown" (not a JSON-RPC
+    // request) and "other" (too many distinct methods).
+    pub method: String,
+
+    // Each request counted only once, even when retried.
+    pub calls: u64,
+    pub errors: u64,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub resp_time: String, // Average, in milliseconds.
+
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub resp_time_max: String, // In milliseconds.
+}
+
+impl MethodStatsInfo {
+    pub fn new(method: String) -> Self {
+        MethodStatsInfo {
+            method,
+            ..Default::default()
+        }
+    }
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct MethodStatsResponse {
+    pub header: Header,
+
+    // Sorted by number of calls (most called first).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub methods: Option<Vec<MethodStatsInfo>>,
+
+    // This is the output when the option 'display' is true.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display: Option<String>,
+
+    // This is the output when the option 'debug' is true.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub debug: Option<String>,
+}
+
+impl MethodStatsResponse {
+    pub fn new() -> Self {
+        Self {
+            header: Header::default(),
+            methods: None,
+            display: None,
+            debug: None,
+        }
+    }
+}
+
 #[serde_as]
 #[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
@@ -150,6 +212,10 @@ pub struct StatusService {
     pub status_info: Option<String>, // Info related to status.
     pub help_info: Option<String>, // Short help info (e.g. the faucet URL)
     pub pid: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<u64>, // Process start (Unix timestamp in seconds). Uptime is from now.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
 }
 
 impl StatusService {
@@ -160,6 +226,8 @@ impl StatusService {
             status_info: None,
             help_info: None,
             pid: None,
+            started_at: None,
+            version: None,
         }
     }
 }
@@ -185,6 +253,10 @@ pub struct WorkdirStatusResponse {
     // Finer grain status for each process/feature/service.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub services: Option<Vec<StatusService>>,
+
+    // Addresses below low_gas_threshold_mist (see getAddressBalances). Does not affect the status.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub low_gas_warning: Option<String>,
 }
 
 impl WorkdirStatusResponse {
@@ -196,6 +268,7 @@ impl WorkdirStatusResponse {
             client_version: None,
             network_version: None,
             services: None,
+            low_gas_warning: None,
         }
     }
 }
@@ -214,6 +287,147 @@ impl VersionedEq for WorkdirStatusResponse {
             && self.client_version == other.client_version
             && self.network_version == other.network_version
             && self.services == other.services
+            && self.low_gas_warning == other.low_gas_warning
+    }
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CoinBalance {
+    pub coin_type: String,
+    pub balance: String, // u128 as a string (a JSON number would lose precision).
+    pub object_count: u64,
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AddressBalance {
+    pub address: String, // Hexa (with 0x)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>, // From the keystore aliases.
+    pub active: bool,    // Active address of the client.
+    pub sui_balance: String, // MIST
+    pub coins: Vec<CoinBalance>, // All coin types, including SUI.
+    pub owned_objects: u64,
+    pub owned_objects_more: bool, // When owned_objects is a lower bound (too many to count).
+    pub low_gas: bool, // Checked only for the active address and the configured addresses.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>, // When the last query failed (the values are from the prior one).
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AddressBalancesResponse {
+    pub header: Header,
+    pub addresses: Vec<AddressBalance>,
+    pub low_gas_threshold: u64, // MIST. Zero when disabled.
+}
+
+impl AddressBalancesResponse {
+    pub fn new() -> Self {
+        Self {
+            header: Header::default(),
+            addresses: Vec::new(),
+            low_gas_threshold: 0,
+        }
+    }
+}
+
+impl Default for AddressBalancesResponse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VersionedEq for AddressBalancesResponse {
+    fn versioned_eq(&self, other: &Self) -> bool {
+        // Purposely do not include header in the comparison.
+        self.addresses == other.addresses && self.low_gas_threshold == other.low_gas_threshold
+    }
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FundsResponse {
+    pub header: Header,
+    pub address: String,
+    pub source: String, // "faucet" or the funder address.
+    pub amount: u64,    // MIST transferred.
+    pub tx_digests: Vec<String>,
+}
+
+impl FundsResponse {
+    pub fn new() -> Self {
+        Self {
+            header: Header::default(),
+            address: String::new(),
+            source: String::new(),
+            amount: 0,
+            tx_digests: Vec::new(),
+        }
+    }
+}
+
+impl Default for FundsResponse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SupervisedService {
+    pub label: String,
+    pub state: String, // RUNNING, DOWN, BACKOFF, CRASH LOOP or STOPPED
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u64>,
+    pub consecutive_failures: u32,
+    pub restarts_in_window: u32, // Restarts within the crash loop detection window.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_restart_in_secs: Option<u64>,
+    pub output: Vec<String>, // Latest log and restart command output lines.
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceRestart {
+    pub timestamp: u64, // Unix time in seconds.
+    pub label: String,
+    pub reason: String,
+    pub outcome: String,
+    pub crash_loop: bool,
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ServicesHistoryResponse {
+    pub header: Header,
+    pub restart_policy: String,
+    pub services: Vec<SupervisedService>,
+    pub restarts: Vec<ServiceRestart>, // Most recent first.
+}
+
+impl ServicesHistoryResponse {
+    pub fn new() -> Self {
+        Self {
+            header: Header::default(),
+            restart_policy: String::new(),
+            services: Vec::new(),
+            restarts: Vec::new(),
+        }
+    }
+}
+
+impl Default for ServicesHistoryResponse {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -250,6 +464,66 @@ impl Default for WorkdirSuiEventsResponse {
     }
 }
 
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsoleLogResponse {
+    pub header: Header,
+
+    // Most recent lines of the console log file, oldest first.
+    pub lines: Vec<String>,
+}
+
+impl ConsoleLogResponse {
+    pub fn new() -> Self {
+        Self {
+            header: Header::default(),
+            lines: Vec::new(),
+        }
+    }
+}
+
+impl Default for ConsoleLogResponse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct WaitForEventResponse {
+    pub header: Header,
+
+    // Cursor of this event. Use as after_id to wait for a following event.
+    pub id: i64,
+    pub timestamp_ms: i64,
+    pub package_name: String,
+    pub package_id: String,
+
+    // The Sui event (type, sender, parsedJson...).
+    pub event: serde_json::Value,
+}
+
+impl WaitForEventResponse {
+    pub fn new() -> Self {
+        Self {
+            header: Header::default(),
+            id: 0,
+            timestamp_ms: 0,
+            package_name: String::new(),
+            package_id: String::new(),
+            event: serde_json::Value::Null,
+        }
+    }
+}
+
+impl Default for WaitForEventResponse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[serde_as]
 #[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
@@ -289,6 +563,17 @@ pub struct SuiObjectType {
     name: String,
 }
 
+// Split "[package::]module::name[<...>]" into (package, module, name).
+fn split_object_type(object_type: &str) -> Option<(Option<&str>, &str, &str)> {
+    let base = object_type.split('<').next().unwrap_or_default();
+    let names: Vec<&str> = base.split("::").map(str::trim).collect();
+    match names.as_slice() {
+        [module, name] => Some((None, *module, *name)),
+        [package, module, name] => Some((Some(*package), *module, *name)),
+        _ => None,
+    }
+}
+
 impl SuiObjectType {
     pub fn new(package_id: Option<String>, module: String, name: String) -> Self {
         Self {
@@ -297,6 +582,16 @@ impl SuiObjectType {
             name,
         }
     }
+
+    // Fully qualified type. self_package_id is the package that created the object.
+    pub fn to_full_type(&self, self_package_id: &str) -> String {
+        format!(
+            "0x{}::{}::{}",
+            self.package_id.as_deref().unwrap_or(self_package_id),
+            self.module,
+            self.name
+        )
+    }
 }
 
 #[serde_as]
@@ -320,6 +615,202 @@ impl SuiObjectInstance {
     pub fn object_id(&self) -> &str {
         &self.object_id
     }
+
+    pub fn object_type(&self) -> Option<&SuiObjectType> {
+        self.object_type.as_ref()
+    }
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PublishedObject {
+    pub object_id: String, // Hexa (with 0x)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub object_type: Option<String>, // "0x<package_id>::module::name"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package_id: Option<String>, // Hexa (with 0x)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PublishedObjectsResponse {
+    pub header: Header,
+    pub objects: Vec<PublishedObject>,
+}
+
+impl PublishedObjectsResponse {
+    pub fn new() -> Self {
+        Self {
+            header: Header::default(),
+            objects: Vec::new(),
+        }
+    }
+}
+
+impl Default for PublishedObjectsResponse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Deployment {
+    pub workdir: String,
+    pub package_id: String, // Hexa (with 0x)
+    pub timestamp: String,  // Publication. Epoch in milliseconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub digest: Option<String>,
+    // Nearest prior network in the promotion order with the same bytecode.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub promoted_from: Option<String>,
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ModuleDiff {
+    pub module: String,
+    // Key is the workdir. None when the module is not in that deployment.
+    pub digests: BTreeMap<String, Option<String>>,
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageDeployments {
+    pub package_uuid: String,
+    pub package_name: String,
+    // Most recent publication on every network, in the promotion order
+    // (localnet, devnet, testnet then mainnet).
+    pub deployments: Vec<Deployment>,
+    // Only the modules with a different bytecode between the deployments.
+    pub module_diffs: Vec<ModuleDiff>,
+}
+
+impl PackageDeployments {
+    // instances are the (workdir, most recent PackageInstance), in the promotion order.
+    pub fn from_instances(package_uuid: String, instances: &[(String, &PackageInstance)]) -> Self {
+        let package_name = instances
+            .first()
+            .map(|(_, instance)| instance.name.clone())
+            .unwrap_or_default();
+
+        let mut deployments: Vec<Deployment> = Vec::new();
+        for (i, (workdir, instance)) in instances.iter().enumerate() {
+            let promoted_from = instance.digest.as_ref().and_then(|digest| {
+                instances[..i]
+                    .iter()
+                    .rev()
+                    .find(|(_, prior)| prior.digest.as_ref() == Some(digest))
+                    .map(|(prior_workdir, _)| prior_workdir.clone())
+            });
+            deployments.push(Deployment {
+                workdir: workdir.clone(),
+                package_id: format!("0x{}", instance.pid),
+                timestamp: instance.ts.clone(),
+                version: instance.version,
+                commit: instance.commit.clone(),
+                digest: instance.digest.clone(),
+                promoted_from,
+            });
+        }
+
+        // Compare only the deployments with known module digests.
+        let with_digests: Vec<&(String, &PackageInstance)> = instances
+            .iter()
+            .filter(|(_, instance)| !instance.module_digests.is_empty())
+            .collect();
+        let mut modules: Vec<&String> = with_digests
+            .iter()
+            .flat_map(|(_, instance)| instance.module_digests.keys())
+            .collect();
+        modules.sort();
+        modules.dedup();
+        let mut module_diffs = Vec::new();
+        for module in modules {
+            let digests: BTreeMap<String, Option<String>> = with_digests
+                .iter()
+                .map(|(workdir, instance)| {
+                    (
+                        workdir.clone(),
+                        instance.module_digests.get(module).cloned(),
+                    )
+                })
+                .collect();
+            let first = digests.values().next();
+            if digests.values().any(|digest| Some(digest) != first) {
+                module_diffs.push(ModuleDiff {
+                    module: module.clone(),
+                    digests,
+                });
+            }
+        }
+
+        Self {
+            package_uuid,
+            package_name,
+            deployments,
+            module_diffs,
+        }
+    }
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct DeploymentsResponse {
+    pub header: Header,
+    pub packages: Vec<PackageDeployments>,
+}
+
+impl DeploymentsResponse {
+    pub fn new() -> Self {
+        Self {
+            header: Header::default(),
+            packages: Vec::new(),
+        }
+    }
+}
+
+impl Default for DeploymentsResponse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ObjectAliasesResponse {
+    pub header: Header,
+    pub aliases: BTreeMap<String, String>, // Key is the alias, value is the object id.
+}
+
+impl ObjectAliasesResponse {
+    pub fn new() -> Self {
+        Self {
+            header: Header::default(),
+            aliases: BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for ObjectAliasesResponse {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 /*
@@ -340,6 +831,49 @@ mod package_path_serializer {
     }
 }*/
 
+// Gas cost of a publication (MIST).
+#[serde_as]
+#[derive(Clone, Debug, Default, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PublishGas {
+    pub computation_cost: u64,
+    pub storage_cost: u64,
+    pub storage_rebate: u64,
+    pub non_refundable_storage_fee: u64,
+    pub total: i64, // computation_cost + storage_cost - storage_rebate
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ModuleSize {
+    pub name: String,
+    pub size: u64, // Bytes of the compiled module.
+}
+
+#[serde_as]
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PublishDependency {
+    pub package_id: String, // Hexa (with 0x)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>, // When a framework or a package of this workdir.
+}
+
+// Analysis of the publish-output.json and build of a publication.
+#[serde_as]
+#[derive(Clone, Debug, Default, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PublishReport {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tx_digest: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas: Option<PublishGas>,
+    pub modules: Vec<ModuleSize>,
+    pub total_size: u64,
+    pub dependencies: Vec<PublishDependency>,
+}
+
 #[serde_as]
 #[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
@@ -351,6 +885,34 @@ pub struct PackageInstance {
     owner: Option<String>, // Hexa account address (no 0x)
     init: Option<Vec<SuiObjectInstance>>,
 
+    // Upgrade lineage (see update_upgrade_lineage). All the publications sharing
+    // the same UpgradeCap are versions of the same package.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    upgrade_cap: Option<String>, // Object ID. Hexa (no 0x)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<u64>, // 1 for the original publication.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    policy: Option<String>, // "compatible", "additive", "dep_only" or "immutable"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    original_pid: Option<String>, // Only for an upgrade. Hexa (no 0x)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    previous_pid: Option<String>, // Only for an upgrade. Hexa (no 0x)
+
+    // Source and bytecode identification (see getDeployments).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    commit: Option<String>, // git commit of the Move.toml directory. "-dirty" suffix for local changes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    digest: Option<String>, // sha256 of all the module digests.
+    #[serde(skip)]
+    module_digests: BTreeMap<String, String>, // Key is the module name.
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    publish: Option<PublishReport>,
+
+    // Tagged instances are kept regardless of the retention (see packages_keep_last).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+
     #[serde(skip)]
     package_path: PackagePath, // Conveniently contains the UUID.
 }
@@ -370,12 +932,82 @@ impl PackageInstance {
             ts: package_path.get_package_timestamp().to_string(),
             owner: None,
             init: None,
+            upgrade_cap: None,
+            version: None,
+            policy: None,
+            original_pid: None,
+            previous_pid: None,
+            commit: None,
+            digest: None,
+            module_digests: BTreeMap::new(),
+            publish: None,
+            tags: Vec::new(),
             package_path,
         }
     }
 
+    pub fn set_upgrade_info(
+        &mut self,
+        upgrade_cap: Option<String>,
+        version: Option<u64>,
+        policy: Option<String>,
+    ) {
+        self.upgrade_cap = upgrade_cap.map(|id| id.trim_start_matches("0x").to_string());
+        self.version = version;
+        self.policy = policy;
+    }
+
+    pub fn get_upgrade_cap(&self) -> Option<&str> {
+        self.upgrade_cap.as_deref()
+    }
+
+    pub fn set_source_commit(&mut self, commit: String) {
+        self.commit = Some(commit);
+    }
+
+    pub fn set_bytecode_digests(
+        &mut self,
+        digest: String,
+        module_digests: BTreeMap<String, String>,
+    ) {
+        self.digest = Some(digest);
+        self.module_digests = module_digests;
+    }
+
+    pub fn get_version(&self) -> Option<u64> {
+        self.version
+    }
+
+    pub fn get_commit(&self) -> Option<&str> {
+        self.commit.as_deref()
+    }
+
+    pub fn get_digest(&self) -> Option<&str> {
+        self.digest.as_deref()
+    }
+
+    pub fn get_module_digests(&self) -> &BTreeMap<String, String> {
+        &self.module_digests
+    }
+
+    pub fn set_publish_report(&mut self, publish: PublishReport) {
+        self.publish = Some(publish);
+    }
+
+    pub fn get_publish_report(&self) -> Option<&PublishReport> {
+        self.publish.as_ref()
+    }
+
+    pub fn set_tags(&mut self, tags: Vec<String>) {
+        self.tags = tags;
+    }
+
+    pub fn get_tags(&self) -> &[String] {
+        &self.tags
+    }
+
     pub fn set_package_owner(&mut self, package_owner: String) {
-        self.owner = Some(package_owner);
+        self.owner = Some(package_owner.trim_start_matches("0x").to_string());
     }
 
     pub fn set_init_objects(&mut self, init_objects: Vec<SuiObjectInstance>) {
@@ -534,6 +1166,13 @@ impl WorkdirPackagesResponse {
         })
     }
 
+    // Create an iterator of all PackageInstance in the WorkdirPackagesResponse.
+    pub fn iter_package_instances(&self) -> impl Iterator<Item = &PackageInstance> {
+        self.move_configs
+            .iter()
+            .flat_map(|(_, move_config)| move_config.packages.values())
+    }
+
     // Create an iterator of *most recent* PackageInstance for every UUID.
     pub fn iter_most_recent_package_instance(&self) -> impl Iterator<Item = &PackageInstance> {
         self.move_configs.iter().filter_map(|(_, move_config)| {
@@ -545,6 +1184,19 @@ impl WorkdirPackagesResponse {
         })
     }
 
+    // Same as iter_most_recent_package_instance, with the UUID.
+    pub fn iter_most_recent_package_instance_by_uuid(
+        &self,
+    ) -> impl Iterator<Item = (&String, &PackageInstance)> {
+        self.move_configs.iter().filter_map(|(uuid, move_config)| {
+            move_config
+                .packages
+                .values()
+                .next()
+                .map(|package_instance| (uuid, package_instance))
+        })
+    }
+
     pub fn iter_mut_most_recent_package_instance(
         &mut self,
     ) -> impl Iterator<Item = &mut PackageInstance> {
@@ -557,6 +1209,14 @@ impl WorkdirPackagesResponse {
         })
     }
 
+    pub fn find_mut_package_instance(&mut self, package_id: &str) -> Option<&mut PackageInstance> {
+        let package_id = package_id.trim_start_matches("0x");
+        self.move_configs
+            .values_mut()
+            .flat_map(|move_config| move_config.packages.values_mut())
+            .find(|package_instance| package_instance.pid == package_id)
+    }
+
     // Returns true if a change was performed.
     pub fn delete_package_instance(&mut self, package_path: &PackagePath) -> bool {
         if let Some(move_config) = self.move_configs.get_mut(package_path.get_package_uuid()) {
@@ -599,6 +1259,141 @@ impl WorkdirPackagesResponse {
         }
     }
 
+    // Objects created by the most recent publication of every package (the same lookup
+    // as the Rust Helper::published_new_object_ids).
+    //
+    // object_type is one of:
+    //   "module::name"                   (any package)
+    //   "<package_name>::module::name"
+    //   "0x<package_id>::module::name"
+    //
+    // Type parameters (e.g. "pool::Pool<0x2::sui::SUI>") are ignored.
+    pub fn find_init_objects(&self, object_type: &str) -> Vec<PublishedObject> {
+        let (package, module, name) = match split_object_type(object_type) {
+            Some(split) => split,
+            None => return Vec::new(),
+        };
+
+        let mut objects = Vec::new();
+        for package_instance in self.iter_most_recent_package_instance() {
+            let init_objects = match &package_instance.init {
+                Some(init_objects) => init_objects,
+                None => continue,
+            };
+            for init_object in init_objects {
+                let sui_object_type = match &init_object.object_type {
+                    Some(sui_object_type) => sui_object_type,
+                    None => continue,
+                };
+                if sui_object_type.module != module || sui_object_type.name != name {
+                    continue;
+                }
+                let type_package_id = sui_object_type
+                    .package_id
+                    .as_deref()
+                    .unwrap_or(&package_instance.pid);
+                let package_matches = match package {
+                    None => true,
+                    Some(package) if package.starts_with("0x") => {
+                        package.trim_start_matches("0x") == type_package_id
+                    }
+                    Some(package) => package == package_instance.name,
+                };
+                if package_matches {
+                    objects.push(PublishedObject {
+                        object_id: init_object.object_id.clone(),
+                        object_type: Some(sui_object_type.to_full_type(&package_instance.pid)),
+                        package_name: Some(package_instance.name.clone()),
+                        package_id: Some(format!("0x{}", package_instance.pid)),
+                        alias: None,
+                    });
+                }
+            }
+        }
+        objects
+    }
+
+    // Link the publications sharing the same UpgradeCap, in publication order.
+    //
+    // Done across all the move_configs, so an upgrade is linked even when published
+    // from a different Move.toml location. The chain starts at the oldest publication
+    // known locally (original_pid is unknown if the original publication was removed).
+    //
+    // Returns true if a change was performed.
+    pub fn update_upgrade_lineage(&mut self) -> bool {
+        // Key is the UpgradeCap, value is the (timestamp, package id) of every publication.
+        let mut lineages: HashMap<String, Vec<(u64, String)>> = HashMap::new();
+        for move_config in self.move_configs.values() {
+            for package_instance in move_config.packages.values() {
+                if let Some(upgrade_cap) = &package_instance.upgrade_cap {
+                    let ts = package_instance.ts.parse::<u64>().unwrap_or(0);
+                    lineages
+                        .entry(upgrade_cap.clone())
+                        .or_default()
+                        .push((ts, package_instance.pid.clone()));
+                }
+            }
+        }
+        for lineage in lineages.values_mut() {
+            lineage.sort();
+        }
+
+        let mut changed = false;
+        for move_config in self.move_configs.values_mut() {
+            for package_instance in move_config.packages.values_mut() {
+                let lineage = match &package_instance.upgrade_cap {
+                    Some(upgrade_cap) => &lineages[upgrade_cap],
+                    None => continue,
+                };
+                let pos = lineage
+                    .iter()
+                    .position(|(_, pid)| *pid == package_instance.pid)
+                    .unwrap_or(0);
+                let (original_pid, previous_pid) = if pos == 0 {
+                    (None, None)
+                } else {
+                    (Some(lineage[0].1.clone()), Some(lineage[pos - 1].1.clone()))
+                };
+                if package_instance.original_pid != original_pid
+                    || package_instance.previous_pid != previous_pid
+                {
+                    package_instance.original_pid = original_pid;
+                    package_instance.previous_pid = previous_pid;
+                    changed = true;
+                }
+            }
+        }
+        changed
+    }
+
+    // Name the publish dependencies that are packages of this workdir. Returns true
+    // if a change was performed.
+    pub fn resolve_dependency_names(&mut self) -> bool {
+        let names: HashMap<String, String> = self
+            .move_configs
+            .values()
+            .flat_map(|move_config| move_config.packages.values())
+            .map(|package_instance| (package_instance.pid.clone(), package_instance.name.clone()))
+            .collect();
+
+        let mut changed = false;
+        for move_config in self.move_configs.values_mut() {
+            for package_instance in move_config.packages.values_mut() {
+                let dependencies = match &mut package_instance.publish {
+                    Some(publish) => &mut publish.dependencies,
+                    None => continue,
+                };
+                for dependency in dependencies.iter_mut().filter(|d| d.name.is_none()) {
+                    if let Some(name) = names.get(dependency.package_id.trim_start_matches("0x")) {
+                        dependency.name = Some(name.clone());
+                        changed = true;
+                    }
+                }
+            }
+        }
+        changed
+    }
+
     // Follow-up with calling this after all changes with add/delete_package_instance() are done.
     /*
     pub fn update_move_configs_set(&mut self) -> &HashSet<PackagePath> {
@@ -687,6 +1482,19 @@ pub trait ProxyApi {
         debug: Option<bool>,
     ) -> RpcResult<LinksResponse>;
 
+    /// Returns the user traffic stats per JSON-RPC method
+    /// for a given workdir.
+    ///
+    /// Same data/display/debug output variations as getLinks.
+    #[method(name = "getMethodStats")]
+    async fn get_method_stats(
+        &self,
+        workdir: String,
+        data: Option<bool>,
+        display: Option<bool>,
+        debug: Option<bool>,
+    ) -> RpcResult<MethodStatsResponse>;
+
     #[method(name = "fsChange")]
     async fn fs_change(&self, path: String) -> RpcResult<InfoResponse>;
 }
@@ -722,6 +1530,36 @@ pub trait GeneralApi {
         data_uuid: Option<String>,
     ) -> RpcResult<WorkdirStatusResponse>;
 
+    // SUI and coins balances, and owned objects count, of the keystore addresses (and
+    // of the "balance_addresses" in suibase.yaml).
+    //
+    // Same versioning as getWorkdirStatus.
+    #[method(name = "getAddressBalances")]
+    async fn get_address_balances(
+        &self,
+        workdir: String,
+        method_uuid: Option<String>,
+        data_uuid: Option<String>,
+    ) -> RpcResult<AddressBalancesResponse>;
+
+    // Fund an address on localnet or devnet, from the "faucet_funder_address" when
+    // configured, otherwise from the faucet. Default amount is one faucet request (or
+    // faucet_target_mist from the funder). The funder never pays more than faucet_target_mist.
+    //
+    // Subject to the faucet_min_interval_secs and faucet_max_requests_per_hour limits.
+    #[method(name = "requestFunds")]
+    async fn request_funds(
+        &self,
+        workdir: String,
+        address: String,
+        amount: Option<u64>,
+    ) -> RpcResult<FundsResponse>;
+
+    // State of the supervised local services (e.g. localnet sui and faucet processes)
+    // with their latest output, and the restart history.
+    #[method(name = "getServicesHistory")]
+    async fn get_services_history(&self, workdir: String) -> RpcResult<ServicesHistoryResponse>;
+
     // Allow to modify the asui selection.
     //
     // Choices are "localnet", "devnet", "testnet" or "mainnet".
@@ -751,6 +1589,61 @@ pub trait PackagesApi {
         last_ts: Option<String>,
     ) -> RpcResult<WorkdirSuiEventsResponse>;
 
+    // Object IDs created by the most recent publication of each package.
+    //
+    // object_type is "module::name", "<package_name>::module::name" or
+    // "0x<package_id>::module::name". A name without "::" is an alias (see setObjectAlias).
+    #[method(name = "getObjectIds")]
+    async fn get_object_ids(
+        &self,
+        workdir: String,
+        object_type: String,
+    ) -> RpcResult<PublishedObjectsResponse>;
+
+    // Name an object for later lookup with getObjectIds. No object_id removes the alias.
+    //
+    // Kept with the published data of the workdir (so removed on a localnet regen).
+    #[method(name = "setObjectAlias")]
+    async fn set_object_alias(
+        &self,
+        workdir: String,
+        alias: String,
+        object_id: Option<String>,
+    ) -> RpcResult<SuccessResponse>;
+
+    #[method(name = "getObjectAliases")]
+    async fn get_object_aliases(&self, workdir: String) -> RpcResult<ObjectAliasesResponse>;
+
+    // Registry of the publications of the same package (same uuid) across the networks.
+    //
+    // Optional filters on package_uuid and package_name. Only the packages published on
+    // at least one network are listed.
+    #[method(name = "getDeployments")]
+    async fn get_deployments(
+        &self,
+        package_uuid: Option<String>,
+        package_name: Option<String>,
+    ) -> RpcResult<DeploymentsResponse>;
+
+    // Tag a package instance (package_id) to keep it regardless of the retention
+    // (see packages_keep_last in suibase.yaml).
+    #[method(name = "tagPackageInstance")]
+    async fn tag_package_instance(
+        &self,
+        workdir: String,
+        package_id: String,
+        tag: String,
+    ) -> RpcResult<SuccessResponse>;
+
+    // No tag removes all the tags of the package instance.
+    #[method(name = "untagPackageInstance")]
+    async fn untag_package_instance(
+        &self,
+        workdir: String,
+        package_id: String,
+        tag: Option<String>,
+    ) -> RpcResult<SuccessResponse>;
+
     #[method(name = "getWorkdirPackages")]
     async fn get_workdir_packages(
         &self,
@@ -777,4 +1670,75 @@ pub trait PackagesApi {
         package_timestamp: String,
         package_id: String,
     ) -> RpcResult<SuccessResponse>;
+
+    // Last lines of the console log of a package (see log_worker.rs).
+    //
+    // lines defaults to 100. max_level is "error", "warn", "info", "debug" or "trace"
+    // (lines more verbose than this level are skipped).
+    #[method(name = "tailLogs")]
+    async fn tail_logs(
+        &self,
+        workdir: String,
+        package_name: String,
+        lines: Option<u32>,
+        max_level: Option<String>,
+    ) -> RpcResult<ConsoleLogResponse>;
+
+    // Write the stored events to a file on the daemon host.
+    //
+    // format is "jsonl", "csv" or "parquet". path is a file name created in
+    // workdirs/<workdir>/exports/ (relative, no "..", must not already exist).
+    // Optional filters: package (name or id), event_type (Move type prefix)
+    // and a [from_ms, to_ms) time range.
+    #[method(name = "exportEvents")]
+    async fn export_events(
+        &self,
+        workdir: String,
+        format: String,
+        path: String,
+        package: Option<String>,
+        event_type: Option<String>,
+        from_ms: Option<u64>,
+        to_ms: Option<u64>,
+    ) -> RpcResult<SuccessResponse>;
+
+    // Block until a user event matching the filter is persisted, then returns it.
+    //
+    // filter is an object with optional "package" (name or id), "eventType" (Move
+    // type prefix), "sender" and "fields" (subset of the parsedJson).
+    //
+    // When after_id is specified, the events already persisted after it are also
+    // considered (no race when chaining calls with the id of the previous response).
+    //
+    // timeout_ms defaults to 30 seconds. An error is returned on timeout.
+    #[method(name = "waitForEvent")]
+    async fn wait_for_event(
+        &self,
+        workdir: String,
+        filter: serde_json::Value,
+        timeout_ms: Option<u64>,
+        after_id: Option<i64>,
+    ) -> RpcResult<WaitForEventResponse>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_object_type() {
+        assert_eq!(
+            split_object_type("counter::Counter"),
+            Some((None, "counter", "Counter"))
+        );
+        assert_eq!(
+            split_object_type("demo::counter::Counter"),
+            Some((Some("demo"), "counter", "Counter"))
+        );
+        assert_eq!(
+            split_object_type("0xe065::pool::Pool<0x2::sui::SUI, 0xdba3::usdc::USDC>"),
+            Some((Some("0xe065"), "pool", "Pool"))
+        );
+        assert_eq!(split_object_type("Counter"), None);
+    }
 }
@@ -5,10 +5,12 @@ use jsonrpsee::core::RpcResult;
 
 use crate::admin_controller::AdminController;
 use crate::shared_types::Globals;
+use crate::workers::request_funds;
 
 use super::{
-    GeneralApiServer, Header, RpcInputError, RpcSuibaseError, SuccessResponse, VersionsResponse,
-    WorkdirStatusResponse,
+    AddressBalancesResponse, FundsResponse, GeneralApiServer, Header, RpcInputError,
+    RpcSuibaseError, ServiceRestart, ServicesHistoryResponse, SuccessResponse, SupervisedService,
+    VersionsResponse, WorkdirStatusResponse,
 };
 
 use super::def_header::Versioned;
@@ -208,6 +210,17 @@ impl GeneralApiServer for GeneralApiImpl {
             }
         }
 
+        // Section for getAddressBalances version.
+        {
+            let globals_read_guard = self.globals.get_balances(workdir_idx).read().await;
+            let globals = &*globals_read_guard;
+            if let Some(ui) = &globals.ui {
+                let mut hdr = Header::new("getAddressBalances");
+                hdr.set_from_uuids(ui.get_uuid());
+                resp.versions.push(hdr);
+            }
+        }
+
         // Initialize the uuids in the response header.
         // Use api_mutex.last_versions_response to detect if this response is different.
         // If yes, then increment its uuid_data.
@@ -278,6 +291,130 @@ impl GeneralApiServer for GeneralApiImpl {
         }
     }
 
+    async fn get_address_balances(
+        &self,
+        workdir: String,
+        method_uuid: Option<String>,
+        data_uuid: Option<String>,
+    ) -> RpcResult<AddressBalancesResponse> {
+        // Verify workdir param is OK and get its corresponding workdir_idx.
+        let workdir_idx = match common::shared_types::get_workdir_idx_by_name(&workdir) {
+            Some(workdir_idx) => workdir_idx,
+            None => return Err(RpcInputError::InvalidParams("workdir".to_string(), workdir).into()),
+        };
+
+        let globals_read_guard = self.globals.get_balances(workdir_idx).read().await;
+        let globals = &*globals_read_guard;
+
+        let ui = match &globals.ui {
+            Some(ui) => ui,
+            None => {
+                return Err(RpcSuibaseError::InfoError(
+                    "Backend still initializing. Balances not yet known".to_string(),
+                )
+                .into())
+            }
+        };
+        if let (Some(method_uuid), Some(data_uuid)) = (method_uuid, data_uuid) {
+            let uuids = ui.get_uuid();
+            if data_uuid != uuids.get_data_uuid() || method_uuid != uuids.get_method_uuid() {
+                // Caller can get the latest UUIDs with getVersions.
+                return Err(RpcSuibaseError::OutdatedUUID().into());
+            }
+        }
+        let mut resp = ui.get_data().clone();
+        resp.header.set_from_uuids(ui.get_uuid());
+        Ok(resp)
+    }
+
+    async fn request_funds(
+        &self,
+        workdir: String,
+        address: String,
+        amount: Option<u64>,
+    ) -> RpcResult<FundsResponse> {
+        // Verify workdir param is OK and get its corresponding workdir_idx.
+        let workdir_idx = match common::shared_types::get_workdir_idx_by_name(&workdir) {
+            Some(workdir_idx) => workdir_idx,
+            None => return Err(RpcInputError::InvalidParams("workdir".to_string(), workdir).into()),
+        };
+        if amount == Some(0) {
+            return Err(RpcInputError::InvalidParams("amount".to_string(), "0".to_string()).into());
+        }
+
+        let funding = request_funds(
+            &self.globals,
+            &self.admctrl_tx,
+            workdir_idx,
+            &address,
+            amount,
+        )
+        .await
+        .map_err(|e| RpcSuibaseError::InfoError(e.to_string()))?;
+
+        let mut resp = FundsResponse::new();
+        resp.header.method = "requestFunds".to_string();
+        resp.header.key = Some(workdir);
+        resp.address = address;
+        resp.source = funding.source;
+        resp.amount = funding.amount;
+        resp.tx_digests = funding.tx_digests;
+
+        // Balances changed... update the status now (instead of waiting for next audit).
+        let _ = AdminController::send_event_update(&self.admctrl_tx, workdir_idx).await;
+
+        Ok(resp)
+    }
+
+    async fn get_services_history(&self, workdir: String) -> RpcResult<ServicesHistoryResponse> {
+        // Verify workdir param is OK and get its corresponding workdir_idx.
+        let workdir_idx = match common::shared_types::get_workdir_idx_by_name(&workdir) {
+            Some(workdir_idx) => workdir_idx,
+            None => return Err(RpcInputError::InvalidParams("workdir".to_string(), workdir).into()),
+        };
+
+        let mut resp = ServicesHistoryResponse::new();
+        resp.header.method = "getServicesHistory".to_string();
+        resp.header.key = Some(workdir);
+        {
+            let globals_read_guard = self.globals.get_config(workdir_idx).read().await;
+            resp.restart_policy = globals_read_guard
+                .user_config
+                .supervisor_restart_policy()
+                .to_string();
+        }
+
+        let supervisor_guard = self.globals.get_supervisor(workdir_idx).lock().await;
+        let supervisor = &*supervisor_guard;
+        let now = std::time::Instant::now();
+        resp.services = supervisor
+            .services
+            .iter()
+            .map(|service| SupervisedService {
+                label: service.label.clone(),
+                state: service.state.as_str().to_string(),
+                pid: service.pid,
+                consecutive_failures: service.consecutive_failures,
+                restarts_in_window: service.restarts_in_window() as u32,
+                next_restart_in_secs: service.next_restart_in(now).map(|d| d.as_secs()),
+                output: service.output.iter().cloned().collect(),
+            })
+            .collect();
+        resp.restarts = supervisor
+            .history
+            .iter()
+            .rev()
+            .map(|record| ServiceRestart {
+                timestamp: record.timestamp,
+                label: record.label.clone(),
+                reason: record.reason.clone(),
+                outcome: record.outcome.clone(),
+                crash_loop: record.crash_loop,
+            })
+            .collect();
+        Ok(resp)
+    }
+
     async fn set_asui_selection(&self, workdir: String) -> RpcResult<SuccessResponse> {
         // Verify workdir param is OK and get its corresponding workdir_idx.
         let workdir_idx = match common::shared_types::get_workdir_idx_by_name(&workdir) {
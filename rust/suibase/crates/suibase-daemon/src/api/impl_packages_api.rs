@@ -9,21 +9,29 @@ use common::log_safe;
 use jsonrpsee::core::RpcResult;
 use jsonrpsee_types::ErrorObjectOwned as RpcError;
 
-use chrono::Utc;
-
 use crate::admin_controller::AdminController;
 
 use crate::api::RpcSuibaseError;
-use crate::shared_types::Globals;
+use crate::shared_types::{
+    console_log_path, level_from_str, tail_log_file, EventWaitFilter, EventWaitParams, Globals,
+    PackagePath, PACKAGE_TAGS_FILE,
+};
+use crate::workers::{
+    resolve_export_path, EventsExportFormat, EventsExportParams, EventsExportResult,
+};
 
+use super::suibase_toml::{find_suibase_toml, short_hash, SuibaseToml, SUIBASE_TOML};
 use super::{
-    PackagesApiServer, RpcInputError, SuccessResponse, WorkdirPackagesResponse,
-    WorkdirSuiEventsResponse,
+    ConsoleLogResponse, DeploymentsResponse, ObjectAliasesResponse, PackageDeployments,
+    PackageInstance, PackagesApiServer, PublishedObject, PublishedObjectsResponse, RpcInputError,
+    SuccessResponse, WaitForEventResponse, WorkdirPackagesResponse, WorkdirSuiEventsResponse,
 };
 
 pub struct PackagesApiImpl {
     pub globals: Globals,
     pub admctrl_tx: AdminControllerTx,
+    // Serialize the read-modify-write of the object-aliases.json files.
+    object_aliases_lock: tokio::sync::Mutex<()>,
 }
 
 impl PackagesApiImpl {
@@ -31,6 +39,7 @@ impl PackagesApiImpl {
         Self {
             globals,
             admctrl_tx,
+            object_aliases_lock: tokio::sync::Mutex::new(()),
         }
     }
 
@@ -38,8 +47,7 @@ impl PackagesApiImpl {
     // and return it as a string.
     pub fn short_hash(move_toml_path: &str) -> String {
         // the string is a RFC4648 Base32 (no pad) of the md5sum of the move_toml_path.
-        let md5 = md5::compute(move_toml_path);
-        data_encoding::BASE32_NOPAD.encode(&md5.to_vec())
+        short_hash(move_toml_path)
     }
 }
 
@@ -145,6 +153,17 @@ impl PackagesApiServer for PackagesApiImpl {
             return Err(RpcSuibaseError::InternalError(err_msg).into());
         }
 
+        // Record the source of this publication (see getDeployments). Best-effort, done
+        // before the POST_PUBLISH_EVENT so the PackagesPoller finds it.
+        Self::write_source_info(
+            workdir_idx,
+            &move_toml_path,
+            &package_name,
+            &package_uuid,
+            &package_timestamp,
+        )
+        .await;
+
         // Send a POST_PUBLISH_EVENT to the admin controller.
         let _ = AdminController::send_event_post_publish(&self.admctrl_tx, workdir_idx).await;
 
@@ -154,6 +173,384 @@ impl PackagesApiServer for PackagesApiImpl {
         Ok(resp)
     }
 
+    async fn export_events(
+        &self,
+        workdir: String,
+        format: String,
+        path: String,
+        package: Option<String>,
+        event_type: Option<String>,
+        from_ms: Option<u64>,
+        to_ms: Option<u64>,
+    ) -> RpcResult<SuccessResponse> {
+        let workdir_idx = match common::shared_types::get_workdir_idx_by_name(&workdir) {
+            Some(workdir_idx) => workdir_idx,
+            None => return Err(RpcInputError::InvalidParams("workdir".to_string(), workdir).into()),
+        };
+        let format = match format.parse::<EventsExportFormat>() {
+            Ok(format) => format,
+            Err(_) => return Err(RpcInputError::InvalidParams("format".to_string(), format).into()),
+        };
+        // Only a file name under workdirs/<workdir>/exports/ (never an arbitrary path).
+        if resolve_export_path(workdir_idx, &path).is_err() {
+            return Err(RpcInputError::InvalidParams("path".to_string(), path).into());
+        }
+
+        let mut resp = SuccessResponse::new();
+        resp.header.method = "exportEvents".to_string();
+        resp.header.key = Some(workdir.clone());
+
+        let params = EventsExportParams {
+            format,
+            path,
+            package,
+            event_type,
+            from_ms,
+            to_ms,
+        };
+        let data_json = match serde_json::to_value(&params) {
+            Ok(data_json) => data_json,
+            Err(e) => return Err(RpcSuibaseError::InternalError(e.to_string()).into()),
+        };
+        let resp_str = AdminController::send_events_exec(
+            &self.admctrl_tx,
+            workdir_idx,
+            "export_events".to_string(),
+            data_json,
+        )
+        .await
+        .map_err(|e| RpcSuibaseError::InternalError(e.to_string()))?;
+
+        let result = serde_json::from_str::<EventsExportResult>(&resp_str)
+            .map_err(|_| RpcSuibaseError::InternalError(resp_str.clone()))?;
+        if let Some(error) = result.error {
+            return Err(RpcSuibaseError::InternalError(error).into());
+        }
+        resp.result = true;
+        resp.info = Some(format!(
+            "{} events exported to {}",
+            result.events, result.path
+        ));
+        Ok(resp)
+    }
+
+    async fn wait_for_event(
+        &self,
+        workdir: String,
+        filter: serde_json::Value,
+        timeout_ms: Option<u64>,
+        after_id: Option<i64>,
+    ) -> RpcResult<WaitForEventResponse> {
+        const DEFAULT_TIMEOUT_MS: u64 = 30_000;
+        const MAX_TIMEOUT_MS: u64 = 3_600_000;
+
+        let workdir_idx = match common::shared_types::get_workdir_idx_by_name(&workdir) {
+            Some(workdir_idx) => workdir_idx,
+            None => return Err(RpcInputError::InvalidParams("workdir".to_string(), workdir).into()),
+        };
+        let filter = match serde_json::from_value::<EventWaitFilter>(filter.clone()) {
+            Ok(filter) => filter,
+            Err(_) => {
+                return Err(
+                    RpcInputError::InvalidParams("filter".to_string(), filter.to_string()).into(),
+                )
+            }
+        };
+        let timeout_ms = timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS).min(MAX_TIMEOUT_MS);
+
+        let mut resp = WaitForEventResponse::new();
+        resp.header.method = "waitForEvent".to_string();
+        resp.header.key = Some(workdir.clone());
+
+        let params = EventWaitParams { filter, after_id };
+        let data_json = match serde_json::to_value(&params) {
+            Ok(data_json) => data_json,
+            Err(e) => return Err(RpcSuibaseError::InternalError(e.to_string()).into()),
+        };
+        // Dropping the request on timeout also cancels the wait in the db_worker.
+        let resp_str = match tokio::time::timeout(
+            std::time::Duration::from_millis(timeout_ms),
+            AdminController::send_events_exec(
+                &self.admctrl_tx,
+                workdir_idx,
+                "wait_for_event".to_string(),
+                data_json,
+            ),
+        )
+        .await
+        {
+            Ok(resp_str) => resp_str.map_err(|e| RpcSuibaseError::InternalError(e.to_string()))?,
+            Err(_) => {
+                return Err(RpcSuibaseError::InfoError(format!(
+                    "no matching event within {} ms",
+                    timeout_ms
+                ))
+                .into())
+            }
+        };
+
+        let result = serde_json::from_str::<serde_json::Value>(&resp_str)
+            .map_err(|_| RpcSuibaseError::InternalError(resp_str.clone()))?;
+        if let Some(error) = result.get("error").and_then(|e| e.as_str()) {
+            return Err(RpcSuibaseError::InternalError(error.to_string()).into());
+        }
+        resp.id = result["id"].as_i64().unwrap_or(0);
+        resp.timestamp_ms = result["timestamp_ms"].as_i64().unwrap_or(0);
+        resp.package_name = result["package_name"].as_str().unwrap_or("").to_string();
+        resp.package_id = format!("0x{}", result["package_id"].as_str().unwrap_or(""));
+        // The stored "message" is the stringified Sui event.
+        resp.event = result["event"]["message"]
+            .as_str()
+            .and_then(|message| serde_json::from_str(message).ok())
+            .unwrap_or(serde_json::Value::Null);
+        Ok(resp)
+    }
+
+    async fn tail_logs(
+        &self,
+        workdir: String,
+        package_name: String,
+        lines: Option<u32>,
+        max_level: Option<String>,
+    ) -> RpcResult<ConsoleLogResponse> {
+        const MAX_LINES: usize = 10_000;
+
+        let workdir_idx = match common::shared_types::get_workdir_idx_by_name(&workdir) {
+            Some(workdir_idx) => workdir_idx,
+            None => return Err(RpcInputError::InvalidParams("workdir".to_string(), workdir).into()),
+        };
+        let max_level = match max_level {
+            None => common::basic_types::EVENT_LEVEL_MAX,
+            Some(max_level) => match level_from_str(&max_level) {
+                Some(level) => level,
+                None => {
+                    return Err(
+                        RpcInputError::InvalidParams("max_level".to_string(), max_level).into(),
+                    )
+                }
+            },
+        };
+        let n_lines = lines.map_or(100, |n| n as usize).min(MAX_LINES);
+
+        let mut resp = ConsoleLogResponse::new();
+        resp.header.method = "tailLogs".to_string();
+        resp.header.key = Some(workdir.clone());
+
+        // No file until the first log of the package.
+        let path = console_log_path(workdir_idx, &package_name);
+        resp.lines = match tail_log_file(&path, n_lines, max_level).await {
+            Ok(lines) => lines,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(resp),
+            Err(e) => {
+                let err_msg = format!("Failed to read {}: {}", path.display(), e);
+                return Err(RpcSuibaseError::FileAccessError(err_msg).into());
+            }
+        };
+        Ok(resp)
+    }
+
+    async fn get_object_ids(
+        &self,
+        workdir: String,
+        object_type: String,
+    ) -> RpcResult<PublishedObjectsResponse> {
+        let workdir_idx = match common::shared_types::get_workdir_idx_by_name(&workdir) {
+            Some(workdir_idx) => workdir_idx,
+            None => return Err(RpcInputError::InvalidParams("workdir".to_string(), workdir).into()),
+        };
+
+        let mut resp = PublishedObjectsResponse::new();
+        resp.header.method = "getObjectIds".to_string();
+        resp.header.key = Some(workdir.clone());
+
+        if !object_type.contains("::") {
+            let aliases = Self::load_object_aliases(workdir_idx).await?;
+            if let Some(object_id) = aliases.get(&object_type) {
+                resp.objects.push(PublishedObject {
+                    object_id: object_id.clone(),
+                    object_type: None,
+                    package_name: None,
+                    package_id: None,
+                    alias: Some(object_type),
+                });
+            }
+            return Ok(resp);
+        }
+
+        let globals_read_guard = self.globals.get_packages(workdir_idx).read().await;
+        let globals = &*globals_read_guard;
+        match &globals.ui {
+            Some(ui) => resp.objects = ui.get_data().find_init_objects(&object_type),
+            None => {
+                return Err(RpcSuibaseError::InfoError(
+                    "Backend still initializing. Status not yet known".to_string(),
+                )
+                .into())
+            }
+        }
+        Ok(resp)
+    }
+
+    async fn set_object_alias(
+        &self,
+        workdir: String,
+        alias: String,
+        object_id: Option<String>,
+    ) -> RpcResult<SuccessResponse> {
+        let workdir_idx = match common::shared_types::get_workdir_idx_by_name(&workdir) {
+            Some(workdir_idx) => workdir_idx,
+            None => return Err(RpcInputError::InvalidParams("workdir".to_string(), workdir).into()),
+        };
+        let is_valid_alias = !alias.is_empty()
+            && alias
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.');
+        if !is_valid_alias {
+            return Err(RpcInputError::InvalidParams("alias".to_string(), alias).into());
+        }
+        if let Some(object_id) = &object_id {
+            let hex = object_id.trim_start_matches("0x");
+            let is_valid_id = object_id.starts_with("0x")
+                && !hex.is_empty()
+                && hex.len() <= 64
+                && hex.chars().all(|c| c.is_ascii_hexdigit());
+            if !is_valid_id {
+                return Err(RpcInputError::InvalidParams(
+                    "object_id".to_string(),
+                    object_id.clone(),
+                )
+                .into());
+            }
+        }
+
+        let mut resp = SuccessResponse::new();
+        resp.header.method = "setObjectAlias".to_string();
+        resp.header.key = Some(workdir.clone());
+
+        let _lock = self.object_aliases_lock.lock().await;
+        let mut aliases = Self::load_object_aliases(workdir_idx).await?;
+        let info = match object_id {
+            Some(object_id) => {
+                aliases.insert(alias, object_id.to_lowercase());
+                None
+            }
+            None => aliases
+                .remove(&alias)
+                .is_none()
+                .then(|| "alias not found".to_string()),
+        };
+        let path = Self::object_aliases_path(workdir_idx);
+        if let Some(parent) = path.parent() {
+            let _ = tokio::fs::create_dir_all(parent).await;
+        }
+        let content = serde_json::to_string_pretty(&aliases)
+            .map_err(|e| RpcSuibaseError::InternalError(e.to_string()))?;
+        if let Err(e) = tokio::fs::write(&path, content).await {
+            let err_msg = format!("Failed to write {}: {}", path.display(), e);
+            log::error!("{}", err_msg);
+            return Err(RpcSuibaseError::FileAccessError(err_msg).into());
+        }
+        resp.result = true;
+        resp.info = info;
+        Ok(resp)
+    }
+
+    async fn get_object_aliases(&self, workdir: String) -> RpcResult<ObjectAliasesResponse> {
+        let workdir_idx = match common::shared_types::get_workdir_idx_by_name(&workdir) {
+            Some(workdir_idx) => workdir_idx,
+            None => return Err(RpcInputError::InvalidParams("workdir".to_string(), workdir).into()),
+        };
+        let mut resp = ObjectAliasesResponse::new();
+        resp.header.method = "getObjectAliases".to_string();
+        resp.header.key = Some(workdir.clone());
+        resp.aliases = Self::load_object_aliases(workdir_idx).await?;
+        Ok(resp)
+    }
+
+    async fn get_deployments(
+        &self,
+        package_uuid: Option<String>,
+        package_name: Option<String>,
+    ) -> RpcResult<DeploymentsResponse> {
+        let mut resp = DeploymentsResponse::new();
+        resp.header.method = "getDeployments".to_string();
+
+        // Promotion order is localnet, devnet, testnet then mainnet.
+        let mut registry: std::collections::BTreeMap<String, Vec<(String, PackageInstance)>> =
+            std::collections::BTreeMap::new();
+        for (workdir_idx, workdir) in common::shared_types::WORKDIRS_KEYS.iter().enumerate().rev() {
+            let globals_read_guard = self.globals.get_packages(workdir_idx as u8).read().await;
+            let globals = &*globals_read_guard;
+            let ui = match &globals.ui {
+                Some(ui) => ui,
+                None => continue,
+            };
+            for (uuid, instance) in ui.get_data().iter_most_recent_package_instance_by_uuid() {
+                if package_uuid.as_ref().is_some_and(|filter| filter != uuid)
+                    || package_name
+                        .as_deref()
+                        .is_some_and(|filter| filter != instance.get_package_name())
+                {
+                    continue;
+                }
+                registry
+                    .entry(uuid.clone())
+                    .or_default()
+                    .push((workdir.to_string(), instance.clone()));
+            }
+        }
+
+        for (uuid, instances) in registry {
+            let instances: Vec<(String, &PackageInstance)> = instances
+                .iter()
+                .map(|(workdir, instance)| (workdir.clone(), instance))
+                .collect();
+            resp.packages
+                .push(PackageDeployments::from_instances(uuid, &instances));
+        }
+        Ok(resp)
+    }
+
+    async fn tag_package_instance(
+        &self,
+        workdir: String,
+        package_id: String,
+        tag: String,
+    ) -> RpcResult<SuccessResponse> {
+        let is_valid_tag = !tag.is_empty()
+            && tag
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.');
+        if !is_valid_tag {
+            return Err(RpcInputError::InvalidParams("tag".to_string(), tag).into());
+        }
+        self.update_package_tags("tagPackageInstance", workdir, package_id, |tags| {
+            if tags.contains(&tag) {
+                return Some("already tagged".to_string());
+            }
+            tags.push(tag);
+            None
+        })
+        .await
+    }
+
+    async fn untag_package_instance(
+        &self,
+        workdir: String,
+        package_id: String,
+        tag: Option<String>,
+    ) -> RpcResult<SuccessResponse> {
+        self.update_package_tags("untagPackageInstance", workdir, package_id, |tags| {
+            let n_tags = tags.len();
+            match &tag {
+                Some(tag) => tags.retain(|t| t != tag),
+                None => tags.clear(),
+            }
+            (tags.len() == n_tags).then(|| "tag not found".to_string())
+        })
+        .await
+    }
+
     async fn get_workdir_packages(
         &self,
         workdir: String,
@@ -202,6 +599,156 @@ impl PackagesApiServer for PackagesApiImpl {
 }
 
 impl PackagesApiImpl {
+    async fn git_output(dir: &str, args: &[&str]) -> Option<std::process::Output> {
+        tokio::process::Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .output()
+            .await
+            .ok()
+    }
+
+    // Write published-data/{name}/{uuid}/{timestamp}/source.json with the git commit
+    // of the Move.toml directory (if any).
+    async fn write_source_info(
+        workdir_idx: u8,
+        move_toml_path: &str,
+        package_name: &str,
+        package_uuid: &str,
+        package_timestamp: &str,
+    ) {
+        let move_toml_dir = move_toml_path.trim_end_matches("Move.toml");
+        let commit = match Self::git_output(move_toml_dir, &["rev-parse", "HEAD"]).await {
+            Some(output) if output.status.success() => {
+                String::from_utf8_lossy(&output.stdout).trim().to_string()
+            }
+            _ => return, // Not a git repository (or no git).
+        };
+        let is_dirty = Self::git_output(move_toml_dir, &["status", "--porcelain", "--", "."])
+            .await
+            .is_some_and(|output| !output.stdout.is_empty());
+        let commit = if is_dirty {
+            format!("{}-dirty", commit)
+        } else {
+            commit
+        };
+
+        let package_path = PackagePath::new(
+            package_name.to_string(),
+            package_uuid.to_string(),
+            package_timestamp.to_string(),
+        );
+        let published_data_path = common::shared_types::get_workdir_paths(workdir_idx)
+            .workdir_root_path()
+            .join("published-data");
+        let path = package_path
+            .get_path(&published_data_path)
+            .join("source.json");
+        let content = serde_json::json!({
+            "move_toml_path": move_toml_path,
+            "commit": commit,
+        });
+        if let Err(e) = tokio::fs::write(&path, content.to_string()).await {
+            log::warn!("Failed to write {}: {}", path.display(), e);
+        }
+    }
+
+    // Apply update on the tags of a package instance, both in its tags.json and in
+    // the globals. update returns the optional info of the response.
+    async fn update_package_tags(
+        &self,
+        method: &str,
+        workdir: String,
+        package_id: String,
+        update: impl FnOnce(&mut Vec<String>) -> Option<String>,
+    ) -> RpcResult<SuccessResponse> {
+        let workdir_idx = match common::shared_types::get_workdir_idx_by_name(&workdir) {
+            Some(workdir_idx) => workdir_idx,
+            None => return Err(RpcInputError::InvalidParams("workdir".to_string(), workdir).into()),
+        };
+
+        let mut resp = SuccessResponse::new();
+        resp.header.method = method.to_string();
+        resp.header.key = Some(workdir.clone());
+
+        let mut globals_write_guard = self.globals.get_packages(workdir_idx).write().await;
+        let globals = &mut *globals_write_guard;
+        let ui = match &mut globals.ui {
+            Some(ui) => ui,
+            None => {
+                return Err(RpcSuibaseError::InfoError(
+                    "Backend still initializing. Status not yet known".to_string(),
+                )
+                .into())
+            }
+        };
+        let package_instance = match ui.get_mut_data().find_mut_package_instance(&package_id) {
+            Some(package_instance) => package_instance,
+            None => {
+                return Err(
+                    RpcInputError::InvalidParams("package_id".to_string(), package_id).into(),
+                )
+            }
+        };
+
+        let mut tags = package_instance.get_tags().to_vec();
+        let info = update(&mut tags);
+        if info.is_none() {
+            let published_data_path = common::shared_types::get_workdir_paths(workdir_idx)
+                .workdir_root_path()
+                .join("published-data");
+            let path = package_instance
+                .get_package_path()
+                .get_path(&published_data_path)
+                .join(PACKAGE_TAGS_FILE);
+            let written = if tags.is_empty() {
+                tokio::fs::remove_file(&path).await
+            } else {
+                let content = serde_json::to_string(&tags)
+                    .map_err(|e| RpcSuibaseError::InternalError(e.to_string()))?;
+                tokio::fs::write(&path, content).await
+            };
+            if let Err(e) = written {
+                let err_msg = format!("Failed to update {}: {}", path.display(), e);
+                log::error!("{}", err_msg);
+                return Err(RpcSuibaseError::FileAccessError(err_msg).into());
+            }
+            package_instance.set_tags(tags);
+            ui.inc_uuid();
+        }
+        resp.result = info.is_none();
+        resp.info = info;
+        Ok(resp)
+    }
+
+    fn object_aliases_path(workdir_idx: u8) -> std::path::PathBuf {
+        common::shared_types::get_workdir_paths(workdir_idx)
+            .workdir_root_path()
+            .join("published-data")
+            .join("object-aliases.json")
+    }
+
+    // Key is the alias, value is the object id. Empty when the file does not exist.
+    async fn load_object_aliases(
+        workdir_idx: u8,
+    ) -> Result<std::collections::BTreeMap<String, String>, RpcError> {
+        let path = Self::object_aliases_path(workdir_idx);
+        match tokio::fs::read_to_string(&path).await {
+            Ok(content) => serde_json::from_str(&content).map_err(|e| {
+                RpcSuibaseError::FileAccessError(format!("Invalid {}: {}", path.display(), e))
+                    .into()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Default::default()),
+            Err(e) => Err(RpcSuibaseError::FileAccessError(format!(
+                "Failed to read {}: {}",
+                path.display(),
+                e
+            ))
+            .into()),
+        }
+    }
+
     async fn internal_prepublish(
         &self,
         workdir: &String,
@@ -218,111 +765,53 @@ impl PackagesApiImpl {
             }
         };
 
-        // Identify the path of Suibase.toml co-located to the Move.toml
-        let move_toml_path_only = move_toml_path.trim_end_matches("Move.toml");
-        let toml_path = std::path::PathBuf::from(move_toml_path_only);
-        let suibase_toml_path = toml_path.join("Suibase.toml");
-
-        // if the file suibase_toml_path does not exists, then create it with the following content:
-        //
-        // [meta]
-        // creation_timestamp = "<epoch timestamp in microseconds> ISO 8061 datetime in local timezone"
-        //
-        // [packages]
-        // <package_name> = { uuid="<hash_of_move_toml_filepath>", custom_uuid=false }
-        //
-        // For now, it seems Mysten Labs will have only *one* package per Move.toml, so same
-        // to be expected for the co-located Suibase.toml.
-        //
-        // Hash is defined as the RFC4648 Base32 (no pad) of the md5 bytes of the move_toml_path.
-        let suibase_toml_string = if !suibase_toml_path.exists() {
-            // Create the Suibase.toml file.
-            let mut packages_section = toml_edit::Table::new();
-            // Build a Table for each package.
-            let mut package_table = toml_edit::InlineTable::new();
-            package_table.insert("uuid", Self::short_hash(move_toml_path).into());
-            let uuid_custom = false;
-            package_table.insert("uuid_custom", uuid_custom.into());
-            packages_section.insert(package_name, toml_edit::Item::Value(package_table.into()));
-
-            let mut meta_section = toml_edit::Table::new();
-            let now = std::time::SystemTime::now();
-            let datetime_utc: chrono::DateTime<Utc> = now.into();
-            let datetime_local = datetime_utc.with_timezone(&chrono::Local);
-            meta_section.insert(
-                "creation_timestamp",
-                toml_edit::value(format!(
-                    "{} {}",
-                    datetime_utc.timestamp_micros(),
-                    datetime_local
-                )),
-            );
-
-            let mut suibase_toml_doc = toml_edit::Document::new();
-            suibase_toml_doc["meta"] = toml_edit::Item::Table(meta_section);
-            suibase_toml_doc["packages"] = toml_edit::Item::Table(packages_section);
-
-            let new_file_string = suibase_toml_doc.to_string();
-            match tokio::fs::write(suibase_toml_path.clone(), new_file_string.clone()).await {
-                Ok(_) => {}
-                Err(e) => {
-                    let err_msg = format!("Failed to write Suibase.toml: {}", e);
-                    log::error!("{}", err_msg);
-                    return Err(RpcSuibaseError::FileAccessError(err_msg).into());
-                }
-            }
-            new_file_string
-        } else {
-            // Read the existing Suibase.toml file.
-            match tokio::fs::read_to_string(suibase_toml_path.clone()).await {
-                Ok(read_string) => read_string,
-                Err(e) => {
-                    let err_msg = format!("Failed to read Suibase.toml: {}", e);
-                    log::error!("{}", err_msg);
-                    return Err(RpcSuibaseError::FileAccessError(err_msg).into());
+        // Identify the Suibase.toml for this Move.toml (co-located or workspace).
+        let move_toml_dir = std::path::PathBuf::from(move_toml_path.trim_end_matches("Move.toml"));
+        let move_toml_file = move_toml_dir.join("Move.toml");
+        let (suibase_toml_path, mut suibase_toml) = match find_suibase_toml(&move_toml_dir) {
+            Some(suibase_toml_path) => {
+                let content = match tokio::fs::read_to_string(&suibase_toml_path).await {
+                    Ok(read_string) => read_string,
+                    Err(e) => {
+                        let err_msg = format!("Failed to read Suibase.toml: {}", e);
+                        log::error!("{}", err_msg);
+                        return Err(RpcSuibaseError::FileAccessError(err_msg).into());
+                    }
+                };
+                let dir = suibase_toml_path
+                    .parent()
+                    .unwrap_or(&move_toml_dir)
+                    .to_path_buf();
+                let suibase_toml = SuibaseToml::parse(&dir, &content);
+                if suibase_toml.is_repaired() {
+                    // Keep the damaged file for the user to review.
+                    let _ = tokio::fs::copy(
+                        &suibase_toml_path,
+                        suibase_toml_path.with_extension("toml.bak"),
+                    )
+                    .await;
                 }
+                (suibase_toml_path, suibase_toml)
             }
+            None => (
+                move_toml_dir.join(SUIBASE_TOML),
+                SuibaseToml::new(&move_toml_dir),
+            ),
         };
+        suibase_toml.detect_relocation(|original_dir| original_dir.join(SUIBASE_TOML).exists());
 
-        // TODO Add robustness to toml_edit if the document exists but the user deleted the UUID field.
-        // TODO Implement to regenerate the UUID if moving of Suibase.toml is detected. Need to put path in toml for this.
-
-        let suibase_toml_doc = match suibase_toml_string.parse::<toml_edit::Document>() {
-            Ok(suibase_toml_doc) => Some(suibase_toml_doc),
-            Err(e) => {
-                log::error!("Failed to parse Suibase.toml: {}", e);
-                None
-            }
-        };
-
-        // TODO: Handling of multiple package.
-        let mut package_uuid = String::new();
-        if let Some(doc) = suibase_toml_doc {
-            if let Some(packages) = doc.get("packages") {
-                if let Some(packages) = packages.as_table() {
-                    // Iterate the packages until finding the one that match package_name.
-                    for (key, value) in packages.iter() {
-                        if *key == *package_name {
-                            if let Some(value) = value.as_inline_table() {
-                                if let Some(uuid) = value.get("uuid") {
-                                    if let Some(uuid) = uuid.as_str() {
-                                        package_uuid = uuid.to_string();
-                                    }
-                                }
-                            }
-                            break;
-                        }
-                    }
-                }
-            }
+        let package_uuid = suibase_toml.package_uuid(package_name, &move_toml_file);
+        if suibase_toml.is_repaired() {
+            log::warn!("Repaired {}", suibase_toml_path.display());
         }
 
-        // If somehow could not load the package_uuid from the file, then default
-        // to a calculated value.
-
-        // TODO: Try to fix the Suibase.toml and try once again.
-        if package_uuid.is_empty() {
-            package_uuid = Self::short_hash(move_toml_path);
+        if suibase_toml.is_modified() {
+            let new_file_string = suibase_toml.to_string();
+            if let Err(e) = tokio::fs::write(&suibase_toml_path, new_file_string).await {
+                let err_msg = format!("Failed to write Suibase.toml: {}", e);
+                log::error!("{}", err_msg);
+                return Err(RpcSuibaseError::FileAccessError(err_msg).into());
+            }
         }
 
         Ok((workdir_idx, package_uuid))
@@ -4,11 +4,12 @@ use axum::async_trait;
 
 use jsonrpsee::core::RpcResult;
 
-use crate::shared_types::{GlobalsProxyMT, ServerStats};
+use crate::shared_types::{AllMethodsStats, GlobalsProxyMT, ServerStats};
 use common::basic_types::{AdminControllerMsg, AdminControllerTx, SafeUuid, TargetServerIdx};
 
 use super::{InfoResponse, ProxyApiServer, VersionedEq};
 use super::{LinkStats, LinksResponse, LinksSummary, RpcInputError};
+use super::{MethodStatsInfo, MethodStatsResponse};
 
 use super::def_header::Versioned;
 
@@ -41,19 +42,42 @@ impl VersionedEq for GetLinksInput {
     }
 }
 
+#[derive(Clone, PartialEq)]
+struct GetMethodStatsInput {
+    pub all_methods_stats: Option<AllMethodsStats>,
+}
+
+impl GetMethodStatsInput {
+    pub fn new() -> Self {
+        Self {
+            all_methods_stats: None,
+        }
+    }
+}
+
+impl VersionedEq for GetMethodStatsInput {
+    fn versioned_eq(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
 pub struct ProxyApiImpl {
     pub globals: GlobalsProxyMT,
     pub admctrl_tx: AdminControllerTx,
     prev_get_links_input: Mutex<Versioned<GetLinksInput>>,
+    prev_get_method_stats_input: Mutex<Versioned<GetMethodStatsInput>>,
 }
 
 impl ProxyApiImpl {
     pub fn new(globals: GlobalsProxyMT, admctrl_tx: AdminControllerTx) -> Self {
         let prev_get_links_input = Mutex::new(Versioned::new(GetLinksInput::new()));
+        let prev_get_method_stats_input =
+            Mutex::new(Versioned::new(GetMethodStatsInput::new()));
         Self {
             globals,
             admctrl_tx,
             prev_get_links_input,
+            prev_get_method_stats_input,
         }
     }
 
@@ -529,6 +553,110 @@ impl ProxyApiServer for ProxyApiImpl {
         Ok(resp)
     }
 
+    async fn get_method_stats(
+        &self,
+        workdir: String,
+        data: Option<bool>,
+        display: Option<bool>,
+        debug: Option<bool>,
+    ) -> RpcResult<MethodStatsResponse> {
+        let mut resp = MethodStatsResponse::new();
+
+        // Initialize some of the header fields.
+        resp.header.method = "getMethodStats".to_string();
+        resp.header.key = Some(workdir.clone());
+
+        // Same defaults as getLinks.
+        let debug = debug.unwrap_or(false);
+        let display = display.unwrap_or(debug);
+        let data = data.unwrap_or(!(debug || display));
+
+        let mut debug_out = String::new();
+
+        // Variables initialized during the read lock.
+        let mut inputs = GetMethodStatsInput::new();
+        let mut inputs_version: Option<SafeUuid> = None;
+
+        {
+            // Get read lock access to the globals and just quickly copy what is needed.
+            let globals_read_guard = self.globals.read().await;
+            let globals = &*globals_read_guard;
+
+            if let Some(input_port) = globals.find_input_port_by_name(&workdir) {
+                inputs.all_methods_stats = Some(input_port.all_methods_stats.clone());
+            }
+
+            if data {
+                // To avoid race condition, prev_get_method_stats_input is lock and modified only here.
+                let prev_input = &mut *self.prev_get_method_stats_input.lock().await;
+                inputs_version = Some(prev_input.set(&inputs));
+            }
+        } // Release the read lock.
+
+        let all_methods_stats = match inputs.all_methods_stats {
+            Some(all_methods_stats) => all_methods_stats,
+            None => {
+                return Err(RpcInputError::InvalidParams("workdir".to_string(), workdir).into());
+            }
+        };
+
+        if debug {
+            debug_out.push_str(&format!("{:?}", all_methods_stats));
+        }
+
+        // Map into the API MethodStatsInfo (most called first).
+        let mut methods: Vec<MethodStatsInfo> = all_methods_stats
+            .iter()
+            .map(|(method, stats)| {
+                let mut info = MethodStatsInfo::new(method.clone());
+                info.calls = stats.calls();
+                info.errors = stats.errors();
+                info.bytes_in = stats.bytes_in();
+                info.bytes_out = stats.bytes_out();
+                info.resp_time = Self::fmt_f64_api(stats.avg_latency_ms());
+                info.resp_time_max = Self::fmt_f64_api(stats.max_latency_ms());
+                info
+            })
+            .collect();
+        methods.sort_by(|a, b| b.calls.cmp(&a.calls).then_with(|| a.method.cmp(&b.method)));
+
+        if display {
+            let mut display_out = String::new();
+            display_out.push_str(
+                "method                                Calls   Errors    Bytes In   Bytes Out   RespT ms    Max ms\n\
+                 ---------------------------------------------------------------------------------------------------\n",
+            );
+            for info in methods.iter() {
+                display_out.push_str(&format!(
+                    "{:<34}{:>9}{:>9}{:>12}{:>12}{:>11}{:>10}\n",
+                    format!("{:.33}", info.method),
+                    info.calls,
+                    info.errors,
+                    info.bytes_in,
+                    info.bytes_out,
+                    Self::fmt_str_ms(&info.resp_time),
+                    Self::fmt_str_ms(&info.resp_time_max),
+                ));
+            }
+            resp.display = Some(display_out);
+        }
+
+        if debug {
+            resp.debug = Some(debug_out);
+        }
+
+        if data {
+            resp.methods = Some(methods);
+
+            if let Some(version) = inputs_version {
+                resp.header.method_uuid = Some(version.get_method_uuid());
+                resp.header.data_uuid = Some(version.get_data_uuid());
+            }
+        }
+
+        Ok(resp)
+    }
+
     async fn fs_change(&self, path: String) -> RpcResult<InfoResponse> {
         let mut resp = InfoResponse::new();
 
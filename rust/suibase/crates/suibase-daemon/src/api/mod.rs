@@ -13,3 +13,4 @@ mod impl_general_api;
 mod impl_packages_api;
 mod impl_proxy_api;
 mod rpc_error;
+mod suibase_toml;
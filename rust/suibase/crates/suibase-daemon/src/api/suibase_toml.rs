@@ -0,0 +1,270 @@
+// Suibase.toml handling for the prePublish/postPublish API.
+//
+// A Suibase.toml is either co-located with a Move.toml, or is a "workspace" Suibase.toml
+// in a parent directory shared by several Move packages:
+//
+// [meta]
+// creation_timestamp = "<epoch timestamp in microseconds> ISO 8061 datetime in local timezone"
+// path = "<absolute directory of this Suibase.toml>"
+// workspace = true   # Only for a workspace.
+//
+// [packages]
+// <package_name> = { uuid="<hash_of_move_toml_filepath>", uuid_custom=false, path="<relative Move.toml path>" }
+//
+// The "path" in [meta] detects when the Suibase.toml was copied or moved:
+//   - copied: the original still exists, so the non-custom uuid are regenerated (two
+//     checkouts must not share the same uuid). The prior location is kept in
+//     [meta] original_path.
+//   - moved: the original no longer exists, the uuid are kept (the publications
+//     history stays with the package).
+//
+// A damaged Suibase.toml (not parseable, missing sections or uuid) is repaired.
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+
+pub const SUIBASE_TOML: &str = "Suibase.toml";
+
+// Hash is defined as the RFC4648 Base32 (no pad) of the md5 bytes of the move_toml_path.
+pub fn short_hash(move_toml_path: &str) -> String {
+    let md5 = md5::compute(move_toml_path);
+    data_encoding::BASE32_NOPAD.encode(&md5.to_vec())
+}
+
+// Path of the Suibase.toml to use for a Move.toml directory.
+//
+// A co-located Suibase.toml has priority, otherwise the nearest workspace Suibase.toml
+// in a parent directory. None when a co-located one should be created.
+pub fn find_suibase_toml(move_toml_dir: &Path) -> Option<PathBuf> {
+    let co_located = move_toml_dir.join(SUIBASE_TOML);
+    if co_located.exists() {
+        return Some(co_located);
+    }
+    for dir in move_toml_dir.ancestors().skip(1) {
+        let path = dir.join(SUIBASE_TOML);
+        if let Ok(content) = std::fs::read_to_string(&path) {
+            let is_workspace = content
+                .parse::<toml_edit::Document>()
+                .ok()
+                .and_then(|doc| doc.get("meta")?.get("workspace")?.as_bool())
+                .unwrap_or(false);
+            if is_workspace {
+                return Some(path);
+            }
+        }
+    }
+    None
+}
+
+pub struct SuibaseToml {
+    doc: toml_edit::Document,
+    dir: PathBuf, // Directory of the Suibase.toml
+    modified: bool,
+    repaired: bool,
+}
+
+impl SuibaseToml {
+    pub fn new(dir: &Path) -> Self {
+        let mut meta_section = toml_edit::Table::new();
+        let datetime_utc: chrono::DateTime<Utc> = std::time::SystemTime::now().into();
+        let datetime_local = datetime_utc.with_timezone(&chrono::Local);
+        meta_section.insert(
+            "creation_timestamp",
+            toml_edit::value(format!(
+                "{} {}",
+                datetime_utc.timestamp_micros(),
+                datetime_local
+            )),
+        );
+        meta_section.insert("path", toml_edit::value(dir.to_string_lossy().to_string()));
+
+        let mut doc = toml_edit::Document::new();
+        doc["meta"] = toml_edit::Item::Table(meta_section);
+        doc["packages"] = toml_edit::Item::Table(toml_edit::Table::new());
+        Self {
+            doc,
+            dir: dir.to_path_buf(),
+            modified: true,
+            repaired: false,
+        }
+    }
+
+    // Never fails. A file that can't be parsed is replaced (see is_repaired).
+    pub fn parse(dir: &Path, content: &str) -> Self {
+        let doc = match content.parse::<toml_edit::Document>() {
+            Ok(doc) => doc,
+            Err(e) => {
+                log::warn!("Repairing {}: {}", dir.join(SUIBASE_TOML).display(), e);
+                let mut new = Self::new(dir);
+                new.repaired = true;
+                return new;
+            }
+        };
+        let mut suibase_toml = Self {
+            doc,
+            dir: dir.to_path_buf(),
+            modified: false,
+            repaired: false,
+        };
+        for section in ["meta", "packages"] {
+            if !suibase_toml.doc.get(section).is_some_and(|s| s.is_table()) {
+                suibase_toml.doc[section] = toml_edit::Item::Table(toml_edit::Table::new());
+                suibase_toml.modified = true;
+                suibase_toml.repaired = true;
+            }
+        }
+        suibase_toml
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    pub fn is_repaired(&self) -> bool {
+        self.repaired
+    }
+
+    // See top of this file. original_exists is called with the prior directory.
+    pub fn detect_relocation(&mut self, original_exists: impl Fn(&Path) -> bool) {
+        let current = self.dir.to_string_lossy().to_string();
+        let stored = self.doc["meta"]
+            .get("path")
+            .and_then(|p| p.as_str())
+            .map(str::to_string);
+        match stored {
+            Some(stored) if stored == current => return,
+            Some(stored) if original_exists(Path::new(&stored)) => {
+                log::info!("Suibase.toml copied from {} to {}", stored, current);
+                self.regenerate_uuids();
+                self.doc["meta"]["original_path"] = toml_edit::value(stored);
+            }
+            Some(stored) => log::info!("Suibase.toml moved from {} to {}", stored, current),
+            None => {} // Created prior to the path tracking.
+        }
+        self.doc["meta"]["path"] = toml_edit::value(current);
+        self.modified = true;
+    }
+
+    // The [packages] table, recreated if it was replaced by something else since
+    // the parse (e.g. an inline table).
+    fn packages_table<'a>(
+        doc: &'a mut toml_edit::Document,
+        repaired: &mut bool,
+    ) -> &'a mut toml_edit::Table {
+        let item = &mut doc["packages"];
+        if !item.is_table() {
+            log::warn!("Repairing [packages] of {}", SUIBASE_TOML);
+            *item = toml_edit::Item::Table(toml_edit::Table::new());
+            *repaired = true;
+        }
+        match item {
+            toml_edit::Item::Table(table) => table,
+            _ => unreachable!(), // Replaced above.
+        }
+    }
+
+    fn regenerate_uuids(&mut self) {
+        let dir = self.dir.clone();
+        let packages = Self::packages_table(&mut self.doc, &mut self.repaired);
+        for (name, package) in packages.iter_mut() {
+            let name = name.get().to_string();
+            let entry = match package.as_table_like_mut() {
+                Some(entry) => entry,
+                None => continue,
+            };
+            if entry.get("uuid_custom").and_then(|c| c.as_bool()) == Some(true) {
+                continue;
+            }
+            let move_toml_path = match entry.get("path").and_then(|p| p.as_str()) {
+                Some(path) => dir.join(path),
+                None => dir.join("Move.toml"),
+            };
+            let uuid = short_hash(&move_toml_path.to_string_lossy());
+            log::info!("New uuid {} for package {}", uuid, name);
+            entry.insert("uuid", toml_edit::value(uuid));
+        }
+    }
+
+    // Get the uuid of a package. An entry is created (or repaired) as needed.
+    pub fn package_uuid(&mut self, package_name: &str, move_toml_path: &Path) -> String {
+        let relative_path = move_toml_path
+            .strip_prefix(&self.dir)
+            .unwrap_or(move_toml_path)
+            .to_string_lossy()
+            .to_string();
+        let packages = Self::packages_table(&mut self.doc, &mut self.repaired);
+
+        let existing = packages
+            .get_mut(package_name)
+            .and_then(|p| p.as_table_like_mut());
+        if let Some(entry) = existing {
+            if let Some(uuid) = entry.get("uuid").and_then(|u| u.as_str()) {
+                let uuid = uuid.to_string();
+                if entry.get("path").and_then(|p| p.as_str()) != Some(relative_path.as_str()) {
+                    // Created prior to the path tracking, or moved within the workspace.
+                    entry.insert("path", toml_edit::value(relative_path));
+                    self.modified = true;
+                }
+                return uuid;
+            }
+            self.repaired = true;
+        }
+
+        let uuid = short_hash(&move_toml_path.to_string_lossy());
+        let mut package_table = toml_edit::InlineTable::new();
+        package_table.insert("uuid", uuid.clone().into());
+        package_table.insert("uuid_custom", false.into());
+        package_table.insert("path", relative_path.into());
+        packages.insert(package_name, toml_edit::Item::Value(package_table.into()));
+        self.modified = true;
+        uuid
+    }
+}
+
+impl std::fmt::Display for SuibaseToml {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.doc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suibase_toml_packages() {
+        let dir = Path::new("/home/user/ws");
+        let mut toml = SuibaseToml::new(dir);
+        let uuid_a = toml.package_uuid("a", &dir.join("a/Move.toml"));
+        let uuid_b = toml.package_uuid("b", &dir.join("b/Move.toml"));
+        assert_ne!(uuid_a, uuid_b);
+        assert_eq!(toml.package_uuid("a", &dir.join("a/Move.toml")), uuid_a);
+
+        // Copied: the uuid are regenerated.
+        let copy_dir = Path::new("/home/user/ws2");
+        let mut copy = SuibaseToml::parse(copy_dir, &toml.to_string());
+        copy.detect_relocation(|_| true);
+        assert_ne!(
+            copy.package_uuid("a", &copy_dir.join("a/Move.toml")),
+            uuid_a
+        );
+
+        // Moved: the uuid are kept.
+        let mut moved = SuibaseToml::parse(copy_dir, &toml.to_string());
+        moved.detect_relocation(|_| false);
+        assert_eq!(
+            moved.package_uuid("a", &copy_dir.join("a/Move.toml")),
+            uuid_a
+        );
+
+        // Damaged.
+        let mut damaged = SuibaseToml::parse(dir, "[packages]\na = { uuid_custom = false }");
+        assert_eq!(damaged.package_uuid("a", &dir.join("a/Move.toml")), uuid_a);
+        assert!(damaged.is_repaired());
+
+        let mut damaged = SuibaseToml::parse(dir, "[meta]\n");
+        damaged.doc["packages"] = toml_edit::value("bad");
+        assert_eq!(damaged.package_uuid("a", &dir.join("a/Move.toml")), uuid_a);
+        assert!(damaged.is_repaired());
+    }
+}
@@ -0,0 +1,65 @@
+// JSON-RPC client to the API of an already running suibase-daemon.
+//
+// Used by the command line subcommands other than "run" (e.g. "export-events"), so
+// the work is done by the daemon owning the data.
+use anyhow::{anyhow, Result};
+
+use common::shared_types::{get_workdir_common_path, ApiUserConfig, SUIBASE_API_PORT};
+
+fn api_uri() -> String {
+    // Same config as the APIServer. Optional, so no error if does not exists.
+    let mut api_config = ApiUserConfig::new();
+    let _ = api_config.load_and_merge_from_file(&get_workdir_common_path().join("suibase.yaml"));
+
+    // A bind to all interfaces is still reachable locally.
+    let host = match api_config.bind_address() {
+        "0.0.0.0" | "::" | "localhost" => "127.0.0.1",
+        bind_address => bind_address,
+    };
+    let scheme = if api_config.tls().enabled {
+        "https"
+    } else {
+        "http"
+    };
+    if host.contains(':') {
+        format!("{}://[{}]:{}", scheme, host, SUIBASE_API_PORT)
+    } else {
+        format!("{}://{}:{}", scheme, host, SUIBASE_API_PORT)
+    }
+}
+
+// Returns the "result" of the JSON-RPC response.
+pub async fn call_api(method: &str, params: serde_json::Value) -> Result<serde_json::Value> {
+    // The API may use a self-signed certificate (see tls.rs).
+    let client = reqwest::Client::builder()
+        .no_proxy()
+        .danger_accept_invalid_certs(true)
+        .build()?;
+    let request = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": method,
+        "params": params
+    });
+    let uri = api_uri();
+    let resp: serde_json::Value = client
+        .post(&uri)
+        .header("Content-Type", "application/json")
+        .json(&request)
+        .send()
+        .await
+        .map_err(|e| anyhow!("suibase-daemon not reachable at {} ({})", uri, e))?
+        .json()
+        .await?;
+
+    if let Some(err) = resp.get("error") {
+        let message = err.get("message").and_then(|m| m.as_str());
+        return Err(anyhow!(
+            "{}",
+            message.map_or(err.to_string(), str::to_string)
+        ));
+    }
+    resp.get("result")
+        .cloned()
+        .ok_or_else(|| anyhow!("{} missing result {}", method, resp))
+}
@@ -4,7 +4,8 @@
 use acoins_monitor::ACoinsMonitor;
 // main.rs does:
 //  - Validate command line.
-//  - Telemetry setup
+//  - Subcommands other than "run" are requests to the already running daemon (see daemon_client.rs).
+//  - Telemetry setup (see telemetry.rs for the OpenTelemetry spans and export)
 //  - Top level tasks started here. These runs until the program terminates:
 //     - AdminController: The "leader" thread validating and applying the config changes and user actions.
 //     - NetworkMonitor: Maintains all remote server stats. Info coming from multiple sources (on a mpsc channel).
@@ -16,6 +17,9 @@ use acoins_monitor::ACoinsMonitor;
 //  - ProxyServer:        One long-running thread instance per listening port. Async handling of all user traffic.
 //                        Uses axum::Server and reqwest::Client. Started/stopped by the AdminController.
 //
+//  - AccessLogWriter:    Optional JSON lines access log of the ProxyServer traffic. One instance per listening
+//                        port. Started by the ProxyServer.
+//
 //  - RequestWorker:      Perform on-demand health check+latency test of RPC servers.
 //                        Uses reqwest::Client. Started/stopped by the NetworkMonitor.
 //
@@ -28,10 +32,17 @@ use acoins_monitor::ACoinsMonitor;
 //
 //  - WebSocketWorker:    Manage subscribe/unsubscribe and receiving Sui events for a single connection. Forwards
 //                        subscribed sui events to its parent EventsWriterWorker for dedup. Uses tokio-tungstenite.
+//                        One instance per link with a "ws" URL.
 //
 //  - DBWorker:           Manage the in-memory (or file) DB for a single workdir. Write to DB the already
 //                        validated and dedup Sui events from its parent (EventsWriterWorker).
 //
+//  - LogWorker:          Write the console::ConsoleEvent of the packages to rotating log files (one per package).
+//                        Child of the EventsWriterWorker. Read back with the tailLogs API.
+//
+//  - WebhookWorker:      POST the events written by the DBWorker to the webhooks of a single workdir.
+//                        Retries with backoff, dead-letter table. Child of the EventsWriterWorker.
+//
 //  - CliPoller:          Periodic/on-demand CLI "status" command. Parse CLI output, update globals and generate
 //                        events on changes. Sandboxed in case of parsing error (auto-restart on panic).
 //                        Started by the AdminController.
@@ -53,11 +64,15 @@ mod admin_controller;
 mod api;
 mod app_error;
 
+mod access_log;
 mod acoins_monitor;
 mod clock_trigger;
+mod daemon_client;
 mod network_monitor;
 mod proxy_server;
 mod shared_types;
+mod telemetry;
+mod tls;
 mod workdirs_watcher;
 mod workers;
 
@@ -87,6 +102,32 @@ use workers::WebserverWorker;
 pub enum Command {
     #[clap(name = "run")]
     Run {},
+
+    /// Export the events stored by the running daemon to a file.
+    #[clap(name = "export-events")]
+    ExportEvents {
+        /// Workdir name (e.g. localnet, testnet).
+        #[arg(long)]
+        workdir: String,
+        /// jsonl, csv or parquet.
+        #[arg(long, default_value = "jsonl")]
+        format: String,
+        /// File name to create in ~/suibase/workdirs/<workdir>/exports/ (must not exist).
+        #[arg(long, short)]
+        output: String,
+        /// Package name or package id.
+        #[arg(long)]
+        package: Option<String>,
+        /// Move event type prefix (e.g. 0x...::counter::CounterChanged).
+        #[arg(long)]
+        event_type: Option<String>,
+        /// Start time (unix epoch milliseconds, inclusive).
+        #[arg(long)]
+        from: Option<u64>,
+        /// End time (unix epoch milliseconds, exclusive).
+        #[arg(long)]
+        to: Option<u64>,
+    },
 }
 
 impl Command {
@@ -214,23 +255,39 @@ impl Command {
                 }
                 Ok(errors?)
             } // end Command::Run
+            Command::ExportEvents {
+                workdir,
+                format,
+                output,
+                package,
+                event_type,
+                from,
+                to,
+            } => {
+                // The file is written by the daemon, in the exports directory of the workdir.
+                let params = serde_json::json!([
+                    workdir,
+                    format,
+                    output,
+                    package,
+                    event_type,
+                    from,
+                    to
+                ]);
+                let result = daemon_client::call_api("exportEvents", params).await?;
+                if let Some(info) = result.get("info").and_then(|info| info.as_str()) {
+                    println!("{}", info);
+                }
+                Ok(())
+            }
         }
     }
 } // end of Command
 
 #[tokio::main]
 async fn main() {
-    // Initialize tracing with env filter support
-    let subscriber = tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "poi_server=info,database=info,tower_http=warn".into()),
-        )
-        .with_span_events(tracing_subscriber::fmt::format::FmtSpan::ACTIVE)
-        .compact() // Modern preference for daemon logs
-        .finish();
-
-    tracing::subscriber::set_global_default(subscriber).expect("Failed to set tracing subscriber");
+    // Initialize tracing with env filter support (and optional OTLP export).
+    let _telemetry_guard = telemetry::init_telemetry();
 
     // Allocate the globals "singleton".
     //
@@ -1,6 +1,9 @@
 use std::collections::HashMap;
 
-use crate::shared_types::{InputPort, REQUEST_FAILED_NO_SERVER_AVAILABLE};
+use crate::access_log::{AccessLogMsg, AccessLogResult, AccessLogTx};
+use crate::shared_types::{
+    method_from_request_body, InputPort, METHOD_STATS_NOT_READ, REQUEST_FAILED_NO_SERVER_AVAILABLE,
+};
 use common::basic_types::*;
 
 use crate::shared_types::{
@@ -10,6 +13,7 @@ use crate::shared_types::{
 use crate::workers::RequestWorker;
 
 use bitflags::bitflags;
+use hyper::body::Bytes;
 
 use anyhow::{anyhow, Result};
 use tokio_graceful_shutdown::{FutureExt, SubsystemBuilder, SubsystemHandle};
@@ -28,9 +32,12 @@ pub struct NetmonMsg {
     server_idx: u8,
     // Interpretation depends on the event_id.
     timestamp: EpochTimestamp,
-    para32: [u32; 2],
+    para32: [u32; 4],
     para8: [u8; 2],
     para16: [u16; 1],
+    // JSON-RPC method of the user request (for the per method stats).
+    // Always None for anything else than a once per request report.
+    method: Option<Box<str>>,
 }
 
 impl NetmonMsg {
@@ -41,15 +48,20 @@ impl NetmonMsg {
             port_idx: u8::MAX,
             server_idx: u8::MAX,
             timestamp: Instant::now(),
-            para32: [0; 2],
+            para32: [0; 4],
             para8: [0; 2],
             para16: [0; 1],
+            method: None,
         }
     }
     pub fn server_idx(&self) -> u8 {
         self.server_idx
     }
 
+    pub fn port_idx(&self) -> u8 {
+        self.port_idx
+    }
+
     /*
     pub fn para32(&self) -> &[u32; 2] {
         &self.para32
@@ -81,6 +93,7 @@ bitflags! {
         const NEED_GLOBAL_READ_MUTEX = 0x02;
         const HEADER_SBSD_SERVER_IDX_SET = 0x04;
         const HEADER_SBSD_SERVER_HC_SET = 0x08;
+        const JSON_RPC_ERROR_RESP = 0x10;
     }
 }
 
@@ -125,11 +138,22 @@ pub struct NetworkMonitor {
 
 // This is how the ProxyHandler communicate with the NetworkMonitor.
 // It creates a ProxyHandlerReport instance and call into it.
+//
+// When the access log is enabled, the same (once per request) calls
+// also produce one AccessLogMsg.
 pub struct ProxyHandlerReport<'a> {
     tx_channel: &'a NetMonTx,
     flags: NetmonFlags,
     port_idx: InputPortIdx,
     handler_start: EpochTimestamp,
+
+    // Set once the request body is read (for the per method stats).
+    method: Option<Box<str>>,
+    req_size: u32,
+    resp_size: Option<usize>,
+
+    access_log_tx: Option<&'a AccessLogTx>,
+    access_log_req_body: Option<Bytes>,
 }
 
 impl<'a> ProxyHandlerReport<'a> {
@@ -143,6 +167,11 @@ impl<'a> ProxyHandlerReport<'a> {
             flags: NetmonFlags::empty(),
             port_idx,
             handler_start,
+            method: None,
+            req_size: 0,
+            resp_size: None,
+            access_log_tx: None,
+            access_log_req_body: None,
         }
     }
 
@@ -150,6 +179,61 @@ impl<'a> ProxyHandlerReport<'a> {
         &mut self.flags
     }
 
+    pub fn enable_access_log(&mut self, access_log_tx: &'a AccessLogTx) {
+        self.access_log_tx = Some(access_log_tx);
+    }
+
+    pub fn set_req_body(&mut self, req_body: &Bytes) {
+        self.req_size = req_body.len().try_into().unwrap_or(u32::MAX);
+        // Health checks are not user traffic (not in the per method stats).
+        if !self.flags.intersects(NetmonFlags::HEADER_SBSD_SERVER_HC_SET) {
+            self.method = Some(method_from_request_body(req_body).into_boxed_str());
+        }
+        if self.access_log_tx.is_some() {
+            self.access_log_req_body = Some(req_body.clone());
+        }
+    }
+
+    pub fn set_resp_size(&mut self, resp_size: usize) {
+        self.resp_size = Some(resp_size);
+    }
+
+    // The response is a JSON-RPC error (counted as an error in the per method stats).
+    pub fn set_json_rpc_error(&mut self) {
+        self.flags.insert(NetmonFlags::JSON_RPC_ERROR_RESP);
+    }
+
+    fn set_method_stats_params(&mut self, msg: &mut NetmonMsg) {
+        msg.method = self.method.take();
+        msg.para32[2] = self.req_size;
+        msg.para32[3] = self
+            .resp_size
+            .map_or(0, |size| size.try_into().unwrap_or(u32::MAX));
+    }
+
+    fn access_log(
+        &mut self,
+        server_idx: Option<TargetServerIdx>,
+        retry_count: u8,
+        result: AccessLogResult,
+        reason: Option<RequestFailedReason>,
+    ) {
+        if let Some(access_log_tx) = self.access_log_tx {
+            let msg = AccessLogMsg {
+                timestamp: chrono::Utc::now(),
+                server_idx,
+                retry_count,
+                latency_micros: duration_to_micros(self.handler_start.elapsed()),
+                req_body: self.access_log_req_body.take(),
+                resp_size: self.resp_size,
+                result,
+                reason,
+            };
+            // Never block the handler. Drop the log when the writer is falling behind.
+            let _ = access_log_tx.try_send(msg);
+        }
+    }
+
     pub async fn req_resp_ok(
         &mut self,
         server_idx: TargetServerIdx,
@@ -167,6 +251,9 @@ impl<'a> ProxyHandlerReport<'a> {
         msg.para32[0] = duration_to_micros(req_initiation_time - self.handler_start);
         msg.para32[1] = duration_to_micros(resp_received - req_initiation_time);
         msg.para8[0] = retry_count;
+        self.set_method_stats_params(&mut msg);
+
+        self.access_log(Some(server_idx), retry_count, AccessLogResult::Ok, None);
 
         // Send the message.
         self.tx_channel.send(msg).await.map_err(|e| {
@@ -194,6 +281,14 @@ impl<'a> ProxyHandlerReport<'a> {
         msg.para32[1] = duration_to_micros(resp_received - req_initiation_time);
         msg.para8[0] = retry_count;
         msg.para8[1] = reason;
+        self.set_method_stats_params(&mut msg);
+
+        self.access_log(
+            Some(server_idx),
+            retry_count,
+            AccessLogResult::Err,
+            Some(reason),
+        );
 
         // Send the message.
         self.tx_channel.send(msg).await.map_err(|e| {
@@ -214,6 +309,9 @@ impl<'a> ProxyHandlerReport<'a> {
         msg.para32[0] = duration_to_micros(error_time - self.handler_start);
         msg.para8[0] = retry_count;
         msg.para8[1] = reason;
+        self.set_method_stats_params(&mut msg);
+
+        self.access_log(None, retry_count, AccessLogResult::Fail, Some(reason));
 
         // Send the message.
         self.tx_channel.send(msg).await.map_err(|e| {
@@ -490,6 +588,28 @@ impl NetworkMonitor {
         None
     }
 
+    // Update the per method stats for a user traffic request.
+    //
+    // latency_micros is None when there was no response. A request that failed
+    // before its body was read has no method (counted as METHOD_STATS_NOT_READ, not
+    // mixed with the non JSON-RPC requests of METHOD_STATS_UNKNOWN).
+    fn update_method_stats(
+        input_ports: &mut ManagedVec<InputPort>,
+        msg: &NetmonMsg,
+        is_error: bool,
+        latency_micros: Option<u32>,
+    ) {
+        if let Some(input_port) = input_ports.get_mut(msg.port_idx) {
+            input_port.all_methods_stats.handle_report(
+                msg.method.as_deref().unwrap_or(METHOD_STATS_NOT_READ),
+                is_error,
+                msg.para32[2],
+                msg.para32[3],
+                latency_micros,
+            );
+        }
+    }
+
     fn update_selection_vectors(input_ports: &mut ManagedVec<InputPort>, msg: &NetmonMsg) {
         if let Some(input_port) = input_ports.get_mut(msg.port_idx) {
             input_port.update_selection_vectors();
@@ -534,6 +654,13 @@ impl NetworkMonitor {
                             }
                         } else {
                             // This is for the user traffic.
+                            Self::update_method_stats(
+                                input_ports,
+                                &cur_msg,
+                                cur_msg.flags.intersects(NetmonFlags::JSON_RPC_ERROR_RESP),
+                                Some(cur_msg.para32[0].saturating_add(cur_msg.para32[1])),
+                            );
+
                             if let Some(stats) = crate::NetworkMonitor::get_mut_all_servers_stats(
                                 input_ports,
                                 &cur_msg,
@@ -586,6 +713,13 @@ impl NetworkMonitor {
                             }
                         } else {
                             // An error in the response for the user traffic.
+                            Self::update_method_stats(
+                                input_ports,
+                                &cur_msg,
+                                true,
+                                Some(cur_msg.para32[0].saturating_add(cur_msg.para32[1])),
+                            );
+
                             if let Some(stats) = crate::NetworkMonitor::get_mut_all_servers_stats(
                                 input_ports,
                                 &cur_msg,
@@ -667,6 +801,13 @@ impl NetworkMonitor {
                                     stats.handle_req_failed(cur_msg.timestamp, cur_msg.para8[1]);
                                 }
                             }
+
+                            if !cur_msg
+                                .flags
+                                .intersects(NetmonFlags::HEADER_SBSD_SERVER_HC_SET)
+                            {
+                                Self::update_method_stats(input_ports, &cur_msg, true, None);
+                            }
                         }
                     }
                     _ => {
@@ -801,7 +942,7 @@ impl NetworkMonitor {
 
         // Start another thread to initiate requests toward target servers (e.g. health check)
         let (request_worker_tx, request_worker_rx) = tokio::sync::mpsc::channel(MPSC_Q_SIZE);
-        let request_worker = RequestWorker::new(request_worker_rx);
+        let request_worker = RequestWorker::new(self.globals.clone(), request_worker_rx);
         subsys.start(SubsystemBuilder::new("request-worker", |a| {
             request_worker.run(a)
         }));
@@ -1,9 +1,12 @@
-use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::net::SocketAddr;
 
 use std::sync::Arc;
 use std::time::Duration;
 
+use crate::access_log::{access_log_channel, AccessLogTx, AccessLogWriter};
 use crate::app_error::AppError;
+use crate::telemetry;
+use crate::tls;
 
 use common::basic_types::*;
 
@@ -29,6 +32,7 @@ use hyper::body::Bytes;
 use memchr::memmem;
 use serde::{Deserialize, Serialize};
 use tokio_graceful_shutdown::SubsystemHandle;
+use tracing::Instrument;
 
 // An application target the localhost:port
 //
@@ -41,6 +45,7 @@ pub struct SharedStates {
     port_idx: ManagedVecU8,
     client: reqwest::Client,
     netmon_tx: NetMonTx,
+    access_log_tx: AccessLogTx,
     globals: GlobalsProxyMT,
 }
 
@@ -109,6 +114,21 @@ impl ProxyServer {
     async fn proxy_handler(
         State(states): State<Arc<SharedStates>>,
         req: Request<Body>,
+    ) -> Result<Response<Body>, AppError> {
+        // Every attempt (including retries) is a child span of this one.
+        let span = tracing::info_span!(
+            "proxy_request",
+            otel.kind = "server",
+            port_idx = states.port_idx,
+            http.method = %req.method(),
+        );
+        telemetry::set_parent_from_headers(&span, req.headers());
+        Self::proxy_handler_inner(states, req).instrument(span).await
+    }
+
+    async fn proxy_handler_inner(
+        states: Arc<SharedStates>,
+        req: Request<Body>,
     ) -> Result<Response<Body>, AppError> {
         // Statistic Accumulation Design
         //
@@ -147,18 +167,26 @@ impl ProxyServer {
         let do_force_target_server_idx =
             ProxyServer::process_header_server_idx(&mut headers, &mut report);
 
-        let _ = ProxyServer::process_header_server_health_check(&mut headers, &mut report);
+        let is_health_check =
+            ProxyServer::process_header_server_health_check(&mut headers, &mut report);
         headers.remove(header::HOST); // Remove the host header (will be replace with the target server).
 
         let mut retry_count = 0;
 
         // Find which target servers to send to...
         let mut targets: Vec<(u8, String)> = Vec::new();
+        let mut target_aliases: Vec<String> = Vec::new(); // Only for tracing.
+        let mut target_headers: Vec<Option<Arc<axum::http::HeaderMap>>> = Vec::new();
         {
             let globals_read_guard = states.globals.read().await;
             let globals = &*globals_read_guard;
 
             if let Some(input_port) = globals.input_ports.get(states.port_idx) {
+                // Internal health checks are not user traffic, so not logged.
+                if input_port.is_access_log_enabled() && !is_health_check {
+                    report.enable_access_log(&states.access_log_tx);
+                }
+
                 // Check that the proxy is still enabled/running.
                 if !input_port.is_proxy_enabled() {
                     let _perf_report = report
@@ -189,6 +217,26 @@ impl ProxyServer {
                 } else {
                     input_port.get_best_target_servers(&mut targets, &handler_start)
                 }
+
+                // Custom headers and auth configured per link (cheap Arc clone).
+                for (server_idx, _) in &targets {
+                    target_headers.push(
+                        input_port
+                            .target_servers
+                            .get(*server_idx)
+                            .and_then(|ts| ts.extra_headers()),
+                    );
+                }
+
+                if !tracing::Span::current().is_disabled() {
+                    for (server_idx, _) in &targets {
+                        let alias = input_port
+                            .target_servers
+                            .get(*server_idx)
+                            .map_or_else(String::new, |ts| ts.alias());
+                        target_aliases.push(alias);
+                    }
+                }
             }
         }
         let targets = &targets; // Make immutable.
@@ -229,19 +277,43 @@ impl ProxyServer {
             }
         };
 
+        report.set_req_body(&bytes);
+
         const MAX_RETRIES: u8 = 4; // Must be >= 1
 
-        for (server_idx, target_uri) in targets.iter() {
+        for (target_pos, (server_idx, target_uri)) in targets.iter().enumerate() {
             let mut same_server_attempt = true;
 
             while same_server_attempt && retry_count < MAX_RETRIES {
                 same_server_attempt = false; // Will change to true in this loop if need to retry *same* server.
 
+                let attempt_span = tracing::info_span!(
+                    "proxy_attempt",
+                    otel.kind = "client",
+                    server = target_aliases.get(target_pos).map_or("", |s| s.as_str()),
+                    server_idx = *server_idx,
+                    retry = retry_count,
+                    outcome = tracing::field::Empty,
+                );
+
+                // Propagate this attempt context to the target server.
+                let mut attempt_headers = headers.clone();
+                if !attempt_span.is_disabled() {
+                    telemetry::inject_span_context(&attempt_span, &mut attempt_headers);
+                }
+
+                // Apply the link custom headers and auth (overrides the user headers).
+                if let Some(Some(extra_headers)) = target_headers.get(target_pos) {
+                    for (name, value) in extra_headers.iter() {
+                        attempt_headers.insert(name.clone(), value.clone());
+                    }
+                }
+
                 // Build the request toward the current target server.
                 let req_builder = states
                     .client
                     .request(method.clone(), target_uri)
-                    .headers(headers.clone())
+                    .headers(attempt_headers)
                     .body(bytes.clone());
 
                 // Following works also (if one day bytes and cloning won't be needed):
@@ -249,11 +321,12 @@ impl ProxyServer {
 
                 let req_initiation_time = EpochTimestamp::now();
                 // Execute the request.
-                let resp = req_builder.send().await;
+                let resp = req_builder.send().instrument(attempt_span.clone()).await;
 
                 let resp = match resp {
                     Ok(resp) => resp,
                     Err(_err) => {
+                        attempt_span.record("outcome", "send_failed");
                         // TODO Map _err to SendFailureReason for debugging.
 
                         // Report a 'send' error, which is a failure to connect to a target server.
@@ -280,6 +353,7 @@ impl ProxyServer {
                 let resp = match resp.error_for_status() {
                     Ok(resp) => resp,
                     Err(err) => {
+                        attempt_span.record("outcome", "http_error");
                         // Decide if trying another server or not depending if the HTTP
                         // problem is with the request or with the server.
                         // When in doubt, this will assume a problem with the server.
@@ -303,11 +377,12 @@ impl ProxyServer {
                     }
                 };
 
-                let resp_bytes = resp.bytes().await;
+                let resp_bytes = resp.bytes().instrument(attempt_span.clone()).await;
 
                 let resp_bytes = match resp_bytes {
                     Ok(resp_bytes) => resp_bytes,
                     Err(err) => {
+                        attempt_span.record("outcome", "resp_bytes_rx");
                         let _ = report
                             .req_resp_err(
                                 *server_idx,
@@ -345,6 +420,7 @@ impl ProxyServer {
                                 Self::is_retryable_sui_level_error(&bytes, &json_resp).await
                             {
                                 if safe_retry_approved {
+                                    attempt_span.record("outcome", "retryable_error");
                                     // Safe to retry after a delay of 1 secs.
                                     tokio::time::sleep(Duration::from_secs(1)).await;
                                     // Retry with a different server, except when there is no other server
@@ -360,6 +436,7 @@ impl ProxyServer {
 
                         // This is the standard way to handle JSON-RPC errors (with "error" object).
                         if let Some(err_obj) = json_resp["error"].as_object() {
+                            report.set_json_rpc_error();
                             if !err_obj.contains_key("data") {
                                 // Insert our own "data" field.
                                 let data =
@@ -375,6 +452,11 @@ impl ProxyServer {
                     }
                 }
 
+                let resp_size = modified_resp_bytes
+                    .as_ref()
+                    .map_or(resp_bytes.len(), |b| b.len());
+                report.set_resp_size(resp_size);
+
                 let builder = if let Some(modified_resp_bytes) = modified_resp_bytes {
                     Response::builder().body(Body::from(modified_resp_bytes))
                 } else {
@@ -384,6 +466,7 @@ impl ProxyServer {
                 let resp = match builder {
                     Ok(resp) => resp,
                     Err(err) => {
+                        attempt_span.record("outcome", "resp_builder");
                         let _ = report
                             .req_resp_err(
                                 *server_idx,
@@ -398,6 +481,7 @@ impl ProxyServer {
                     }
                 };
 
+                attempt_span.record("outcome", "ok");
                 let _ = report
                     .req_resp_ok(*server_idx, req_initiation_time, resp_received, retry_count)
                     .await;
@@ -421,6 +505,7 @@ impl ProxyServer {
         globals: GlobalsProxyMT,
         netmon_tx: NetMonTx,
     ) -> Result<()> {
+        let (access_log_tx, access_log_rx) = access_log_channel();
         let shared_states: Arc<SharedStates> = Arc::new(SharedStates {
             port_idx,
             client: reqwest::Client::builder()
@@ -430,12 +515,13 @@ impl ProxyServer {
                 .build()?,
             globals,
             netmon_tx,
+            access_log_tx,
         });
 
         // Validate access to the PortStates in the Globals with an async confirmation that
         // there is a ProxyServer running for it (which will get clear on any failure to
         // start or later on any reason for thread exit).
-        let port_number = {
+        let (port_number, workdir_idx, bind_address, tls_config) = {
             // Yes... it is amazingly complicated just to get access... but this is happening rarely
             // and is the price to pay to make "flexible and safe" multi-threaded globals in Rust.
             let mut globals_write_guard = shared_states.globals.write().await;
@@ -443,19 +529,60 @@ impl ProxyServer {
             let input_ports = &mut globals.input_ports;
             if let Some(input_port) = input_ports.get_mut(port_idx) {
                 input_port.report_proxy_server_starting();
-                input_port.port_number()
+                (
+                    input_port.port_number(),
+                    input_port.workdir_idx(),
+                    input_port.bind_address().to_string(),
+                    input_port.tls().clone(),
+                )
             } else {
                 log::error!("port {} not found", port_idx);
                 return Err(anyhow!("port {} not found", port_idx));
             }
         };
 
+        // The access log writer exits on its own when the shared_states are dropped.
+        let access_log_writer = AccessLogWriter::new(
+            shared_states.globals.clone(),
+            port_idx,
+            workdir_idx,
+            access_log_rx,
+        );
+        tokio::spawn(access_log_writer.run());
+
         let app = Router::new()
             .fallback(get(Self::proxy_handler).post(Self::proxy_handler))
             .with_state(shared_states.clone());
 
-        let bind_address = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), port_number);
-        log::info!("listening on {}", bind_address);
+        // Resolve the listening address and TLS settings before accepting any traffic.
+        let bind_setup = tls::resolve_bind_ip(&bind_address).and_then(|bind_ip| {
+            let rustls_config = if tls_config.enabled {
+                let self_signed_dir = common::shared_types::get_workdir_paths(workdir_idx)
+                    .workdir_root_path()
+                    .join("tls");
+                Some(tls::load_server_config(
+                    &tls_config,
+                    &self_signed_dir,
+                    bind_ip,
+                )?)
+            } else {
+                None
+            };
+            Ok((SocketAddr::new(bind_ip, port_number), rustls_config))
+        });
+
+        let (bind_address, rustls_config) = match bind_setup {
+            Ok(bind_setup) => bind_setup,
+            Err(e) => {
+                log::error!("port {} setup failed: {}", port_number, e);
+                let mut globals_write_guard = shared_states.globals.write().await;
+                let globals = &mut *globals_write_guard;
+                if let Some(input_port) = globals.input_ports.get_mut(port_idx) {
+                    input_port.report_proxy_server_not_running();
+                }
+                return Err(e);
+            }
+        };
 
         let handle = axum_server::Handle::new();
 
@@ -463,11 +590,22 @@ impl ProxyServer {
         tokio::spawn(graceful_shutdown(subsys, handle.clone()));
 
         //let listener = tokio::net::TcpListener::bind(&bind_address).await.unwrap();
-        axum_server::bind(bind_address)
-            .handle(handle)
-            .serve(app.into_make_service())
-            .await
-            .unwrap();
+        if let Some(rustls_config) = rustls_config {
+            log::info!("listening on {} (TLS)", bind_address);
+            let rustls_config = axum_server::tls_rustls::RustlsConfig::from_config(rustls_config);
+            axum_server::bind_rustls(bind_address, rustls_config)
+                .handle(handle)
+                .serve(app.into_make_service())
+                .await
+                .unwrap();
+        } else {
+            log::info!("listening on {}", bind_address);
+            axum_server::bind(bind_address)
+                .handle(handle)
+                .serve(app.into_make_service())
+                .await
+                .unwrap();
+        }
 
         log::info!("stopped for {}", bind_address);
 
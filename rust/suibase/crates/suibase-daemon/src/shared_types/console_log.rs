@@ -0,0 +1,206 @@
+// Format of the console log files (see log_worker.rs).
+//
+// One file per package:
+//   ~/suibase/workdirs/{workdir}/logs/console/{package_name}.log
+//
+// Rotated files are {package_name}.log.1 (most recent) to {package_name}.log.N
+//
+// Line format:
+//   [Time] [Level] [Sender] "text" objects:[ {"type":"package_id::module::type", "address":"0x123", "object":{}}, ... ]
+//
+// The "objects:" array is present only when the text refers to objects with
+// "$object:<address>" labels.
+use std::path::{Path, PathBuf};
+
+use chrono::{TimeZone, Utc};
+use common::basic_types;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+pub const CONSOLE_OBJECT_LABEL: &str = "$object:";
+
+// Size of the chunks read backward by tail_log_file.
+const TAIL_CHUNK_BYTES: u64 = 64 * 1024;
+
+pub fn level_name(level: u8) -> &'static str {
+    match level {
+        basic_types::EVENT_LEVEL_ERROR => "ERROR",
+        basic_types::EVENT_LEVEL_WARN => "WARN",
+        basic_types::EVENT_LEVEL_INFO => "INFO",
+        basic_types::EVENT_LEVEL_DEBUG => "DEBUG",
+        basic_types::EVENT_LEVEL_TRACE => "TRACE",
+        _ => "INVALID",
+    }
+}
+
+// Accepts a level name (any case) or its number.
+pub fn level_from_str(level: &str) -> Option<u8> {
+    if let Ok(level) = level.parse::<u8>() {
+        return (basic_types::EVENT_LEVEL_MIN..=basic_types::EVENT_LEVEL_MAX)
+            .contains(&level)
+            .then_some(level);
+    }
+    (basic_types::EVENT_LEVEL_MIN..=basic_types::EVENT_LEVEL_MAX)
+        .find(|l| level_name(*l).eq_ignore_ascii_case(level))
+}
+
+// Level of a line written by format_log_line.
+pub fn parse_log_line_level(line: &str) -> Option<u8> {
+    let after_time = &line[line.find("] [")? + 3..];
+    let name = &after_time[..after_time.find(']')?];
+    level_from_str(name)
+}
+
+// Addresses referred with "$object:<address>" in a console message (no duplicates).
+pub fn object_labels(message: &str) -> Vec<String> {
+    let mut addresses: Vec<String> = Vec::new();
+    for (start, _) in message.match_indices(CONSOLE_OBJECT_LABEL) {
+        let address: String = message[start + CONSOLE_OBJECT_LABEL.len()..]
+            .chars()
+            .take_while(|c| c.is_ascii_hexdigit() || *c == 'x')
+            .collect();
+        if address.starts_with("0x") && address.len() > 2 && !addresses.contains(&address) {
+            addresses.push(address);
+        }
+    }
+    addresses
+}
+
+pub fn format_log_line(
+    timestamp_ms: u64,
+    level: u8,
+    sender: &str,
+    message: &str,
+    objects: &[serde_json::Value],
+) -> String {
+    let time = Utc
+        .timestamp_millis_opt(timestamp_ms as i64)
+        .single()
+        .map(|t| t.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string())
+        .unwrap_or_else(|| timestamp_ms.to_string());
+    // The text is quoted as a JSON string (escapes any newline or quote).
+    let text = serde_json::Value::String(message.to_string());
+    let mut line = format!("[{}] [{}] [{}] {}", time, level_name(level), sender, text);
+    if !objects.is_empty() {
+        line.push_str(&format!(" objects:{}", serde_json::Value::from(objects)));
+    }
+    line
+}
+
+// Path of the log file of a package (name reduced to characters safe for a file name).
+pub fn console_log_path(workdir_idx: basic_types::WorkdirIdx, package_name: &str) -> PathBuf {
+    let file_name: String = package_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    common::shared_types::get_workdir_paths(workdir_idx)
+        .workdir_root_path()
+        .join("logs")
+        .join("console")
+        .join(format!("{}.log", file_name))
+}
+
+// Last n_lines of a log file with a level up to max_level (oldest first).
+//
+// The file is read backward from its end, so a large file is not loaded whole.
+pub async fn tail_log_file(
+    path: &Path,
+    n_lines: usize,
+    max_level: u8,
+) -> std::io::Result<Vec<String>> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut pos = file.metadata().await?.len();
+    let mut selected: Vec<String> = Vec::new();
+    // Start of a line split by a chunk boundary (completed by the next chunk read).
+    let mut partial: Vec<u8> = Vec::new();
+    while pos > 0 && selected.len() < n_lines {
+        let chunk_len = TAIL_CHUNK_BYTES.min(pos);
+        pos -= chunk_len;
+        file.seek(std::io::SeekFrom::Start(pos)).await?;
+        let mut chunk = vec![0u8; chunk_len as usize];
+        file.read_exact(&mut chunk).await?;
+        chunk.extend_from_slice(&partial);
+
+        let mut segments: Vec<&[u8]> = chunk.split(|b| *b == b'\n').collect();
+        partial = if pos > 0 {
+            segments.remove(0).to_vec()
+        } else {
+            Vec::new()
+        };
+        for segment in segments.into_iter().rev() {
+            if selected.len() == n_lines {
+                break;
+            }
+            let line = String::from_utf8_lossy(segment);
+            if !line.is_empty()
+                && parse_log_line_level(&line).is_none_or(|level| level <= max_level)
+            {
+                selected.push(line.into_owned());
+            }
+        }
+    }
+    selected.reverse();
+    Ok(selected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_console_log_line() {
+        assert_eq!(
+            object_labels("counter $object:0x5f is $object:0x5f or $object:none"),
+            vec!["0x5f".to_string()]
+        );
+        let objects = vec![serde_json::json!({"address": "0x5f"})];
+        let line = format_log_line(1703895010111, 3, "0xf7ae", "hello \"world\"", &objects);
+        assert_eq!(
+            line,
+            r#"[2023-12-30T00:10:10.111Z] [INFO] [0xf7ae] "hello \"world\"" objects:[{"address":"0x5f"}]"#
+        );
+        assert_eq!(parse_log_line_level(&line), Some(3));
+        assert_eq!(level_from_str("debug"), Some(4));
+        assert_eq!(level_from_str("9"), None);
+    }
+
+    #[tokio::test]
+    async fn test_tail_log_file() {
+        let path = std::env::temp_dir().join(format!("tail_log_file_{}.log", std::process::id()));
+        // Lines longer than a chunk to cover the lines split by a chunk boundary.
+        let text = "x".repeat(TAIL_CHUNK_BYTES as usize / 3);
+        let lines: Vec<String> = (0..10)
+            .map(|i| {
+                format_log_line(
+                    1703895010111,
+                    3 + (i % 2),
+                    "0xf7ae",
+                    &format!("{}{}", i, text),
+                    &[],
+                )
+            })
+            .collect();
+        std::fs::write(&path, lines.join("\n") + "\n").unwrap();
+
+        let tail = tail_log_file(&path, 3, basic_types::EVENT_LEVEL_MAX)
+            .await
+            .unwrap();
+        assert_eq!(tail, lines[7..].to_vec());
+        // Only the INFO lines (even i).
+        let tail = tail_log_file(&path, 2, basic_types::EVENT_LEVEL_INFO)
+            .await
+            .unwrap();
+        assert_eq!(tail, vec![lines[6].clone(), lines[8].clone()]);
+        let tail = tail_log_file(&path, 100, basic_types::EVENT_LEVEL_MAX)
+            .await
+            .unwrap();
+        assert_eq!(tail, lines);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
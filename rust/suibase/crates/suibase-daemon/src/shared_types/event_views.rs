@@ -0,0 +1,255 @@
+// Typed SQL views over the stored Sui events.
+//
+// The user events are stored as JSON (see db_worker.rs). For every event type, the
+// layout of the Move struct (from "sui_getNormalizedMoveStruct") is used to create
+// a view with one column per field, with a proper SQLite type.
+//
+// Example, for "0xe065...::counter::CounterChanged" in localnet:
+//
+//   SELECT count, by_address FROM localnet_sui_view_counter_CounterChanged_e065;
+//
+// The view name ends with the first hex digits of the package id, so the packages
+// with the same module and struct names (e.g. republications) each have their view.
+//
+// A field named like one of the VIEW_FIXED_COLUMNS is renamed "field_{name}".
+use serde::{Deserialize, Serialize};
+
+const VIEW_PACKAGE_ID_DIGITS: usize = 8;
+
+// Columns of every view, before the fields of the event.
+const VIEW_FIXED_COLUMNS: [&str; 5] = [
+    "id",
+    "package_instance_id",
+    "timestamp",
+    "sender",
+    "tx_digest",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MoveFieldColumn {
+    pub name: String,
+    pub sqlite_type: String, // INTEGER or TEXT
+}
+
+impl MoveFieldColumn {
+    // Name of the column in the view.
+    pub fn column_name(&self) -> String {
+        if VIEW_FIXED_COLUMNS.contains(&self.name.as_str()) {
+            format!("field_{}", self.name)
+        } else {
+            self.name.clone()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MoveStructLayout {
+    pub package_id: String, // With leading 0x
+    pub module: String,
+    pub name: String,
+    pub fields: Vec<MoveFieldColumn>,
+}
+
+// Move identifier: [A-Za-z_][A-Za-z0-9_]*
+//
+// Only these are used to build the SQL of a view (they are not escaped).
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+// "0x" followed by hex digits.
+fn is_package_id(s: &str) -> bool {
+    match s.strip_prefix("0x") {
+        Some(hex) => !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit()),
+        None => false,
+    }
+}
+
+// Split a Move event type "0x..::module::Struct<T>" into (package_id, module, struct).
+//
+// Type parameters are ignored (one view for all instantiations).
+pub fn parse_event_type(event_type: &str) -> Option<(String, String, String)> {
+    let base = event_type.split('<').next()?;
+    let mut parts = base.split("::");
+    let package_id = parts.next()?;
+    let module = parts.next()?;
+    let name = parts.next()?;
+    if parts.next().is_some()
+        || !is_package_id(package_id)
+        || !is_identifier(module)
+        || !is_identifier(name)
+    {
+        return None;
+    }
+    Some((package_id.to_string(), module.to_string(), name.to_string()))
+}
+
+// SQLite type for a field, given its normalized Move type (JSON).
+//
+// Up to u32 are INTEGER. The u64/u128/u256 are TEXT because SQLite integers are
+// signed 64 bits (e.g. a u64::MAX balance would not fit). Vectors and structs are
+// kept as JSON (TEXT).
+pub fn sqlite_type(move_type: &serde_json::Value) -> &'static str {
+    match move_type.as_str() {
+        Some("Bool") | Some("U8") | Some("U16") | Some("U32") => "INTEGER",
+        _ => "TEXT",
+    }
+}
+
+impl MoveStructLayout {
+    // Parse a "sui_getNormalizedMoveStruct" result.
+    //
+    // Expected JSON: {"abilities":{...},"typeParameters":[...],"fields":[{"name":"count","type":"U64"},...]}
+    pub fn from_normalized_struct(
+        package_id: &str,
+        module: &str,
+        name: &str,
+        normalized: &serde_json::Value,
+    ) -> Option<Self> {
+        if !is_package_id(package_id) || !is_identifier(module) || !is_identifier(name) {
+            return None;
+        }
+        let fields = normalized.get("fields")?.as_array()?;
+        let mut columns = Vec::with_capacity(fields.len());
+        for field in fields {
+            let field_name = field.get("name")?.as_str()?;
+            if !is_identifier(field_name) {
+                return None;
+            }
+            let field_type = field.get("type")?;
+            columns.push(MoveFieldColumn {
+                name: field_name.to_string(),
+                sqlite_type: sqlite_type(field_type).to_string(),
+            });
+        }
+        Some(Self {
+            package_id: package_id.to_string(),
+            module: module.to_string(),
+            name: name.to_string(),
+            fields: columns,
+        })
+    }
+
+    pub fn view_name(&self, workdir_name: &str) -> String {
+        let package_id = self.package_id.trim_start_matches("0x");
+        let short_id = &package_id[..package_id.len().min(VIEW_PACKAGE_ID_DIGITS)];
+        format!(
+            "{}_sui_view_{}_{}_{}",
+            workdir_name, self.module, self.name, short_id
+        )
+    }
+
+    // SQL to (re)create the view over an events table.
+    //
+    // The event_json column is {"sender":..., "message": "<event result as JSON string>"}.
+    pub fn create_view_sql(&self, workdir_name: &str, events_table: &str) -> Vec<String> {
+        let view_name = self.view_name(workdir_name);
+        let event = "json_extract(event_json, '$.message')";
+        let mut columns = vec![
+            "id".to_string(),
+            "package_instance_id".to_string(),
+            "timestamp".to_string(),
+            format!("json_extract({}, '$.sender') AS sender", event),
+            format!("json_extract({}, '$.id.txDigest') AS tx_digest", event),
+        ];
+        for field in &self.fields {
+            columns.push(format!(
+                "CAST(json_extract({0}, '$.parsedJson.{1}') AS {2}) AS \"{3}\"",
+                event,
+                field.name,
+                field.sqlite_type,
+                field.column_name()
+            ));
+        }
+        let type_prefix = format!("{}::{}::{}", self.package_id, self.module, self.name);
+        vec![
+            format!("DROP VIEW IF EXISTS \"{}\"", view_name),
+            format!(
+                "CREATE VIEW \"{0}\" AS SELECT {1} FROM {2}
+                WHERE json_extract({3}, '$.type') = '{4}'
+                OR json_extract({3}, '$.type') LIKE '{4}<%'",
+                view_name,
+                columns.join(", "),
+                events_table,
+                event,
+                type_prefix
+            ),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_move_struct_layout() {
+        assert_eq!(
+            parse_event_type("0xe065::counter::CounterChanged<0x2::sui::SUI>"),
+            Some((
+                "0xe065".to_string(),
+                "counter".to_string(),
+                "CounterChanged".to_string()
+            ))
+        );
+        assert_eq!(parse_event_type("counter::CounterChanged"), None);
+        assert_eq!(parse_event_type("0xe065::counter::Counter'Changed"), None);
+        assert_eq!(parse_event_type("0xe065::counter;::CounterChanged"), None);
+        assert_eq!(parse_event_type("0xe06g::counter::CounterChanged"), None);
+
+        let normalized = serde_json::json!({
+            "fields": [
+                {"name": "count", "type": "U64"},
+                {"name": "by_address", "type": "Address"},
+                {"name": "total", "type": "U128"},
+                {"name": "level", "type": "U8"},
+                {"name": "sender", "type": "Address"},
+            ]
+        });
+        let layout = MoveStructLayout::from_normalized_struct(
+            "0xe065",
+            "counter",
+            "CounterChanged",
+            &normalized,
+        )
+        .unwrap();
+        let types: Vec<&str> = layout
+            .fields
+            .iter()
+            .map(|f| f.sqlite_type.as_str())
+            .collect();
+        assert_eq!(types, vec!["TEXT", "TEXT", "TEXT", "INTEGER", "TEXT"]);
+        assert_eq!(
+            layout.view_name("localnet"),
+            "localnet_sui_view_counter_CounterChanged_e065"
+        );
+        let layout = MoveStructLayout {
+            package_id: "0x2a61bf21c3f4ad5d6cbd4b7c1ac0b5a5".to_string(),
+            ..layout
+        };
+        assert_eq!(
+            layout.view_name("localnet"),
+            "localnet_sui_view_counter_CounterChanged_2a61bf21"
+        );
+        let sql = layout.create_view_sql("localnet", "localnet_sui_event_user_0");
+        assert!(
+            sql[1].starts_with("CREATE VIEW \"localnet_sui_view_counter_CounterChanged_2a61bf21\"")
+        );
+        assert!(sql[1].contains("AS \"count\""));
+        assert!(sql[1].contains("'$.parsedJson.sender') AS TEXT) AS \"field_sender\""));
+
+        let normalized = serde_json::json!({
+            "fields": [{"name": "count\" FROM x; --", "type": "U64"}]
+        });
+        assert_eq!(
+            MoveStructLayout::from_normalized_struct("0xe065", "counter", "Evt", &normalized),
+            None
+        );
+    }
+}
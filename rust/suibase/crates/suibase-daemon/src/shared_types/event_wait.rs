@@ -0,0 +1,108 @@
+// Filter of the "waitForEvent" API (mostly for integration tests).
+//
+// Applies to the user events (not the console events). All specified criteria
+// must match. Example:
+//
+//   { "package": "counter",
+//     "eventType": "0xe065...::counter::CounterChanged",
+//     "fields": { "count": 5 } }
+//
+// "fields" is matched against the "parsedJson" of the event. Only the specified
+// fields are compared (recursively for nested objects). A number also matches its
+// string representation (Sui shows the u64 as strings).
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventWaitFilter {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>, // Package name or package id.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event_type: Option<String>, // Move type prefix.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sender: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fields: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
+// Parameters of a "wait_for_event" command (JSON in GenericChannelMsg::data_json).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EventWaitParams {
+    pub filter: EventWaitFilter,
+    // Id of a user event (see the "id" in the response). The events already persisted
+    // after it are also considered. When None, only the new events are considered.
+    pub after_id: Option<i64>,
+}
+
+fn json_subset_match(expected: &serde_json::Value, actual: &serde_json::Value) -> bool {
+    use serde_json::Value;
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => expected.iter().all(|(k, v)| {
+            actual
+                .get(k)
+                .is_some_and(|actual| json_subset_match(v, actual))
+        }),
+        (Value::Number(n), Value::String(s)) => n.to_string() == *s,
+        (Value::Bool(b), Value::String(s)) => b.to_string() == *s,
+        _ => expected == actual,
+    }
+}
+
+impl EventWaitFilter {
+    // event is the Sui event (the "result" of the subscription notification).
+    pub fn matches(&self, package_name: &str, package_id: &str, event: &serde_json::Value) -> bool {
+        if let Some(package) = &self.package {
+            let package_id = package_id.trim_start_matches("0x");
+            if package != package_name && package.trim_start_matches("0x") != package_id {
+                return false;
+            }
+        }
+        if let Some(event_type) = &self.event_type {
+            let type_str = event.get("type").and_then(|v| v.as_str()).unwrap_or("");
+            if !type_str.starts_with(event_type.as_str()) {
+                return false;
+            }
+        }
+        if let Some(sender) = &self.sender {
+            if event.get("sender").and_then(|v| v.as_str()) != Some(sender.as_str()) {
+                return false;
+            }
+        }
+        if let Some(fields) = &self.fields {
+            let expected = serde_json::Value::Object(fields.clone());
+            let actual = event.get("parsedJson").unwrap_or(&serde_json::Value::Null);
+            if !json_subset_match(&expected, actual) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_wait_filter() {
+        let event = serde_json::json!({
+            "type": "0xe065::counter::CounterChanged",
+            "sender": "0xf7ae",
+            "parsedJson": { "count": "5", "by": { "name": "bob" } }
+        });
+        let filter: EventWaitFilter = serde_json::from_value(serde_json::json!({
+            "package": "0xe065",
+            "eventType": "0xe065::counter::",
+            "fields": { "count": 5, "by": { "name": "bob" } }
+        }))
+        .unwrap();
+        assert!(filter.matches("counter", "e065", &event));
+        assert!(!filter.matches("counter", "e066", &event));
+
+        let filter = EventWaitFilter {
+            sender: Some("0x1234".to_string()),
+            ..Default::default()
+        };
+        assert!(!filter.matches("counter", "e065", &event));
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::{HashSet, VecDeque};
+
 use common::basic_types::AutoSizeVec;
 
 #[derive(Debug, Clone)]
@@ -48,3 +50,94 @@ impl Default for GlobalsEventsDataST {
         Self::new()
     }
 }
+
+// Identify a Sui event uniquely (txDigest, eventSeq) for a given subscription uuid.
+//
+// The uuid is part of the key because the same event can match more than one
+// subscription (e.g. a published package and a "MoveEventType" in suibase.yaml).
+pub type SuiEventKey = (String, String, u64);
+
+// Bounded window of the most recently seen Sui events.
+//
+// Used by the EventsWriterWorker to drop the duplicate events received from multiple
+// websocket connections (one per link). When full, the oldest key is forgotten.
+#[derive(Debug)]
+pub struct SuiEventsDedup {
+    capacity: usize,
+    seen: HashSet<SuiEventKey>,
+    order: VecDeque<SuiEventKey>,
+}
+
+impl SuiEventsDedup {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            seen: HashSet::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    // Extract the key from a "suix_subscribeEvent" notification.
+    //
+    // Expected JSON: {"params":{"result":{"id":{"txDigest":"3Vua...","eventSeq":"1"},...}}}
+    pub fn key_from_json(uuid: &str, data_json: &serde_json::Value) -> Option<SuiEventKey> {
+        let id = data_json.get("params")?.get("result")?.get("id")?;
+        let tx_digest = id.get("txDigest")?.as_str()?;
+        let event_seq = id.get("eventSeq")?;
+        // eventSeq is a string, but accept a number as well.
+        let event_seq = match event_seq.as_str() {
+            Some(event_seq) => event_seq.parse::<u64>().ok()?,
+            None => event_seq.as_u64()?,
+        };
+        Some((uuid.to_string(), tx_digest.to_string(), event_seq))
+    }
+
+    // Returns true when the key was not seen before (and remember it).
+    pub fn insert(&mut self, key: SuiEventKey) -> bool {
+        if self.seen.contains(&key) {
+            return false;
+        }
+        if self.order.len() >= self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        self.seen.insert(key.clone());
+        self.order.push_back(key);
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sui_events_dedup() {
+        let json = serde_json::json!({
+            "params": {"result": {"id": {"txDigest": "3Vua", "eventSeq": "1"}}}
+        });
+        let key = SuiEventsDedup::key_from_json("uuid", &json).unwrap();
+        assert_eq!(key, ("uuid".to_string(), "3Vua".to_string(), 1));
+
+        let mut dedup = SuiEventsDedup::new(3);
+        assert!(dedup.insert(key.clone()));
+        assert!(!dedup.insert(key.clone()));
+        // Same event for another subscription is not a duplicate.
+        let other = SuiEventsDedup::key_from_json("other", &json).unwrap();
+        assert!(dedup.insert(other));
+        assert!(dedup.insert(("uuid".to_string(), "3Vua".to_string(), 2)));
+        assert!(dedup.insert(("uuid".to_string(), "3Vub".to_string(), 0)));
+        // Oldest forgotten once the window is full.
+        assert_eq!(dedup.len(), 3);
+        assert!(dedup.insert(key));
+    }
+}
@@ -0,0 +1,96 @@
+// Rate limits of the faucet manager (see faucet_poller.rs).
+//
+// One FaucetST per workdir, shared by the auto-refill and the "requestFunds" API. The
+// lock is held for the whole funding, so the fundings of a workdir are serialized.
+//
+// Limits (see faucet_* in suibase.yaml):
+//   - faucet_min_interval_secs between two fundings of the same address.
+//   - faucet_max_requests_per_hour for all the addresses of the workdir.
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+const ONE_HOUR: Duration = Duration::from_secs(3600);
+
+#[derive(Debug, Default)]
+pub struct FaucetST {
+    last_funding: HashMap<String, Instant>, // Key is the address.
+    recent_fundings: VecDeque<Instant>,     // Within the last hour (oldest first).
+}
+
+impl FaucetST {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Err is a user readable reason.
+    pub fn check_rate_limits(
+        &mut self,
+        address: &str,
+        now: Instant,
+        min_interval: Duration,
+        max_per_hour: u32,
+    ) -> Result<(), String> {
+        while self
+            .recent_fundings
+            .front()
+            .is_some_and(|t| now.duration_since(*t) >= ONE_HOUR)
+        {
+            self.recent_fundings.pop_front();
+        }
+        if let Some(last) = self.last_funding.get(address) {
+            let elapsed = now.duration_since(*last);
+            if elapsed < min_interval {
+                return Err(format!(
+                    "{} funded {}s ago (minimum interval is {}s)",
+                    address,
+                    elapsed.as_secs(),
+                    min_interval.as_secs()
+                ));
+            }
+        }
+        if max_per_hour > 0 && self.recent_fundings.len() >= max_per_hour as usize {
+            return Err(format!(
+                "limit of {} fundings per hour reached",
+                max_per_hour
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn record_funding(&mut self, address: &str, now: Instant) {
+        self.last_funding.insert(address.to_string(), now);
+        self.recent_fundings.push_back(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_faucet_rate_limits() {
+        let mut faucet = FaucetST::new();
+        let start = Instant::now();
+        let min_interval = Duration::from_secs(60);
+        assert!(faucet
+            .check_rate_limits("0xa", start, min_interval, 2)
+            .is_ok());
+        faucet.record_funding("0xa", start);
+        assert!(faucet
+            .check_rate_limits("0xa", start + Duration::from_secs(10), min_interval, 2)
+            .is_err());
+
+        let later = start + Duration::from_secs(61);
+        assert!(faucet
+            .check_rate_limits("0xa", later, min_interval, 2)
+            .is_ok());
+        faucet.record_funding("0xb", later);
+        assert!(faucet
+            .check_rate_limits("0xc", later, min_interval, 2)
+            .is_err());
+        // The hourly window moves.
+        assert!(faucet
+            .check_rate_limits("0xc", start + ONE_HOUR, min_interval, 2)
+            .is_ok());
+    }
+}
@@ -11,15 +11,18 @@
 // Note: This app also uses message passing between threads to minimize sharing. See NetmonMsg as an example.
 use std::sync::Arc;
 
-use crate::api::{Versioned, VersionsResponse, WorkdirPackagesResponse, WorkdirStatusResponse};
+use crate::api::{
+    AddressBalancesResponse, Versioned, VersionsResponse, WorkdirPackagesResponse,
+    WorkdirStatusResponse,
+};
 use crate::shared_types::InputPort;
 use common::basic_types::{ManagedVec, WorkdirIdx};
 use common::shared_types::{
-    GlobalsWorkdirConfigST, WORKDIR_IDX_DEVNET, WORKDIR_IDX_LOCALNET, WORKDIR_IDX_MAINNET,
-    WORKDIR_IDX_TESTNET,
+    GlobalsWorkdirConfigST, SUIBASE_API_PORT, WORKDIR_IDX_DEVNET, WORKDIR_IDX_LOCALNET,
+    WORKDIR_IDX_MAINNET, WORKDIR_IDX_TESTNET,
 };
 
-use super::GlobalsEventsDataST;
+use super::{FaucetST, GlobalsEventsDataST, SupervisorST};
 
 #[derive(Debug)]
 pub struct GlobalsProxyST {
@@ -102,6 +105,24 @@ impl Default for GlobalsWorkdirPackagesST {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct GlobalsWorkdirBalancesST {
+    // Response of the getAddressBalances API (see balance_poller.rs).
+    pub ui: Option<Versioned<AddressBalancesResponse>>,
+}
+
+impl GlobalsWorkdirBalancesST {
+    pub fn new() -> Self {
+        Self { ui: None }
+    }
+}
+
+impl Default for GlobalsWorkdirBalancesST {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[derive(Debug)]
 pub struct GlobalsAPIMutexST {
     pub last_get_workdir_status_time: tokio::time::Instant,
@@ -138,7 +159,7 @@ impl GlobalsConfigST {
     pub fn new() -> Self {
         Self {
             daemon_ip: "localhost".to_string(),
-            daemon_port: 44399,
+            daemon_port: SUIBASE_API_PORT,
         }
     }
 }
@@ -162,7 +183,10 @@ pub type GlobalsConfigMT = Arc<tokio::sync::RwLock<GlobalsConfigST>>;
 pub type GlobalsWorkdirConfigMT = Arc<tokio::sync::RwLock<GlobalsWorkdirConfigST>>;
 pub type GlobalsWorkdirStatusMT = Arc<tokio::sync::RwLock<GlobalsWorkdirStatusST>>;
 pub type GlobalsWorkdirPackagesMT = Arc<tokio::sync::RwLock<GlobalsWorkdirPackagesST>>;
+pub type GlobalsWorkdirBalancesMT = Arc<tokio::sync::RwLock<GlobalsWorkdirBalancesST>>;
 pub type GlobalsEventsDataMT = Arc<tokio::sync::RwLock<GlobalsEventsDataST>>;
+pub type GlobalsFaucetMT = Arc<tokio::sync::Mutex<FaucetST>>;
+pub type GlobalsSupervisorMT = Arc<tokio::sync::Mutex<SupervisorST>>;
 pub type GlobalsAPIMutexMT = Arc<tokio::sync::Mutex<GlobalsAPIMutexST>>;
 
 // A convenient way to refer to all globals at once.
@@ -205,6 +229,24 @@ pub struct Globals {
     pub packages_testnet: GlobalsWorkdirPackagesMT,
     pub packages_mainnet: GlobalsWorkdirPackagesMT,
 
+    // Balances of the tracked addresses.
+    pub balances_localnet: GlobalsWorkdirBalancesMT,
+    pub balances_devnet: GlobalsWorkdirBalancesMT,
+    pub balances_testnet: GlobalsWorkdirBalancesMT,
+    pub balances_mainnet: GlobalsWorkdirBalancesMT,
+
+    // Faucet rate limits (a funding holds the lock, see faucet_poller.rs).
+    pub faucet_localnet: GlobalsFaucetMT,
+    pub faucet_devnet: GlobalsFaucetMT,
+    pub faucet_testnet: GlobalsFaucetMT,
+    pub faucet_mainnet: GlobalsFaucetMT,
+
+    // Local services supervision (see supervisor_poller.rs).
+    pub supervisor_localnet: GlobalsSupervisorMT,
+    pub supervisor_devnet: GlobalsSupervisorMT,
+    pub supervisor_testnet: GlobalsSupervisorMT,
+    pub supervisor_mainnet: GlobalsSupervisorMT,
+
     // In-memory access to events data of actively monitored modules.
     pub events_data_localnet: GlobalsEventsDataMT,
     pub events_data_devnet: GlobalsEventsDataMT,
@@ -245,6 +287,18 @@ impl Globals {
             packages_devnet: Arc::new(tokio::sync::RwLock::new(GlobalsWorkdirPackagesST::new())),
             packages_testnet: Arc::new(tokio::sync::RwLock::new(GlobalsWorkdirPackagesST::new())),
             packages_mainnet: Arc::new(tokio::sync::RwLock::new(GlobalsWorkdirPackagesST::new())),
+            balances_localnet: Arc::new(tokio::sync::RwLock::new(GlobalsWorkdirBalancesST::new())),
+            balances_devnet: Arc::new(tokio::sync::RwLock::new(GlobalsWorkdirBalancesST::new())),
+            balances_testnet: Arc::new(tokio::sync::RwLock::new(GlobalsWorkdirBalancesST::new())),
+            balances_mainnet: Arc::new(tokio::sync::RwLock::new(GlobalsWorkdirBalancesST::new())),
+            faucet_localnet: Arc::new(tokio::sync::Mutex::new(FaucetST::new())),
+            faucet_devnet: Arc::new(tokio::sync::Mutex::new(FaucetST::new())),
+            faucet_testnet: Arc::new(tokio::sync::Mutex::new(FaucetST::new())),
+            faucet_mainnet: Arc::new(tokio::sync::Mutex::new(FaucetST::new())),
+            supervisor_localnet: Arc::new(tokio::sync::Mutex::new(SupervisorST::new())),
+            supervisor_devnet: Arc::new(tokio::sync::Mutex::new(SupervisorST::new())),
+            supervisor_testnet: Arc::new(tokio::sync::Mutex::new(SupervisorST::new())),
+            supervisor_mainnet: Arc::new(tokio::sync::Mutex::new(SupervisorST::new())),
             events_data_localnet: Arc::new(tokio::sync::RwLock::new(GlobalsEventsDataST::new())),
             events_data_devnet: Arc::new(tokio::sync::RwLock::new(GlobalsEventsDataST::new())),
             events_data_testnet: Arc::new(tokio::sync::RwLock::new(GlobalsEventsDataST::new())),
@@ -287,6 +341,36 @@ impl Globals {
         }
     }
 
+    pub fn get_balances(&self, workdir_idx: WorkdirIdx) -> &GlobalsWorkdirBalancesMT {
+        match workdir_idx {
+            WORKDIR_IDX_LOCALNET => &self.balances_localnet,
+            WORKDIR_IDX_DEVNET => &self.balances_devnet,
+            WORKDIR_IDX_TESTNET => &self.balances_testnet,
+            WORKDIR_IDX_MAINNET => &self.balances_mainnet,
+            _ => panic!("Invalid workdir_idx {}", workdir_idx),
+        }
+    }
+
+    pub fn get_faucet(&self, workdir_idx: WorkdirIdx) -> &GlobalsFaucetMT {
+        match workdir_idx {
+            WORKDIR_IDX_LOCALNET => &self.faucet_localnet,
+            WORKDIR_IDX_DEVNET => &self.faucet_devnet,
+            WORKDIR_IDX_TESTNET => &self.faucet_testnet,
+            WORKDIR_IDX_MAINNET => &self.faucet_mainnet,
+            _ => panic!("Invalid workdir_idx {}", workdir_idx),
+        }
+    }
+
+    pub fn get_supervisor(&self, workdir_idx: WorkdirIdx) -> &GlobalsSupervisorMT {
+        match workdir_idx {
+            WORKDIR_IDX_LOCALNET => &self.supervisor_localnet,
+            WORKDIR_IDX_DEVNET => &self.supervisor_devnet,
+            WORKDIR_IDX_TESTNET => &self.supervisor_testnet,
+            WORKDIR_IDX_MAINNET => &self.supervisor_mainnet,
+            _ => panic!("Invalid workdir_idx {}", workdir_idx),
+        }
+    }
+
     pub fn get_api_mutex(&self, workdir_idx: WorkdirIdx) -> &GlobalsAPIMutexMT {
         match workdir_idx {
             WORKDIR_IDX_LOCALNET => &self.api_mutex_localnet,
@@ -1,8 +1,8 @@
 use crate::shared_types::TargetServer;
 use common::basic_types::*;
-use common::shared_types::{Link, WorkdirUserConfig};
+use common::shared_types::{Link, TlsConfig, WorkdirUserConfig};
 
-use super::ServerStats;
+use super::{AllMethodsStats, ServerStats};
 
 use std::hash::Hasher;
 use twox_hash::XxHash32;
@@ -20,6 +20,10 @@ pub struct InputPort {
     // TCP/UDP port number. Set once at construction.
     port_number: u16,
 
+    // Listening address and TLS settings. Set once at construction.
+    bind_address: String,
+    tls: TlsConfig,
+
     // Request that processing on this port be abandon.
     //
     // This is a irreversible request.
@@ -34,6 +38,7 @@ pub struct InputPort {
     // Active Configuration.
     user_request_start: bool, // true when user_request == "start"
     proxy_enabled: bool,
+    access_log_enabled: bool,
 
     // Maintained by the AdminController such that the runtime idx remain the
     // same for a given alias ("forever", even when deleted from file config).
@@ -41,6 +46,7 @@ pub struct InputPort {
 
     // Periodically updated by the NetworkMonitor.
     pub all_servers_stats: ServerStats,
+    pub all_methods_stats: AllMethodsStats,
 
     // The "TargetServer" selection vectors are updated periodically by
     // the NetworkMonitor. They help the handler to very quickly pick
@@ -71,12 +77,16 @@ impl InputPort {
             workdir_name,
             workdir_idx,
             port_number: workdir_config.proxy_port_number(),
+            bind_address: workdir_config.proxy_bind_address().to_string(),
+            tls: workdir_config.proxy_tls().clone(),
             deactivate_request: false,
             proxy_server_running: false,
             user_request_start: workdir_config.is_user_request_start(),
             proxy_enabled: workdir_config.is_proxy_enabled(),
+            access_log_enabled: workdir_config.is_proxy_access_log_enabled(),
             target_servers: ManagedVec::new(),
             all_servers_stats: ServerStats::new("all".to_string()),
+            all_methods_stats: AllMethodsStats::new(),
             selection_vectors: Vec::new(),
             selection_worst: Vec::new(),
         }
@@ -145,6 +155,24 @@ impl InputPort {
         self.port_number
     }
 
+    pub fn bind_address(&self) -> &str {
+        &self.bind_address
+    }
+
+    pub fn tls(&self) -> &TlsConfig {
+        &self.tls
+    }
+
+    // URI for requests toward this port from within this process (e.g. health checks).
+    pub fn local_uri(&self) -> String {
+        let scheme = if self.tls.enabled { "https" } else { "http" };
+        let host = match crate::tls::resolve_bind_ip(&self.bind_address) {
+            Ok(bind_ip) => crate::tls::local_connect_ip(bind_ip).to_string(),
+            Err(_) => "localhost".to_string(),
+        };
+        format!("{}://{}:{}", scheme, host, self.port_number)
+    }
+
     pub fn deactivate(&mut self) {
         self.deactivate_request = true;
     }
@@ -161,6 +189,14 @@ impl InputPort {
         self.proxy_enabled
     }
 
+    pub fn is_access_log_enabled(&self) -> bool {
+        self.access_log_enabled
+    }
+
+    pub fn set_access_log_enabled(&mut self, value: bool) {
+        self.access_log_enabled = value;
+    }
+
     pub fn set_user_request_start(&mut self, value: bool) {
         self.user_request_start = value;
     }
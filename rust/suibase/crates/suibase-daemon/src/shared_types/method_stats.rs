@@ -0,0 +1,170 @@
+// Maintains per JSON-RPC method stats of the user traffic on an InputPort.
+//
+// Updated by the NetworkMonitor (from the same ProxyHandlerReport calls used for
+// the ServerStats). Each request is counted only once, even when retried.
+//
+// The number of distinct methods is capped (any method name can be sent by a client).
+// Once the cap is reached, the stats of new methods are merged into METHOD_STATS_OTHER.
+use std::collections::HashMap;
+
+pub const METHOD_STATS_MAX_METHODS: usize = 256;
+pub const METHOD_STATS_OTHER: &str = "other";
+pub const METHOD_STATS_UNKNOWN: &str = "unknown"; // Request body is not a JSON-RPC request.
+pub const METHOD_STATS_BATCH: &str = "batch"; // All batch requests are counted together.
+
+// Failed before the request body was read (e.g. proxy disabled, no server available).
+pub const METHOD_STATS_NOT_READ: &str = "not_read";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MethodStats {
+    calls: u64,
+    errors: u64, // Failed request, error response or JSON-RPC error.
+    bytes_in: u64,
+    bytes_out: u64,
+
+    // Latency from the proxy handler start to the response received.
+    // Only for requests with a response (successful or not).
+    latency_count: u64,
+    latency_sum_micros: u64,
+    latency_max_micros: u32,
+}
+
+impl MethodStats {
+    pub fn calls(&self) -> u64 {
+        self.calls
+    }
+
+    pub fn errors(&self) -> u64 {
+        self.errors
+    }
+
+    pub fn bytes_in(&self) -> u64 {
+        self.bytes_in
+    }
+
+    pub fn bytes_out(&self) -> u64 {
+        self.bytes_out
+    }
+
+    pub fn avg_latency_ms(&self) -> f64 {
+        if self.latency_count == 0 {
+            return f64::MAX;
+        }
+        (self.latency_sum_micros as f64 / self.latency_count as f64) / 1000.0
+    }
+
+    pub fn max_latency_ms(&self) -> f64 {
+        if self.latency_count == 0 {
+            return f64::MAX;
+        }
+        self.latency_max_micros as f64 / 1000.0
+    }
+
+    fn handle_report(
+        &mut self,
+        is_error: bool,
+        req_size: u32,
+        resp_size: u32,
+        latency_micros: Option<u32>,
+    ) {
+        self.calls += 1;
+        if is_error {
+            self.errors += 1;
+        }
+        self.bytes_in += req_size as u64;
+        self.bytes_out += resp_size as u64;
+        if let Some(latency_micros) = latency_micros {
+            self.latency_count += 1;
+            self.latency_sum_micros += latency_micros as u64;
+            if latency_micros > self.latency_max_micros {
+                self.latency_max_micros = latency_micros;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AllMethodsStats {
+    methods: HashMap<String, MethodStats>,
+}
+
+impl AllMethodsStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &MethodStats)> {
+        self.methods.iter()
+    }
+
+    pub fn handle_report(
+        &mut self,
+        method: &str,
+        is_error: bool,
+        req_size: u32,
+        resp_size: u32,
+        latency_micros: Option<u32>,
+    ) {
+        let key = if self.methods.len() < METHOD_STATS_MAX_METHODS
+            || self.methods.contains_key(method)
+        {
+            method
+        } else {
+            METHOD_STATS_OTHER
+        };
+        if !self.methods.contains_key(key) {
+            self.methods.insert(key.to_string(), MethodStats::default());
+        }
+        if let Some(stats) = self.methods.get_mut(key) {
+            stats.handle_report(is_error, req_size, resp_size, latency_micros);
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct JsonRpcMethodOnly {
+    method: Option<String>,
+}
+
+// Identify the JSON-RPC method of a request body (used as the stats key).
+//
+// Called by the proxy handler, so must be cheap: the body is scanned without
+// building a serde_json::Value (other fields are skipped).
+pub fn method_from_request_body(body: &[u8]) -> String {
+    if body.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'[') {
+        return METHOD_STATS_BATCH.to_string();
+    }
+    serde_json::from_slice::<JsonRpcMethodOnly>(body)
+        .ok()
+        .and_then(|req| req.method)
+        .unwrap_or_else(|| METHOD_STATS_UNKNOWN.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_method_from_request_body() {
+        let body = br#"{"jsonrpc":"2.0","id":1,"method":"sui_getObject","params":["0x5"]}"#;
+        assert_eq!(method_from_request_body(body), "sui_getObject");
+        let body = br#" [{"jsonrpc":"2.0","id":1,"method":"sui_getObject"}]"#;
+        assert_eq!(method_from_request_body(body), METHOD_STATS_BATCH);
+        assert_eq!(method_from_request_body(b"garbage"), METHOD_STATS_UNKNOWN);
+    }
+
+    #[test]
+    fn test_methods_cap() {
+        let mut stats = AllMethodsStats::new();
+        for i in 0..METHOD_STATS_MAX_METHODS + 10 {
+            stats.handle_report(&format!("m{}", i), false, 10, 20, Some(1000));
+        }
+        assert_eq!(stats.iter().count(), METHOD_STATS_MAX_METHODS + 1);
+        let (_, other) = stats
+            .iter()
+            .find(|(method, _)| *method == METHOD_STATS_OTHER)
+            .unwrap();
+        assert_eq!(other.calls(), 10);
+        assert_eq!(other.bytes_out(), 200);
+    }
+}
@@ -3,16 +3,28 @@
 // This is a submodule specific to suibase-daemon.
 //
 // flatten everything under "shared_type" module.
+pub(crate) use self::console_log::*;
+pub(crate) use self::event_wait::*;
+pub(crate) use self::event_views::*;
 pub(crate) use self::events::*;
+pub(crate) use self::faucet::*;
 pub(crate) use self::globals::*;
 pub(crate) use self::input_port::*;
+pub(crate) use self::method_stats::*;
 pub(crate) use self::packages::*;
 pub(crate) use self::server_stats::*;
+pub(crate) use self::supervisor::*;
 pub(crate) use self::target_server::*;
 
+mod console_log;
+mod event_wait;
+mod event_views;
 mod events;
+mod faucet;
 mod globals;
 mod input_port;
+mod method_stats;
 mod packages;
 mod server_stats;
+mod supervisor;
 mod target_server;
@@ -1,4 +1,9 @@
+use std::collections::{HashMap, HashSet};
 use std::hash::{Hash, Hasher};
+use std::path::Path;
+
+// Tags of a package instance. JSON array of strings in its published-data directory.
+pub const PACKAGE_TAGS_FILE: &str = "tags.json";
 
 #[derive(Debug, Clone, Default)]
 pub struct PackagePath {
@@ -69,3 +74,62 @@ impl PartialEq for PackagePath {
             && self.package_timestamp == other.package_timestamp
     }
 }
+
+// Tags of the package instance at instance_path. Empty when none (or unreadable).
+pub async fn read_package_tags(instance_path: &Path) -> Vec<String> {
+    tokio::fs::read_to_string(instance_path.join(PACKAGE_TAGS_FILE))
+        .await
+        .ok()
+        .and_then(|content| serde_json::from_str::<Vec<String>>(&content).ok())
+        .unwrap_or_default()
+}
+
+// The package instances beyond the keep_last most recent of their package uuid.
+//
+// Tags are not considered here (the caller keeps the tagged ones).
+pub fn expired_package_paths(
+    package_paths: &HashSet<PackagePath>,
+    keep_last: usize,
+) -> Vec<PackagePath> {
+    let mut by_uuid: HashMap<&str, Vec<&PackagePath>> = HashMap::new();
+    for package_path in package_paths {
+        by_uuid
+            .entry(package_path.get_package_uuid())
+            .or_default()
+            .push(package_path);
+    }
+    let mut expired = Vec::new();
+    for mut instances in by_uuid.into_values() {
+        // Most recent first.
+        instances.sort_by_key(|p| {
+            std::cmp::Reverse(p.get_package_timestamp().parse::<u64>().unwrap_or(0))
+        });
+        expired.extend(instances.into_iter().skip(keep_last).cloned());
+    }
+    expired
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expired_package_paths() {
+        let path = |uuid: &str, ts: &str| {
+            PackagePath::new("demo".to_string(), uuid.to_string(), ts.to_string())
+        };
+        let package_paths: HashSet<PackagePath> = [
+            path("A", "900"),
+            path("A", "1000"),
+            path("A", "1100"),
+            path("B", "500"),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            expired_package_paths(&package_paths, 2),
+            vec![path("A", "900")]
+        );
+        assert!(expired_package_paths(&package_paths, 3).is_empty());
+    }
+}
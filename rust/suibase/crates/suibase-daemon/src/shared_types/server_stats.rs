@@ -29,6 +29,22 @@ pub const REQUEST_FAILED_LAST_REASON: u8 = REQUEST_FAILED_NOT_STARTED;
 // Do not touch this.
 pub const REQUEST_FAILED_VEC_SIZE: usize = REQUEST_FAILED_LAST_REASON as usize + 1;
 
+// Short human readable form of a RequestFailedReason (e.g. for logging).
+pub fn request_failed_reason_str(reason: RequestFailedReason) -> &'static str {
+    match reason {
+        REQUEST_FAILED_BODY_READ => "body_read",
+        REQUEST_FAILED_NO_SERVER_RESPONDING => "no_server_responding",
+        REQUEST_FAILED_NO_SERVER_AVAILABLE => "no_server_available",
+        REQUEST_FAILED_RESP_BYTES_RX => "resp_bytes_rx",
+        REQUEST_FAILED_RESP_BUILDER => "resp_builder",
+        REQUEST_FAILED_NETWORK_DOWN => "network_down",
+        REQUEST_FAILED_BAD_REQUEST_HTTP => "bad_request_http",
+        REQUEST_FAILED_CONFIG_DISABLED => "config_disabled",
+        REQUEST_FAILED_NOT_STARTED => "not_started",
+        _ => "unknown",
+    }
+}
+
 // Send Failure Reasons
 // !!! Append new reasons at the end and update REQUEST_FAILED_LAST_REASON
 pub type SendFailedReason = u8;
@@ -0,0 +1,287 @@
+// Supervision state of the local services of a workdir (see supervisor_poller.rs).
+//
+// One SupervisorST per workdir. For each service:
+//   - restarts are delayed by an exponential backoff (reset after a stable run).
+//   - too many restarts within CRASH_LOOP_WINDOW is a crash loop. The service is then
+//     left down until the user stops/starts the workdir.
+//   - a ring buffer of the latest output (service log and restart command output).
+//
+// The restart history is kept for the "getServicesHistory" API.
+use std::collections::VecDeque;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const OUTPUT_MAX_LINES: usize = 200;
+const HISTORY_MAX_RECORDS: usize = 100;
+// Longer than a status refresh, so a restart is not repeated on a stale status.
+const BACKOFF_BASE: Duration = Duration::from_secs(30);
+const STABLE_RUN: Duration = Duration::from_secs(300); // Running that long resets the backoff.
+pub const CRASH_LOOP_WINDOW: Duration = Duration::from_secs(600);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestartPolicy {
+    Always,
+    Never,
+}
+
+impl RestartPolicy {
+    pub fn from_config(policy: &str) -> Self {
+        match policy {
+            "never" => RestartPolicy::Never,
+            _ => RestartPolicy::Always,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceState {
+    Running,
+    Down,      // Not running, restart not yet attempted.
+    Backoff,   // Restart attempted, waiting before the next one.
+    CrashLoop, // No more restart (until the workdir is stopped by the user).
+    Stopped,   // Workdir stopped by the user.
+}
+
+impl ServiceState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ServiceState::Running => "RUNNING",
+            ServiceState::Down => "DOWN",
+            ServiceState::Backoff => "BACKOFF",
+            ServiceState::CrashLoop => "CRASH LOOP",
+            ServiceState::Stopped => "STOPPED",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RestartRecord {
+    pub timestamp: u64, // Unix time in seconds.
+    pub label: String,
+    pub reason: String,
+    pub outcome: String, // "OK" or the error of the restart command.
+    pub crash_loop: bool,
+}
+
+#[derive(Debug)]
+pub struct SupervisedServiceST {
+    pub label: String, // Same as StatusService label (e.g. "Localnet process").
+    pub state: ServiceState,
+    pub pid: Option<u64>,
+    pub consecutive_failures: u32,
+    pub output: VecDeque<String>,
+    running_since: Option<Instant>,
+    restarts: VecDeque<Instant>, // Within CRASH_LOOP_WINDOW (oldest first).
+    next_restart_at: Option<Instant>,
+}
+
+impl SupervisedServiceST {
+    fn new(label: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            state: ServiceState::Stopped,
+            pid: None,
+            consecutive_failures: 0,
+            output: VecDeque::new(),
+            running_since: None,
+            restarts: VecDeque::new(),
+            next_restart_at: None,
+        }
+    }
+
+    pub fn restarts_in_window(&self) -> usize {
+        self.restarts.len()
+    }
+
+    pub fn next_restart_in(&self, now: Instant) -> Option<Duration> {
+        match self.state {
+            ServiceState::Down | ServiceState::Backoff => self
+                .next_restart_at
+                .map(|at| at.saturating_duration_since(now)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SupervisorST {
+    pub services: Vec<SupervisedServiceST>,
+    pub history: VecDeque<RestartRecord>, // Oldest first.
+}
+
+impl SupervisorST {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn service_mut(&mut self, label: &str) -> &mut SupervisedServiceST {
+        let idx = match self.services.iter().position(|s| s.label == label) {
+            Some(idx) => idx,
+            None => {
+                self.services.push(SupervisedServiceST::new(label));
+                self.services.len() - 1
+            }
+        };
+        &mut self.services[idx]
+    }
+
+    // Workdir stopped by the user. Also clears the crash loops.
+    pub fn stop_all(&mut self) {
+        for service in &mut self.services {
+            service.state = ServiceState::Stopped;
+            service.pid = None;
+            service.consecutive_failures = 0;
+            service.running_since = None;
+            service.restarts.clear();
+            service.next_restart_at = None;
+        }
+    }
+
+    pub fn append_output(&mut self, label: &str, lines: &[String]) {
+        let service = self.service_mut(label);
+        for line in lines {
+            service.output.push_back(line.clone());
+        }
+        while service.output.len() > OUTPUT_MAX_LINES {
+            service.output.pop_front();
+        }
+    }
+
+    // Update a service from the latest workdir status.
+    pub fn observe(&mut self, label: &str, running: bool, pid: Option<u64>, now: Instant) {
+        let service = self.service_mut(label);
+        if running {
+            if service.state != ServiceState::Running {
+                service.running_since = Some(now);
+            }
+            service.state = ServiceState::Running;
+            service.pid = pid;
+            if service
+                .running_since
+                .is_some_and(|since| now.duration_since(since) >= STABLE_RUN)
+            {
+                service.consecutive_failures = 0;
+            }
+        } else {
+            service.pid = None;
+            service.running_since = None;
+            if matches!(service.state, ServiceState::Running | ServiceState::Stopped) {
+                service.state = ServiceState::Down;
+            }
+        }
+    }
+
+    pub fn restart_due(&self, label: &str, policy: RestartPolicy, now: Instant) -> bool {
+        if policy == RestartPolicy::Never {
+            return false;
+        }
+        self.services.iter().any(|service| {
+            service.label == label
+                && matches!(service.state, ServiceState::Down | ServiceState::Backoff)
+                && service.next_restart_at.is_none_or(|at| now >= at)
+        })
+    }
+
+    // Returns true when the service is now considered in a crash loop.
+    #[allow(clippy::too_many_arguments)]
+    pub fn record_restart(
+        &mut self,
+        label: &str,
+        reason: &str,
+        outcome: &str,
+        output: &[String],
+        now: Instant,
+        crash_loop_restarts: u32,
+        max_backoff: Duration,
+    ) -> bool {
+        self.append_output(label, output);
+        let service = self.service_mut(label);
+
+        while service
+            .restarts
+            .front()
+            .is_some_and(|at| now.duration_since(*at) >= CRASH_LOOP_WINDOW)
+        {
+            service.restarts.pop_front();
+        }
+        service.restarts.push_back(now);
+
+        service.consecutive_failures += 1;
+        let exponent = (service.consecutive_failures - 1).min(16);
+        let backoff = BACKOFF_BASE.saturating_mul(1 << exponent).min(max_backoff);
+        service.next_restart_at = Some(now + backoff);
+
+        let crash_loop =
+            crash_loop_restarts > 0 && service.restarts.len() >= crash_loop_restarts as usize;
+        service.state = if crash_loop {
+            ServiceState::CrashLoop
+        } else {
+            ServiceState::Backoff
+        };
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        self.history.push_back(RestartRecord {
+            timestamp,
+            label: label.to_string(),
+            reason: reason.to_string(),
+            outcome: outcome.to_string(),
+            crash_loop,
+        });
+        while self.history.len() > HISTORY_MAX_RECORDS {
+            self.history.pop_front();
+        }
+        crash_loop
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_supervisor_backoff_and_crash_loop() {
+        let mut supervisor = SupervisorST::new();
+        let label = "Localnet process";
+        let policy = RestartPolicy::Always;
+        let max_backoff = Duration::from_secs(300);
+        let mut now = Instant::now();
+
+        supervisor.observe(label, true, Some(1234), now);
+        assert!(!supervisor.restart_due(label, policy, now));
+
+        // Down, restart immediately then wait the backoff (30s, 60s, 120s...).
+        supervisor.observe(label, false, None, now);
+        assert!(supervisor.restart_due(label, policy, now));
+        assert!(!supervisor.restart_due(label, RestartPolicy::Never, now));
+        let lines = vec!["panicked".to_string()];
+        assert!(!supervisor.record_restart(
+            label,
+            "NOT RUNNING",
+            "OK",
+            &lines,
+            now,
+            3,
+            max_backoff
+        ));
+        assert!(!supervisor.restart_due(label, policy, now + Duration::from_secs(29)));
+        now += Duration::from_secs(30);
+        assert!(supervisor.restart_due(label, policy, now));
+        supervisor.record_restart(label, "NOT RUNNING", "OK", &lines, now, 3, max_backoff);
+        assert!(!supervisor.restart_due(label, policy, now + Duration::from_secs(59)));
+        now += Duration::from_secs(60);
+
+        // Third restart within the window is a crash loop.
+        assert!(supervisor.record_restart(label, "NOT RUNNING", "OK", &lines, now, 3, max_backoff));
+        assert!(!supervisor.restart_due(label, policy, now + Duration::from_secs(3600)));
+        assert_eq!(supervisor.services[0].state, ServiceState::CrashLoop);
+        assert_eq!(supervisor.services[0].output.len(), 3);
+        assert_eq!(supervisor.history.len(), 3);
+
+        // Cleared when the user stops the workdir.
+        supervisor.stop_all();
+        supervisor.observe(label, false, None, now);
+        assert!(supervisor.restart_due(label, policy, now));
+    }
+}
@@ -1,12 +1,18 @@
+use std::sync::Arc;
+
 use crate::shared_types::ServerStats;
 
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue};
 use common::basic_types::*;
-use common::shared_types::Link;
+use common::shared_types::{Link, LinkAuth};
 
 #[derive(Debug)]
 pub struct TargetServer {
     idx: Option<ManagedVecU8>,
     config: Link,
+    // Pre-built from the config custom headers and auth (None when there is none).
+    // All values are marked "sensitive" (so redacted by Debug).
+    extra_headers: Option<Arc<HeaderMap>>,
     pub stats: ServerStats,
 }
 
@@ -14,13 +20,60 @@ impl TargetServer {
     pub fn new(config: Link) -> Self {
         // alias is the 'key' and can't be changed after construction.
         let alias = config.alias.clone();
+        let extra_headers = Self::build_extra_headers(&config);
         Self {
             idx: None,
             config,
+            extra_headers,
             stats: ServerStats::new(alias),
         }
     }
 
+    // Also used for the websocket connections of a link (see websocket_worker.rs).
+    pub fn build_extra_headers(config: &Link) -> Option<Arc<HeaderMap>> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &config.headers {
+            let name = HeaderName::from_bytes(name.as_bytes());
+            let value = HeaderValue::from_str(value.expose());
+            if let (Ok(name), Ok(mut value)) = (name, value) {
+                value.set_sensitive(true);
+                headers.insert(name, value);
+            } else {
+                log::warn!("link {} has an invalid custom header", config.alias);
+            }
+        }
+
+        let authorization = match &config.auth {
+            Some(LinkAuth::Bearer(token)) => Some(format!("Bearer {}", token.expose())),
+            Some(LinkAuth::Basic { username, password }) => {
+                let credentials = format!("{}:{}", username, password.expose());
+                Some(format!(
+                    "Basic {}",
+                    data_encoding::BASE64.encode(credentials.as_bytes())
+                ))
+            }
+            None => None,
+        };
+        if let Some(authorization) = authorization {
+            if let Ok(mut value) = HeaderValue::from_str(&authorization) {
+                value.set_sensitive(true);
+                headers.insert(header::AUTHORIZATION, value);
+            } else {
+                log::warn!("link {} has an invalid auth", config.alias);
+            }
+        }
+
+        if headers.is_empty() {
+            None
+        } else {
+            Some(Arc::new(headers))
+        }
+    }
+
+    pub fn extra_headers(&self) -> Option<Arc<HeaderMap>> {
+        self.extra_headers.clone()
+    }
+
     pub fn alias(&self) -> String {
         self.config.alias.clone()
     }
@@ -57,6 +110,7 @@ impl TargetServer {
     }
 
     pub fn set_config(&mut self, config: Link) {
+        self.extra_headers = Self::build_extra_headers(&config);
         self.config = config
     }
 }
@@ -70,3 +124,37 @@ impl ManagedElement for TargetServer {
         self.idx = index;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::shared_types::LinkSecret;
+
+    #[test]
+    fn test_build_extra_headers() {
+        let mut link = Link::new("my-provider".to_string(), "https://rpc".to_string());
+        assert!(TargetServer::build_extra_headers(&link).is_none());
+
+        link.headers = vec![
+            ("x-api-key".to_string(), LinkSecret::new("key".to_string())),
+            (
+                "bad header".to_string(),
+                LinkSecret::new("ignored".to_string()),
+            ),
+        ];
+        link.auth = Some(LinkAuth::Basic {
+            username: "me".to_string(),
+            password: LinkSecret::new("pw".to_string()),
+        });
+        let headers = TargetServer::build_extra_headers(&link).unwrap();
+        assert_eq!(headers.len(), 2);
+        assert_eq!(headers["x-api-key"], "key");
+        assert_eq!(headers[header::AUTHORIZATION], "Basic bWU6cHc=");
+        assert!(headers[header::AUTHORIZATION].is_sensitive());
+        assert!(!format!("{:?}", headers).contains("bWU6cHc="));
+
+        link.auth = Some(LinkAuth::Bearer(LinkSecret::new("token".to_string())));
+        let headers = TargetServer::build_extra_headers(&link).unwrap();
+        assert_eq!(headers[header::AUTHORIZATION], "Bearer token");
+    }
+}
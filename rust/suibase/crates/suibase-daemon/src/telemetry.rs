@@ -0,0 +1,220 @@
+// Telemetry setup for the whole process.
+//
+// Spans are always created with the "tracing" crate. They are exported with OpenTelemetry
+// (OTLP over HTTP) only when one of the standard environment variables is set:
+//
+//    OTEL_EXPORTER_OTLP_ENDPOINT         e.g. "http://localhost:4318"
+//    OTEL_EXPORTER_OTLP_TRACES_ENDPOINT  e.g. "http://localhost:4318/v1/traces"
+//
+// Spans being exported:
+//    proxy_request   One per user request on a ProxyServer port. The parent is taken from
+//                    the inbound W3C "traceparent" header (when present).
+//    proxy_attempt   Child of proxy_request. One per attempt (retries included), annotated
+//                    with the TargetServer alias. Its context is propagated upstream.
+//    admctrl_msg     One per message processed by the AdminController.
+//    shell_exec      One per command executed by a ShellWorker.
+//    db_write        One per write done by a DBWorker.
+//
+// Only spans from this crate (and "common") are exported. The console output remains
+// controlled with RUST_LOG, as before.
+use anyhow::Result;
+use axum::http::{HeaderMap, HeaderName, HeaderValue};
+use opentelemetry::propagation::{Extractor, Injector, TextMapPropagator};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::TracerProvider;
+use opentelemetry_sdk::Resource;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{
+    filter::Targets, fmt::format::FmtSpan, layer::SubscriberExt, EnvFilter, Layer,
+};
+
+const SERVICE_NAME: &str = "suibase-daemon";
+
+// Keep this alive until the end of main(). Pending spans are flushed on drop.
+pub struct TelemetryGuard {
+    provider: Option<TracerProvider>,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        if let Some(provider) = self.provider.take() {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("telemetry shutdown failed: {}", e);
+            }
+        }
+    }
+}
+
+fn is_otlp_configured() -> bool {
+    std::env::var_os("OTEL_EXPORTER_OTLP_ENDPOINT").is_some()
+        || std::env::var_os("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT").is_some()
+}
+
+// When endpoint is None, the exporter resolves it from the OTEL_* environment variables.
+pub fn build_tracer_provider(endpoint: Option<&str>) -> Result<TracerProvider> {
+    let mut builder = opentelemetry_otlp::SpanExporter::builder().with_http();
+    if let Some(endpoint) = endpoint {
+        builder = builder.with_endpoint(endpoint);
+    }
+    let exporter = builder.build()?;
+
+    Ok(TracerProvider::builder()
+        .with_batch_exporter(exporter, opentelemetry_sdk::runtime::Tokio)
+        .with_resource(Resource::new(vec![KeyValue::new(
+            "service.name",
+            SERVICE_NAME,
+        )]))
+        .build())
+}
+
+fn otel_targets() -> Targets {
+    Targets::new()
+        .with_target("suibase_daemon", tracing::Level::INFO)
+        .with_target("common", tracing::Level::INFO)
+}
+
+// Must be called once, from within the tokio runtime (the batch exporter spawns a task).
+pub fn init_telemetry() -> TelemetryGuard {
+    let provider = if is_otlp_configured() {
+        match build_tracer_provider(None) {
+            Ok(provider) => Some(provider),
+            Err(e) => {
+                eprintln!("OTLP exporter disabled: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let fmt_layer = tracing_subscriber::fmt::layer()
+        .with_span_events(FmtSpan::ACTIVE)
+        .compact() // Modern preference for daemon logs
+        .with_filter(
+            EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "poi_server=info,database=info,tower_http=warn".into()),
+        );
+
+    let otel_layer = provider.as_ref().map(|provider| {
+        tracing_opentelemetry::layer()
+            .with_tracer(provider.tracer(SERVICE_NAME))
+            .with_filter(otel_targets())
+    });
+
+    let subscriber = tracing_subscriber::registry()
+        .with(fmt_layer)
+        .with(otel_layer);
+
+    tracing::subscriber::set_global_default(subscriber).expect("Failed to set tracing subscriber");
+
+    TelemetryGuard { provider }
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|v| v.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|k| k.as_str()).collect()
+    }
+}
+
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(key.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+// Make the span a child of the inbound "traceparent" (if any).
+pub fn set_parent_from_headers(span: &tracing::Span, headers: &HeaderMap) {
+    if headers.contains_key("traceparent") {
+        let parent_cx = TraceContextPropagator::new().extract(&HeaderExtractor(headers));
+        span.set_parent(parent_cx);
+    }
+}
+
+// Add/replace the "traceparent" header with the span context.
+pub fn inject_span_context(span: &tracing::Span, headers: &mut HeaderMap) {
+    TraceContextPropagator::new().inject_context(&span.context(), &mut HeaderInjector(headers));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::post, Router};
+    use opentelemetry::trace::TraceContextExt;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[test]
+    fn test_extract_traceparent() {
+        let mut headers = HeaderMap::new();
+        headers.insert("traceparent", HeaderValue::from_static(TRACEPARENT));
+        let cx = TraceContextPropagator::new().extract(&HeaderExtractor(&headers));
+        let span_context = cx.span().span_context().clone();
+        assert!(span_context.is_remote());
+        assert_eq!(
+            span_context.trace_id().to_string(),
+            "4bf92f3577b34da6a3ce929d0e0e4736"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_export_to_local_collector() {
+        // Minimal stand-in for an OTLP/HTTP collector. Just count the POSTs.
+        let received = Arc::new(AtomicUsize::new(0));
+        let received_clone = received.clone();
+        let app = Router::new().route(
+            "/v1/traces",
+            post(move || {
+                received_clone.fetch_add(1, Ordering::SeqCst);
+                async { "" }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let endpoint = format!("http://{}/v1/traces", addr);
+        let provider = build_tracer_provider(Some(&endpoint)).unwrap();
+        let subscriber = tracing_subscriber::registry().with(
+            tracing_opentelemetry::layer()
+                .with_tracer(provider.tracer(SERVICE_NAME))
+                .with_filter(otel_targets()),
+        );
+
+        tracing::subscriber::with_default(subscriber, || {
+            let mut headers = HeaderMap::new();
+            headers.insert("traceparent", HeaderValue::from_static(TRACEPARENT));
+            let span = tracing::info_span!("proxy_request");
+            set_parent_from_headers(&span, &headers);
+
+            // The propagated context must keep the inbound trace id.
+            let mut upstream_headers = HeaderMap::new();
+            inject_span_context(&span, &mut upstream_headers);
+            let traceparent = upstream_headers["traceparent"].to_str().unwrap();
+            assert!(traceparent.contains("4bf92f3577b34da6a3ce929d0e0e4736"));
+        });
+
+        // force_flush blocks, so keep it off the async worker threads.
+        tokio::task::spawn_blocking(move || provider.force_flush())
+            .await
+            .unwrap();
+        assert!(received.load(Ordering::SeqCst) >= 1);
+    }
+}
@@ -0,0 +1,188 @@
+// TLS support for the listening ports (ProxyServer and APIServer).
+//
+// Two ways to get a certificate:
+//   - User supplied PEM files (e.g. "proxy_tls_cert" and "proxy_tls_key" in suibase.yaml).
+//   - Self-signed, generated once into a "tls" subdirectory (e.g. ~/suibase/workdirs/localnet/tls)
+//     and re-used on subsequent starts. Regenerated when the bind address is changed (the
+//     names of the certificate are kept in a names.txt). Delete the files to force a
+//     regeneration.
+//
+// Specifying only one of the cert or key file is an error (no fallback to self-signed).
+//
+// The rustls ServerConfig is built explicitly with the "ring" provider (no reliance on a
+// process-wide default provider).
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use common::shared_types::TlsConfig;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+
+const SELF_SIGNED_CERT_FILENAME: &str = "cert.pem";
+const SELF_SIGNED_KEY_FILENAME: &str = "key.pem";
+const SELF_SIGNED_NAMES_FILENAME: &str = "names.txt";
+
+// Convert a bind address setting into an IpAddr.
+//
+// "localhost" is accepted as an alias of 127.0.0.1
+pub fn resolve_bind_ip(bind_address: &str) -> Result<IpAddr> {
+    let bind_address = bind_address.trim();
+    if bind_address.is_empty() || bind_address == "localhost" {
+        return Ok(IpAddr::V4(Ipv4Addr::LOCALHOST));
+    }
+    bind_address
+        .parse::<IpAddr>()
+        .map_err(|e| anyhow!("invalid bind address [{}]: {}", bind_address, e))
+}
+
+// The address to use for connecting to a local listening port (e.g. for health checks).
+pub fn local_connect_ip(bind_ip: IpAddr) -> IpAddr {
+    if bind_ip.is_unspecified() {
+        IpAddr::V4(Ipv4Addr::LOCALHOST)
+    } else {
+        bind_ip
+    }
+}
+
+fn write_private_file(path: &Path, contents: &str) -> Result<()> {
+    std::fs::write(path, contents)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(())
+}
+
+// Subject alternative names of the self-signed certificate for a bind address.
+fn self_signed_names(bind_ip: IpAddr) -> Vec<String> {
+    let mut subject_alt_names = vec!["localhost".to_string(), "127.0.0.1".to_string()];
+    if !bind_ip.is_unspecified() && !bind_ip.is_loopback() {
+        subject_alt_names.push(bind_ip.to_string());
+    }
+    subject_alt_names
+}
+
+fn generate_self_signed(
+    cert_path: &Path,
+    key_path: &Path,
+    names_path: &Path,
+    subject_alt_names: Vec<String>,
+) -> Result<()> {
+    let names = subject_alt_names.join("\n");
+    let rcgen::CertifiedKey { cert, key_pair } =
+        rcgen::generate_simple_self_signed(subject_alt_names)?;
+
+    if let Some(parent) = cert_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(cert_path, cert.pem())?;
+    write_private_file(key_path, &key_pair.serialize_pem())?;
+    std::fs::write(names_path, names)?;
+    log::info!("generated self-signed certificate {}", cert_path.display());
+    Ok(())
+}
+
+// Returns the (cert, key) paths to use, generating a self-signed pair in
+// self_signed_dir when needed.
+fn get_pem_paths(
+    tls: &TlsConfig,
+    self_signed_dir: &Path,
+    bind_ip: IpAddr,
+) -> Result<(PathBuf, PathBuf)> {
+    match (&tls.cert_path, &tls.key_path) {
+        (Some(cert_path), Some(key_path)) => {
+            return Ok((PathBuf::from(cert_path), PathBuf::from(key_path)));
+        }
+        (Some(cert_path), None) => {
+            return Err(anyhow!("TLS cert {} specified without a key", cert_path));
+        }
+        (None, Some(key_path)) => {
+            return Err(anyhow!("TLS key {} specified without a cert", key_path));
+        }
+        (None, None) => {}
+    }
+
+    let cert_path = self_signed_dir.join(SELF_SIGNED_CERT_FILENAME);
+    let key_path = self_signed_dir.join(SELF_SIGNED_KEY_FILENAME);
+    let names_path = self_signed_dir.join(SELF_SIGNED_NAMES_FILENAME);
+    let subject_alt_names = self_signed_names(bind_ip);
+    // Missing names.txt (e.g. generated by an older version) also regenerates.
+    let names_changed = std::fs::read_to_string(&names_path)
+        .ok()
+        .is_none_or(|names| names != subject_alt_names.join("\n"));
+    if !cert_path.exists() || !key_path.exists() || names_changed {
+        generate_self_signed(&cert_path, &key_path, &names_path, subject_alt_names)?;
+    }
+    Ok((cert_path, key_path))
+}
+
+pub fn load_server_config(
+    tls: &TlsConfig,
+    self_signed_dir: &Path,
+    bind_ip: IpAddr,
+) -> Result<Arc<rustls::ServerConfig>> {
+    let (cert_path, key_path) = get_pem_paths(tls, self_signed_dir, bind_ip)?;
+
+    let certs = CertificateDer::pem_file_iter(&cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| anyhow!("failed to read {}: {}", cert_path.display(), e))?;
+    if certs.is_empty() {
+        return Err(anyhow!("no certificate found in {}", cert_path.display()));
+    }
+    let key = PrivateKeyDer::from_pem_file(&key_path)
+        .map_err(|e| anyhow!("failed to read {}: {}", key_path.display(), e))?;
+
+    let mut config = rustls::ServerConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()?
+    .with_no_client_auth()
+    .with_single_cert(certs, key)?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    Ok(Arc::new(config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_pem_paths() {
+        let dir = std::env::temp_dir().join(format!("suibase_tls_{}", std::process::id()));
+        let localhost = resolve_bind_ip("localhost").unwrap();
+        assert_eq!(localhost, IpAddr::V4(Ipv4Addr::LOCALHOST));
+        assert!(resolve_bind_ip("not-an-ip").is_err());
+
+        // Half-specified is an error.
+        let mut tls = TlsConfig {
+            enabled: true,
+            cert_path: Some("/path/to/cert.pem".to_string()),
+            key_path: None,
+        };
+        assert!(get_pem_paths(&tls, &dir, localhost).is_err());
+        tls.key_path = Some("/path/to/key.pem".to_string());
+        let (cert_path, _) = get_pem_paths(&tls, &dir, localhost).unwrap();
+        assert_eq!(cert_path, PathBuf::from("/path/to/cert.pem"));
+
+        // Self-signed, re-used until the bind address changes.
+        let tls = TlsConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        let (cert_path, key_path) = get_pem_paths(&tls, &dir, localhost).unwrap();
+        let cert = std::fs::read_to_string(&cert_path).unwrap();
+        get_pem_paths(&tls, &dir, localhost).unwrap();
+        assert_eq!(std::fs::read_to_string(&cert_path).unwrap(), cert);
+        let bind_ip = resolve_bind_ip("192.168.1.10").unwrap();
+        get_pem_paths(&tls, &dir, bind_ip).unwrap();
+        assert_ne!(std::fs::read_to_string(&cert_path).unwrap(), cert);
+        assert!(load_server_config(&tls, &dir, bind_ip).is_ok());
+        assert!(key_path.exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,398 @@
+// Child task of admin_controller
+//
+// One instance per workdir.
+//
+// Responsible to:
+//  - Periodically query (balance_poll_interval_secs), through the local proxy, the
+//    balances (SUI and other coins) and the number of owned objects of:
+//      - the keystore addresses (from config/sui.aliases, no private key is read).
+//        Localnet and devnet only, to not load the public RPCs with test addresses.
+//      - the "balance_addresses" in suibase.yaml.
+//    Nothing is polled while the workdir is not started.
+//  - Update the globals for the getAddressBalances API.
+//  - Flag a low gas for the active address and the configured addresses. The
+//    cli_poller shows it as the low_gas_warning of the workdir status.
+//
+// The task is auto-restart in case of panic.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use axum::async_trait;
+use common::{
+    basic_types::{Instantiable, WorkdirContext, WorkdirIdx},
+    shared_types::{WORKDIRS_KEYS, WORKDIR_IDX_DEVNET, WORKDIR_IDX_LOCALNET},
+    workers::{PollerWorker, PollingTrait},
+};
+use fastcrypto::hash::{Blake2b256, HashFunction};
+use tokio_graceful_shutdown::SubsystemHandle;
+
+use crate::{
+    api::{AddressBalance, AddressBalancesResponse, CoinBalance, Versioned},
+    shared_types::Globals,
+    workers::proxy_rpc::{get_proxy_uri, proxy_client, rpc_call},
+};
+
+const SUI_COIN_TYPE: &str = "0x2::sui::SUI";
+
+// Owned objects are counted up to 50*4 = 200 (see AddressBalance::owned_objects_more).
+const OWNED_OBJECTS_PAGE_LIMIT: u64 = 50;
+const OWNED_OBJECTS_MAX_PAGES: usize = 4;
+
+#[derive(Clone)]
+pub struct BalancePollerParams {
+    globals: Globals,
+    workdir_idx: WorkdirIdx,
+}
+
+impl WorkdirContext for BalancePollerParams {
+    fn workdir_idx(&self) -> WorkdirIdx {
+        self.workdir_idx
+    }
+}
+
+impl BalancePollerParams {
+    pub fn new(globals: Globals, workdir_idx: WorkdirIdx) -> Self {
+        Self {
+            globals,
+            workdir_idx,
+        }
+    }
+}
+
+pub struct BalancePoller {
+    // "Glue" the specialized PollingTraitObject with its parameters.
+    // The worker does all the background task/events handling.
+    poller: PollerWorker<PollingTraitObject, BalancePollerParams>,
+}
+
+pub struct PollingTraitObject {
+    params: BalancePollerParams,
+    last_poll: Option<tokio::time::Instant>,
+}
+
+#[async_trait]
+impl PollingTrait for PollingTraitObject {
+    // This is called by the PollerWorker task.
+    async fn update(&mut self) {
+        // The audits are more frequent than needed for balances.
+        let (user_request_start, poll_interval) = {
+            let globals_read_guard = self
+                .params
+                .globals
+                .get_config(self.params.workdir_idx)
+                .read()
+                .await;
+            let user_config = &globals_read_guard.user_config;
+            (
+                user_config.is_user_request_start(),
+                tokio::time::Duration::from_secs(user_config.balance_poll_interval_secs()),
+            )
+        };
+        if !user_request_start {
+            return;
+        }
+        if self
+            .last_poll
+            .is_some_and(|last_poll| last_poll.elapsed() < poll_interval)
+        {
+            return;
+        }
+        self.last_poll = Some(tokio::time::Instant::now());
+        self.update_globals_address_balances().await;
+    }
+}
+
+// This allow the PollerWorker to instantiate the PollingTraitObject.
+impl Instantiable<BalancePollerParams> for PollingTraitObject {
+    fn new(params: BalancePollerParams) -> Self {
+        Self {
+            params,
+            last_poll: None,
+        }
+    }
+}
+
+impl BalancePoller {
+    pub fn new(params: BalancePollerParams, subsys: &SubsystemHandle) -> Self {
+        let poller =
+            PollerWorker::<PollingTraitObject, BalancePollerParams>::new(params.clone(), subsys);
+        Self { poller }
+    }
+
+    pub fn get_tx_channel(&self) -> common::basic_types::GenericTx {
+        self.poller.get_tx_channel()
+    }
+}
+
+// Sui address of a "public_key_base64" in sui.aliases (the key scheme flag followed
+// by the public key). The address is the Blake2b-256 of these bytes.
+fn address_from_public_key_base64(public_key_base64: &str) -> Option<String> {
+    let bytes = data_encoding::BASE64
+        .decode(public_key_base64.as_bytes())
+        .ok()?;
+    if bytes.len() < 2 {
+        return None;
+    }
+    let digest = Blake2b256::digest(&bytes);
+    Some(format!(
+        "0x{}",
+        data_encoding::HEXLOWER.encode(&digest.digest)
+    ))
+}
+
+// From a sui.aliases file. Example:
+//   [ { "alias": "sb-1-ed25519", "public_key_base64": "AL..." }, ... ]
+fn parse_keystore_aliases(content: &str) -> Vec<(String, Option<String>)> {
+    let aliases: Vec<serde_json::Value> = serde_json::from_str(content).unwrap_or_default();
+    aliases
+        .iter()
+        .filter_map(|entry| {
+            let address =
+                address_from_public_key_base64(entry.get("public_key_base64")?.as_str()?)?;
+            let alias = entry
+                .get("alias")
+                .and_then(|v| v.as_str())
+                .map(str::to_string);
+            Some((address, alias))
+        })
+        .collect()
+}
+
+// From a client.yaml ("active_address: <address>" line).
+fn parse_active_address(content: &str) -> Option<String> {
+    content.lines().find_map(|line| {
+        let address = line.trim().strip_prefix("active_address:")?;
+        let address = address.trim().trim_matches(|c| c == '"' || c == '\'');
+        address.starts_with("0x").then(|| address.to_lowercase())
+    })
+}
+
+// From the result of a suix_getAllBalances. SUI first.
+fn parse_all_balances(result: &serde_json::Value) -> Vec<CoinBalance> {
+    let mut coins: Vec<CoinBalance> = result
+        .as_array()
+        .map(|balances| {
+            balances
+                .iter()
+                .filter_map(|balance| {
+                    Some(CoinBalance {
+                        coin_type: balance.get("coinType")?.as_str()?.to_string(),
+                        balance: balance.get("totalBalance")?.as_str()?.to_string(),
+                        object_count: balance
+                            .get("coinObjectCount")
+                            .and_then(|v| v.as_u64())
+                            .unwrap_or(0),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    coins.sort_by(|a, b| {
+        (a.coin_type != SUI_COIN_TYPE, &a.coin_type)
+            .cmp(&(b.coin_type != SUI_COIN_TYPE, &b.coin_type))
+    });
+    coins
+}
+
+impl PollingTraitObject {
+    // Addresses to track as (address, alias), keystore first. Also returns the
+    // configured ones (balance_addresses).
+    async fn get_tracked_addresses(&self) -> (Vec<(String, Option<String>)>, Vec<String>) {
+        let workdir_idx = self.params.workdir_idx;
+        let mut addresses = Vec::new();
+        if workdir_idx == WORKDIR_IDX_LOCALNET || workdir_idx == WORKDIR_IDX_DEVNET {
+            let aliases_path = common::shared_types::get_workdir_paths(workdir_idx)
+                .workdir_root_path()
+                .join("config")
+                .join("sui.aliases");
+            addresses = tokio::fs::read_to_string(aliases_path)
+                .await
+                .map(|content| parse_keystore_aliases(&content))
+                .unwrap_or_default();
+        }
+
+        let configured: Vec<String> = {
+            let globals_read_guard = self.params.globals.get_config(workdir_idx).read().await;
+            let config = &*globals_read_guard;
+            config.user_config.balance_addresses().to_vec()
+        };
+        for address in &configured {
+            if !addresses.iter().any(|(a, _)| a == address) {
+                addresses.push((address.clone(), None));
+            }
+        }
+        (addresses, configured)
+    }
+
+    async fn query_address_balance(
+        client: &reqwest::Client,
+        uri: &str,
+        address: &str,
+        balance: &mut AddressBalance,
+    ) -> Result<()> {
+        let result = rpc_call(
+            client,
+            uri,
+            "suix_getAllBalances",
+            serde_json::json!([address]),
+        )
+        .await?;
+        balance.coins = parse_all_balances(&result);
+        balance.sui_balance = balance
+            .coins
+            .iter()
+            .find(|coin| coin.coin_type == SUI_COIN_TYPE)
+            .map(|coin| coin.balance.clone())
+            .unwrap_or_else(|| "0".to_string());
+
+        let mut cursor: Option<String> = None;
+        let mut owned_objects = 0;
+        let mut has_next_page = false;
+        for _ in 0..OWNED_OBJECTS_MAX_PAGES {
+            let result = rpc_call(
+                client,
+                uri,
+                "suix_getOwnedObjects",
+                serde_json::json!([address, null, cursor, OWNED_OBJECTS_PAGE_LIMIT]),
+            )
+            .await?;
+            owned_objects += result
+                .get("data")
+                .and_then(|v| v.as_array())
+                .map_or(0, |data| data.len() as u64);
+            has_next_page = result
+                .get("hasNextPage")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            cursor = result
+                .get("nextCursor")
+                .and_then(|v| v.as_str())
+                .map(str::to_string);
+            if !has_next_page || cursor.is_none() {
+                break;
+            }
+        }
+        balance.owned_objects = owned_objects;
+        balance.owned_objects_more = has_next_page;
+        Ok(())
+    }
+
+    async fn update_globals_address_balances(&mut self) {
+        let workdir_idx = self.params.workdir_idx;
+        let workdir = WORKDIRS_KEYS[workdir_idx as usize].to_string();
+
+        // Not an error when the proxy is not running (e.g. workdir stopped).
+        let uri = match get_proxy_uri(&self.params.globals, workdir_idx).await {
+            Ok(uri) => uri,
+            Err(_) => return,
+        };
+        let client = match proxy_client() {
+            Ok(client) => client,
+            Err(e) => {
+                log::error!("{} balance poller client: {}", workdir, e);
+                return;
+            }
+        };
+
+        let (addresses, configured) = self.get_tracked_addresses().await;
+        let active_address = tokio::fs::read_to_string(
+            common::shared_types::get_workdir_paths(workdir_idx)
+                .workdir_root_path()
+                .join("config")
+                .join("client.yaml"),
+        )
+        .await
+        .ok()
+        .and_then(|content| parse_active_address(&content));
+        let low_gas_threshold = {
+            let globals_read_guard = self.params.globals.get_config(workdir_idx).read().await;
+            let config = &*globals_read_guard;
+            config.user_config.low_gas_threshold_mist()
+        };
+
+        // Prior values are kept for an address that can't be queried.
+        let mut prior: HashMap<String, AddressBalance> = {
+            let globals_read_guard = self.params.globals.get_balances(workdir_idx).read().await;
+            let globals = &*globals_read_guard;
+            globals
+                .ui
+                .as_ref()
+                .map(|ui| {
+                    ui.get_data()
+                        .addresses
+                        .iter()
+                        .map(|balance| (balance.address.clone(), balance.clone()))
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        let mut resp = AddressBalancesResponse::new();
+        resp.header.method = "getAddressBalances".to_string();
+        resp.header.key = Some(workdir.clone());
+        resp.low_gas_threshold = low_gas_threshold;
+        for (address, alias) in addresses {
+            let mut balance = prior.remove(&address).unwrap_or_else(|| AddressBalance {
+                address: address.clone(),
+                alias: None,
+                active: false,
+                sui_balance: "0".to_string(),
+                coins: Vec::new(),
+                owned_objects: 0,
+                owned_objects_more: false,
+                low_gas: false,
+                error: None,
+            });
+            balance.alias = alias;
+            balance.active = active_address.as_ref() == Some(&address);
+            balance.error = Self::query_address_balance(&client, &uri, &address, &mut balance)
+                .await
+                .err()
+                .map(|e| e.to_string());
+            let is_checked = balance.active || configured.contains(&address);
+            balance.low_gas = is_checked
+                && low_gas_threshold > 0
+                && balance.sui_balance.parse::<u128>().unwrap_or(0) < low_gas_threshold as u128;
+            resp.addresses.push(balance);
+        }
+
+        let mut globals_write_guard = self.params.globals.get_balances(workdir_idx).write().await;
+        let globals = &mut *globals_write_guard;
+        if let Some(ui) = &mut globals.ui {
+            // Update globals.ui with resp if different. This will update the uuid_data accordingly.
+            ui.take_if_not_equal(resp);
+        } else {
+            globals.ui = Some(Versioned::new(resp));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keystore_addresses_and_balances() {
+        let aliases = r#"[{"alias":"sb-1","public_key_base64":"AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8g"}]"#;
+        assert_eq!(
+            parse_keystore_aliases(aliases),
+            vec![(
+                "0xb7634fe13c7aca3914ee896e22cfabc9da5b4f13e72a2ccbecb6d44bbda95bcc".to_string(),
+                Some("sb-1".to_string())
+            )]
+        );
+        assert_eq!(
+            parse_active_address("keystore:\n  File: x\nactive_address: \"0xF7AE\"\n"),
+            Some("0xf7ae".to_string())
+        );
+
+        let coins = parse_all_balances(&serde_json::json!([
+            { "coinType": "0xdba3::usdc::USDC", "coinObjectCount": 1, "totalBalance": "5000" },
+            { "coinType": "0x2::sui::SUI", "coinObjectCount": 3, "totalBalance": "998990120" }
+        ]));
+        assert_eq!(coins[0].coin_type, SUI_COIN_TYPE);
+        assert_eq!(coins[0].object_count, 3);
+        assert_eq!(coins[1].balance, "5000");
+    }
+}
@@ -5,6 +5,11 @@
 // Responsible to:
 //  - Periodically and on-demand do "status" CLI commands and update globals.
 //
+// The structured "status --json" output is used. When the output is not JSON:
+//  - a script error (e.g. workdir not initialized) is parsed as is.
+//  - otherwise (older scripts rejecting --json), "status" is re-run without --json
+//    and its human-readable output is parsed.
+//
 // The task is auto-restart in case of panic.
 //
 // Design:
@@ -13,6 +18,8 @@
 //
 
 use axum::async_trait;
+use serde::Deserialize;
+
 use common::{
     basic_types::{AdminControllerTx, GenericTx, Instantiable, WorkdirContext, WorkdirIdx},
     shared_types::WORKDIRS_KEYS,
@@ -87,7 +94,146 @@ impl CliPoller {
     }
 }
 
+// Output of "<workdir> status --json".
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StatusJson {
+    workdir: String,
+    status: String,
+    #[serde(default)]
+    client_version: Option<String>,
+    #[serde(default)]
+    asui_selection: Option<String>,
+    #[serde(default)]
+    services: Vec<StatusJsonService>,
+}
+
+#[derive(Deserialize)]
+struct StatusJsonService {
+    label: String,
+    status: String,
+    #[serde(default)]
+    pid: Option<u64>,
+    #[serde(default)]
+    etime: Option<String>, // Elapsed time since the process started ([[dd-]hh:]mm:ss).
+    #[serde(default)]
+    version: Option<String>,
+}
+
+// Convert a "ps -o etime" elapsed time to seconds.
+fn parse_etime(etime: &str) -> Option<u64> {
+    let (days, hms) = match etime.split_once('-') {
+        Some((days, hms)) => (days.parse::<u64>().ok()?, hms),
+        None => (0, etime),
+    };
+    let mut secs = 0;
+    let mut fields = 0;
+    for field in hms.split(':') {
+        secs = secs * 60 + field.parse::<u64>().ok()?;
+        fields += 1;
+    }
+    if !(2..=3).contains(&fields) {
+        return None;
+    }
+    Some(days * 86400 + secs)
+}
+
 impl PollingTraitObject {
+    // Returns None when the output is not a valid JSON status (the caller then
+    // falls back on the human-readable status).
+    fn convert_status_json_to_status_response(
+        cmd_response: &str,
+        workdir_name: &str,
+        resp: &mut WorkdirStatusResponse,
+    ) -> Option<(bool, Option<String>)> {
+        // The JSON is the last line (there might be warnings before).
+        let line = cmd_response
+            .lines()
+            .rev()
+            .find(|line| line.trim_start().starts_with('{'))?;
+        let status: StatusJson = serde_json::from_str(line.trim()).ok()?;
+        if status.workdir != workdir_name || status.status.is_empty() {
+            return None;
+        }
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        resp.status = Some(status.status);
+        resp.client_version = status
+            .client_version
+            .filter(|v| !v.is_empty())
+            .map(|v| v.trim_start_matches("sui ").to_string());
+        if !status.services.is_empty() {
+            let services = status
+                .services
+                .into_iter()
+                .map(|service| {
+                    let mut new_service = StatusService::new(service.label);
+                    new_service.status = Some(service.status);
+                    new_service.pid = service.pid;
+                    new_service.started_at = service
+                        .etime
+                        .as_deref()
+                        .and_then(parse_etime)
+                        .map(|uptime| now.saturating_sub(uptime));
+                    new_service.version = service
+                        .version
+                        .map(|v| v.trim_start_matches("sui ").to_string());
+                    new_service
+                })
+                .collect();
+            resp.services = Some(services);
+        }
+
+        let asui_selection = status
+            .asui_selection
+            .filter(|asui| WORKDIRS_KEYS.contains(&asui.as_str()));
+        Some((true, asui_selection))
+    }
+
+    // The started_at is derived from the elapsed time, so it may vary by a second from
+    // one poll to the next. Keep the prior value for the same process (avoid needless
+    // status version changes).
+    fn keep_started_at(resp: &mut WorkdirStatusResponse, prev: &WorkdirStatusResponse) {
+        let (Some(services), Some(prev_services)) = (&mut resp.services, &prev.services) else {
+            return;
+        };
+        for service in services.iter_mut() {
+            let prev_started_at = prev_services
+                .iter()
+                .find(|prev| prev.label == service.label && prev.pid == service.pid)
+                .and_then(|prev| prev.started_at);
+            if let (Some(started_at), Some(prev_started_at)) = (service.started_at, prev_started_at)
+            {
+                if started_at.abs_diff(prev_started_at) <= 2 {
+                    service.started_at = Some(prev_started_at);
+                }
+            }
+        }
+    }
+
+    // An error reported by the script, as detected by
+    // convert_status_cmd_resp_to_status_response.
+    fn is_script_error(cmd_response: &str) -> bool {
+        let cmd = common::utils::remove_ascii_color_code(cmd_response);
+        let lines: Vec<&str> = cmd
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with("---"))
+            .collect();
+        lines.iter().any(|line| line.starts_with("Error:"))
+            || lines.iter().take(2).any(|line| {
+                let line_lc = line.to_lowercase();
+                line_lc.contains("not initialized")
+                    || line_lc.contains("not found")
+                    || line_lc.contains("no such")
+                    || line_lc.contains("no command")
+            })
+    }
+
     fn convert_status_cmd_resp_to_status_response(
         cmd_response: String,
         workdir_name: String,
@@ -286,6 +432,45 @@ impl PollingTraitObject {
         (first_line_parsed, asui_selection)
     }
 
+    async fn get_low_gas_warning(&self) -> Option<String> {
+        let globals_read_guard = self
+            .params
+            .globals
+            .get_balances(self.params.workdir_idx)
+            .read()
+            .await;
+        let globals = &*globals_read_guard;
+        let balances = globals.ui.as_ref()?.get_data();
+        let low_gas: Vec<&str> = balances
+            .addresses
+            .iter()
+            .filter(|balance| balance.low_gas)
+            .map(|balance| balance.alias.as_deref().unwrap_or(&balance.address))
+            .collect();
+        if low_gas.is_empty() {
+            return None;
+        }
+        Some(format!(
+            "Below {} MIST: {}",
+            balances.low_gas_threshold,
+            low_gas.join(", ")
+        ))
+    }
+
+    async fn status_cmd(&self, workdir: &str, json: bool) -> String {
+        let json_param = if json { " --json" } else { "" };
+        match AdminController::send_shell_exec(
+            &self.params.admctrl_tx,
+            self.params.workdir_idx,
+            format!("{} status --daemoncall{}", workdir, json_param),
+        )
+        .await
+        {
+            Ok(cmd_resp) => cmd_resp,
+            Err(e) => format!("Error: {e}"),
+        }
+    }
+
     async fn update_globals_workdir_status(&mut self) {
         let workdir_idx = self.params.workdir_idx;
         let workdir = WORKDIRS_KEYS[workdir_idx as usize].to_string();
@@ -297,28 +482,39 @@ impl PollingTraitObject {
 
         // Get an update with a "<workdir> status" shell call.
         // Map it into the resp.
-        let cmd_resp = match AdminController::send_shell_exec(
-            &self.params.admctrl_tx,
-            workdir_idx,
-            format!("{} status --daemoncall", workdir),
-        )
-        .await
-        {
-            Ok(cmd_resp) => cmd_resp,
-            Err(e) => format!("Error: {e}"),
-        };
-
+        //
         // Do not assumes that if shell_exec returns OK that the command was successful.
         // Parse the command response to figure out if really successful.
         resp.status = None;
+        let cmd_resp = self.status_cmd(&workdir, true).await;
         let (is_successful, asui_selection) =
-            Self::convert_status_cmd_resp_to_status_response(cmd_resp, workdir.clone(), &mut resp);
+            match Self::convert_status_json_to_status_response(&cmd_resp, &workdir, &mut resp) {
+                Some(parsed) => parsed,
+                None => {
+                    // Not JSON on errors (e.g. workdir not initialized). These are
+                    // detected by the text parser on the same output. Anything else
+                    // is likely the --json being rejected, so retry without it.
+                    let cmd_resp = if Self::is_script_error(&cmd_resp) {
+                        cmd_resp
+                    } else {
+                        self.status_cmd(&workdir, false).await
+                    };
+                    Self::convert_status_cmd_resp_to_status_response(
+                        cmd_resp,
+                        workdir.clone(),
+                        &mut resp,
+                    )
+                }
+            };
 
         // Default to DOWN if could not identify the status.
         if resp.status.is_none() {
             resp.status = Some("DOWN".to_string());
         }
 
+        // Low gas warning (see balance_poller.rs).
+        resp.low_gas_warning = self.get_low_gas_warning().await;
+
         if is_successful && asui_selection.is_some() {
             self.params.globals.set_asui_selection(asui_selection).await;
         }
@@ -328,6 +524,7 @@ impl PollingTraitObject {
             let mut globals_write_guard = self.params.globals.get_status(workdir_idx).write().await;
             let globals = &mut *globals_write_guard;
             if let Some(ui) = &mut globals.ui {
+                Self::keep_started_at(&mut resp, ui.get_data());
                 // Update globals.ui with resp if different. This will update the uuid_data accordingly.
                 let _was_updated = ui.take_if_not_equal(resp.clone());
                 //if was_updated {
@@ -346,3 +543,51 @@ impl PollingTraitObject {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_status_json() {
+        assert_eq!(parse_etime("05:07"), Some(307));
+        assert_eq!(parse_etime("01:00:00"), Some(3600));
+        assert_eq!(parse_etime("2-00:00:01"), Some(172801));
+        assert_eq!(parse_etime("12"), None);
+
+        let output = r#"Warning: something
+{"workdir":"localnet","status":"DEGRADED","userRequest":"start","clientVersion":"sui 1.30.1-abc","asuiSelection":"localnet","services":[{"label":"Localnet process","status":"OK","pid":1234,"etime":"00:10","version":"sui 1.30.1-abc"},{"label":"Faucet process","status":"NOT RUNNING"}]}"#;
+        let mut resp = WorkdirStatusResponse::new();
+        let parsed = PollingTraitObject::convert_status_json_to_status_response(
+            output, "localnet", &mut resp,
+        );
+        assert_eq!(parsed, Some((true, Some("localnet".to_string()))));
+        assert_eq!(resp.status.as_deref(), Some("DEGRADED"));
+        assert_eq!(resp.client_version.as_deref(), Some("1.30.1-abc"));
+        let services = resp.services.as_ref().unwrap();
+        assert_eq!(services.len(), 2);
+        assert_eq!(services[0].pid, Some(1234));
+        assert!(services[0].started_at.is_some());
+        assert_eq!(services[1].status.as_deref(), Some("NOT RUNNING"));
+
+        // Fallback on the text parser.
+        let mut resp = WorkdirStatusResponse::new();
+        let text = "localnet OK\n---\nLocalnet process : OK ( pid 1234 )";
+        assert!(PollingTraitObject::convert_status_json_to_status_response(
+            text, "localnet", &mut resp
+        )
+        .is_none());
+        assert!(resp.status.is_none());
+
+        // Older scripts reject --json (re-run without it), unlike a real error.
+        assert!(!PollingTraitObject::is_script_error(
+            "Unknown parameter passed: --json"
+        ));
+        assert!(PollingTraitObject::is_script_error(
+            "localnet not initialized"
+        ));
+        assert!(PollingTraitObject::is_script_error(
+            "\nError: suibase not installed"
+        ));
+    }
+}
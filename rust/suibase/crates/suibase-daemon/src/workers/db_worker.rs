@@ -6,11 +6,41 @@
 //     is responsible to perform and "confirm" the writing. Particularly, if the writing
 //     is not successful (e.g. panic), then the data remains in the queue.
 //
+// Periodic maintenance (on audit):
+//   - Purge the events per the retention config (events_max_age_days, events_max_rows).
+//   - Delete the unused package instances (and their cursor).
+//   - Delete the package instances no longer in published-data (e.g. removed by the
+//     packages_keep_last config of the PackagesPoller) with all their events,
+//     transactions and cursor.
+//   - VACUUM the file, at most once a day, when events were purged.
+//
+// A typed view is created for every user event type (see events_decoder.rs).
+//
+// The webhook_worker is notified after every event written (see webhook_worker.rs).
+//
+// The transactions calling a published package are stored in {workdir}_sui_tx, with
+// their object changes in {workdir}_sui_object_change (see tx_poller.rs). Same
+// retention as the events.
+//
+// "wait_for_event" requests are answered from the DB (events after the cursor) or,
+// if none matches yet, kept pending until a matching user event is written.
+//
+// Migration: the DB was previously in memory (lost on every daemon restart). The
+// sqlite.db file is created on the first start, there is nothing to convert. Every
+// package then starts without a cursor, so only its most recent events are fetched
+// (events_initial_backfill). Deleting the file (daemon stopped) resets the indexer.
+//
 // The thread is auto-restart in case of panic.
 
+use std::collections::HashSet;
 use std::sync::Arc;
 
-use crate::shared_types::Globals;
+use crate::shared_types::{EventWaitParams, Globals, MoveStructLayout};
+use crate::workers::events_decoder::run_events_decoder;
+use crate::workers::events_export::{
+    resolve_export_path, EventsExportParams, EventsExportResult, EventsExportWriter, ExportedEvent,
+};
+use crate::workers::tx_poller::SuiTxSummary;
 
 use common::basic_types::{
     self, AutoThread, DBTable, GenericChannelMsg, GenericRx, GenericTx, Runnable, WorkdirIdx,
@@ -22,6 +52,7 @@ use axum::async_trait;
 
 use tokio::sync::Mutex;
 use tokio_graceful_shutdown::{FutureExt, SubsystemHandle};
+use tracing::Instrument;
 
 // Schema: One entry per Package.
 
@@ -42,7 +73,7 @@ impl DBTable for Package {
         namespace: Option<String>,
         _name_suffix: Option<String>,
     ) -> rusqlite::Result<()> {
-        // TODO Recreate the table if not the latest schema version.
+        // Schema changes of existing tables are done with MIGRATIONS.
         let sql = format!(
             "CREATE TABLE IF NOT EXISTS {0}_{1}_package (
                 id              INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -309,7 +340,7 @@ impl DBTable for PackageInstance {
         namespace: Option<String>,
         _name_suffix: Option<String>,
     ) -> rusqlite::Result<()> {
-        // TODO Recreate the table if not the latest schema version.
+        // Schema changes of existing tables are done with MIGRATIONS.
         let sql = format!(
             "CREATE TABLE IF NOT EXISTS {0}_{1}_package_instance (
                 id              INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -325,6 +356,114 @@ impl DBTable for PackageInstance {
 }
 
 impl PackageInstance {
+    // Delete the instances that are no longer the latest of their package and have
+    // no events left (after retention). Their cursor is deleted as well.
+    //
+    // Returns the number of deleted instances.
+    fn delete_unused_in_db(conn: &Connection, workdir_name: &str) -> rusqlite::Result<usize> {
+        let mut no_events: Vec<String> = event_table_suffixes()
+            .iter()
+            .map(|suffix| {
+                format!(
+                    "NOT EXISTS (SELECT 1 FROM {0}_sui_event_{1} AS e
+                    WHERE e.package_instance_id = {0}_sui_package_instance.id)",
+                    workdir_name, suffix
+                )
+            })
+            .collect();
+        no_events.push(format!(
+            "NOT EXISTS (SELECT 1 FROM {0}_sui_tx AS t
+            WHERE t.package_instance_id = {0}_sui_package_instance.id)",
+            workdir_name
+        ));
+        let sql = format!(
+            "DELETE FROM {0}_sui_package_instance
+            WHERE id NOT IN (
+                SELECT latest_instance_id FROM {0}_sui_package WHERE latest_instance_id IS NOT NULL
+            ) AND {1}",
+            workdir_name,
+            no_events.join(" AND ")
+        );
+        let n_deleted = conn.execute(&sql, [])?;
+
+        let sql = format!(
+            "DELETE FROM {0}_sui_event_cursor
+            WHERE package_id NOT IN (SELECT package_id FROM {0}_sui_package_instance)
+            AND package_id NOT LIKE 'subscription:%'",
+            workdir_name
+        );
+        conn.execute(&sql, [])?;
+        Ok(n_deleted)
+    }
+
+    // Delete the instances whose package_id is not in published_ids, along with their
+    // events, transactions (and object changes) and cursor. The subscriptions are not
+    // published packages and are kept.
+    //
+    // Returns the number of deleted instances.
+    fn delete_removed_in_db(
+        conn: &Connection,
+        workdir_name: &str,
+        published_ids: &HashSet<String>,
+    ) -> rusqlite::Result<usize> {
+        let sql = format!(
+            "SELECT i.id, i.package_id
+            FROM {0}_sui_package_instance AS i
+            JOIN {0}_sui_package AS p ON p.id = i.parent_id
+            WHERE p.package_uuid NOT LIKE 'subscription:%'",
+            workdir_name
+        );
+        let mut stmt = conn.prepare(&sql)?;
+        let removed: Vec<(u64, String)> = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?
+            .into_iter()
+            .filter(|(_, package_id)| !published_ids.contains(package_id))
+            .collect();
+
+        for (instance_id, package_id) in &removed {
+            let tx = conn.unchecked_transaction()?;
+            for suffix in event_table_suffixes() {
+                let sql = format!(
+                    "DELETE FROM {}_sui_event_{} WHERE package_instance_id = ?1",
+                    workdir_name, suffix
+                );
+                tx.execute(&sql, [instance_id])?;
+            }
+            let sql = format!(
+                "DELETE FROM {0}_sui_object_change WHERE tx_id IN (
+                    SELECT id FROM {0}_sui_tx WHERE package_instance_id = ?1
+                )",
+                workdir_name
+            );
+            tx.execute(&sql, [instance_id])?;
+            let sql = format!(
+                "DELETE FROM {}_sui_tx WHERE package_instance_id = ?1",
+                workdir_name
+            );
+            tx.execute(&sql, [instance_id])?;
+            let sql = format!(
+                "DELETE FROM {}_sui_event_cursor WHERE package_id = ?1",
+                workdir_name
+            );
+            tx.execute(&sql, [package_id])?;
+            // The latest_instance_id has no ON DELETE action.
+            let sql = format!(
+                "UPDATE {}_sui_package SET latest_instance_id = NULL
+                WHERE latest_instance_id = ?1",
+                workdir_name
+            );
+            tx.execute(&sql, [instance_id])?;
+            let sql = format!(
+                "DELETE FROM {}_sui_package_instance WHERE id = ?1",
+                workdir_name
+            );
+            tx.execute(&sql, [instance_id])?;
+            tx.commit()?;
+        }
+        Ok(removed.len())
+    }
+
     fn insert_in_db(&mut self, conn: &Connection, package: &Package) -> rusqlite::Result<()> {
         let table_name = format!("{}_instance", package.table_fullname);
         let sql = format!(
@@ -396,7 +535,7 @@ impl DBTable for SuiEvent {
         namespace: Option<String>,
         name_suffix: Option<String>,
     ) -> rusqlite::Result<()> {
-        // TODO Recreate the table if not the latest schema version.
+        // Schema changes of existing tables are done with MIGRATIONS.
         let sql = format!(
             "CREATE TABLE IF NOT EXISTS {0}_{1}_event_{2} (
                 id              INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -422,16 +561,371 @@ impl SuiEvent {
             event_json,
         }
     }
+
+    // Delete the events older than min_timestamp_ms and, for every package instance, the
+    // oldest events above max_rows. A limit of zero is "no limit".
+    //
+    // Returns the number of deleted events.
+    fn purge_in_db(
+        conn: &Connection,
+        table_name: &str,
+        min_timestamp_ms: u64,
+        max_rows: u64,
+    ) -> rusqlite::Result<usize> {
+        let mut n_deleted = 0;
+        if min_timestamp_ms > 0 {
+            let sql = format!("DELETE FROM {} WHERE timestamp < ?1", table_name);
+            n_deleted += conn.execute(&sql, [min_timestamp_ms])?;
+        }
+        if max_rows > 0 {
+            let sql = format!(
+                "DELETE FROM {0} WHERE id IN (
+                    SELECT id FROM (
+                        SELECT id, ROW_NUMBER() OVER (
+                            PARTITION BY package_instance_id ORDER BY id DESC
+                        ) AS row_number
+                        FROM {0}
+                    ) WHERE row_number > ?1
+                )",
+                table_name
+            );
+            n_deleted += conn.execute(&sql, [max_rows])?;
+        }
+        Ok(n_deleted)
+    }
+
+    // Write the events matching the params (all tables merged, in chronological order).
+    //
+    // Rows are streamed to the writer, so this works for a large history.
+    fn export_from_db(
+        conn: &Connection,
+        workdir_name: &str,
+        params: &EventsExportParams,
+        writer: &mut EventsExportWriter,
+    ) -> anyhow::Result<()> {
+        let mut selects = Vec::new();
+        for suffix in event_table_suffixes() {
+            if suffix.starts_with("console_") && params.event_type.is_some() {
+                continue; // Console events have no Move type.
+            }
+            selects.push(select_events_sql(
+                workdir_name,
+                &suffix,
+                "e.timestamp >= ?1 AND e.timestamp < ?2",
+            ));
+        }
+        let sql = format!("{} ORDER BY 2, 1", selects.join(" UNION ALL "));
+
+        let from_ms = params.from_ms.unwrap_or(0).min(i64::MAX as u64) as i64;
+        let to_ms = params.to_ms.unwrap_or(u64::MAX).min(i64::MAX as u64) as i64;
+        let package = package_filter_param(params.package.as_ref());
+
+        let mut stmt = conn.prepare(&sql)?;
+        let mut rows = stmt.query(rusqlite::params![
+            from_ms,
+            to_ms,
+            package,
+            params.event_type
+        ])?;
+        while let Some(row) = rows.next()? {
+            writer.write(&exported_event_from_row(row)?)?;
+        }
+        Ok(())
+    }
+
+    // First user event in (after_id, last_id] matching the filter.
+    //
+    // Package and type are narrowed in SQL, the rest of the filter is done here. Read
+    // by pages of WAIT_PAGE_ROWS to not hold a read transaction for the whole history.
+    fn find_in_db(
+        conn: &Connection,
+        workdir_name: &str,
+        filter: &crate::shared_types::EventWaitFilter,
+        after_id: i64,
+        last_id: i64,
+    ) -> anyhow::Result<Option<ExportedEvent>> {
+        let sql = format!(
+            "{} ORDER BY e.id LIMIT {}",
+            select_events_sql(workdir_name, "user_0", "e.id > ?1 AND e.id <= ?2"),
+            WAIT_PAGE_ROWS
+        );
+        let package = package_filter_param(filter.package.as_ref());
+
+        let mut stmt = conn.prepare(&sql)?;
+        let mut from_id = after_id;
+        loop {
+            let mut rows = stmt.query(rusqlite::params![
+                from_id,
+                last_id,
+                package,
+                filter.event_type
+            ])?;
+            let mut n_rows = 0;
+            while let Some(row) = rows.next()? {
+                let event = exported_event_from_row(row)?;
+                n_rows += 1;
+                from_id = event.id;
+                if waited_event_matches(filter, &event) {
+                    return Ok(Some(event));
+                }
+            }
+            if n_rows < WAIT_PAGE_ROWS {
+                return Ok(None);
+            }
+        }
+    }
+}
+
+// The stored user event_json is {"sender", "message"} where "message" is the
+// stringified Sui event.
+fn waited_event_matches(
+    filter: &crate::shared_types::EventWaitFilter,
+    event: &ExportedEvent,
+) -> bool {
+    let sui_event = serde_json::from_str::<serde_json::Value>(&event.event_json)
+        .ok()
+        .and_then(|v| {
+            v.get("message")
+                .and_then(|m| m.as_str())
+                .map(str::to_string)
+        })
+        .and_then(|message| serde_json::from_str::<serde_json::Value>(&message).ok());
+    match sui_event {
+        Some(sui_event) => filter.matches(&event.package_name, &event.package_id, &sui_event),
+        None => false,
+    }
+}
+
+// Schema: One entry per network package_id, and one per "event_subscriptions" of
+// suibase.yaml (keyed by the "subscription:{label}" uuid).
+//
+// Last event persisted (Sui EventID). Used as the starting point for backfilling
+// the events missed while not subscribed (see events_backfill.rs).
+#[derive(Debug)]
+struct EventCursor {
+    package_id: String, // No leading 0x, or the uuid of a subscription.
+    tx_digest: String,
+    event_seq: String,
+    timestamp_ms: u64,
+}
+
+impl DBTable for EventCursor {
+    fn create_table(
+        conn: &Connection,
+        workdir_name: String,
+        namespace: Option<String>,
+        _name_suffix: Option<String>,
+    ) -> rusqlite::Result<()> {
+        let sql = format!(
+            "CREATE TABLE IF NOT EXISTS {0}_{1}_event_cursor (
+                package_id      TEXT PRIMARY KEY,
+                tx_digest       TEXT NOT NULL,
+                event_seq       TEXT NOT NULL,
+                timestamp       INTEGER NOT NULL
+            )",
+            workdir_name,
+            namespace.unwrap_or_else(|| "sui".to_string())
+        );
+        conn.execute(&sql, [])?;
+        Ok(())
+    }
+}
+
+impl EventCursor {
+    // Never move the cursor backward (e.g. an older event arriving late from a backfill).
+    fn upsert_in_db(&self, conn: &Connection, workdir_name: &str) -> rusqlite::Result<()> {
+        let sql = format!(
+            "INSERT INTO {0}_sui_event_cursor (package_id, tx_digest, event_seq, timestamp)
+            VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT(package_id) DO UPDATE SET
+                tx_digest = excluded.tx_digest,
+                event_seq = excluded.event_seq,
+                timestamp = excluded.timestamp
+            WHERE excluded.timestamp >= timestamp",
+            workdir_name
+        );
+        conn.execute(
+            &sql,
+            rusqlite::params![
+                self.package_id,
+                self.tx_digest,
+                self.event_seq,
+                self.timestamp_ms
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn get_from_db(
+        conn: &Connection,
+        workdir_name: &str,
+        package_id: &str,
+    ) -> rusqlite::Result<Option<Self>> {
+        let sql = format!(
+            "SELECT tx_digest, event_seq, timestamp FROM {0}_sui_event_cursor WHERE package_id = ?1",
+            workdir_name
+        );
+        let mut stmt = conn.prepare(&sql)?;
+        let mut rows = stmt.query([package_id])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(Self {
+                package_id: package_id.to_string(),
+                tx_digest: row.get(0)?,
+                event_seq: row.get(1)?,
+                timestamp_ms: row.get(2)?,
+            })),
+            None => Ok(None),
+        }
+    }
+}
+
+// Schema: One entry per transaction calling a published package (see tx_poller.rs).
+//
+// The same transaction is stored once per package instance it calls.
+#[derive(Debug)]
+struct SuiTx {
+    id: u64,
+    package_instance_id: u64, // Foreign key into PackageInstance table.
+    summary: SuiTxSummary,
+}
+
+impl DBTable for SuiTx {
+    fn create_table(
+        conn: &Connection,
+        workdir_name: String,
+        namespace: Option<String>,
+        _name_suffix: Option<String>,
+    ) -> rusqlite::Result<()> {
+        let namespace = namespace.unwrap_or_else(|| "sui".to_string());
+        let sql = format!(
+            "CREATE TABLE IF NOT EXISTS {0}_{1}_tx (
+                id              INTEGER PRIMARY KEY AUTOINCREMENT,
+                package_instance_id INTEGER NOT NULL REFERENCES {0}_{1}_package_instance (id) ON DELETE CASCADE,
+                digest          TEXT NOT NULL,
+                timestamp       INTEGER NOT NULL,
+                checkpoint      INTEGER,
+                sender          TEXT NOT NULL,
+                status          TEXT NOT NULL,
+                error           TEXT,
+                gas_used        INTEGER NOT NULL,
+                UNIQUE (package_instance_id, digest)
+            )",
+            workdir_name, namespace
+        );
+        conn.execute(&sql, [])?;
+        let sql = format!(
+            "CREATE TABLE IF NOT EXISTS {0}_{1}_object_change (
+                id              INTEGER PRIMARY KEY AUTOINCREMENT,
+                tx_id           INTEGER NOT NULL REFERENCES {0}_{1}_tx (id) ON DELETE CASCADE,
+                change_type     TEXT NOT NULL,
+                object_id       TEXT NOT NULL,
+                object_type     TEXT,
+                version         TEXT
+            )",
+            workdir_name, namespace
+        );
+        conn.execute(&sql, [])?;
+        // Index used by the retention queries (same as the event tables).
+        let sql = format!(
+            "CREATE INDEX IF NOT EXISTS {0}_{1}_tx_instance_idx
+            ON {0}_{1}_tx (package_instance_id, timestamp)",
+            workdir_name, namespace
+        );
+        conn.execute(&sql, [])?;
+        let sql = format!(
+            "CREATE INDEX IF NOT EXISTS {0}_{1}_object_change_tx_idx
+            ON {0}_{1}_object_change (tx_id)",
+            workdir_name, namespace
+        );
+        conn.execute(&sql, [])?;
+        Ok(())
+    }
+}
+
+impl SuiTx {
+    // Returns false when the transaction was already stored for this package instance.
+    //
+    // The transaction and its object changes are written atomically.
+    fn insert_in_db(&mut self, conn: &Connection, workdir_name: &str) -> rusqlite::Result<bool> {
+        let tx = conn.unchecked_transaction()?;
+        let sql = format!(
+            "INSERT INTO {0}_sui_tx
+                (package_instance_id, digest, timestamp, checkpoint, sender, status, error, gas_used)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+            ON CONFLICT(package_instance_id, digest) DO NOTHING
+            RETURNING id",
+            workdir_name
+        );
+        let mut stmt = tx.prepare(&sql)?;
+        let mut rows = stmt.query(rusqlite::params![
+            self.package_instance_id,
+            self.summary.digest,
+            self.summary.timestamp_ms,
+            self.summary.checkpoint,
+            self.summary.sender,
+            self.summary.status,
+            self.summary.error,
+            self.summary.gas_used
+        ])?;
+        let id = match rows.next()? {
+            Some(row) => row.get(0)?,
+            None => return Ok(false), // Rollback when tx is dropped (nothing written).
+        };
+        drop(rows);
+        drop(stmt);
+
+        let sql = format!(
+            "INSERT INTO {0}_sui_object_change (tx_id, change_type, object_id, object_type, version)
+            VALUES (?1, ?2, ?3, ?4, ?5)",
+            workdir_name
+        );
+        {
+            let mut stmt = tx.prepare(&sql)?;
+            for change in &self.summary.object_changes {
+                stmt.execute(rusqlite::params![
+                    id,
+                    change.change_type,
+                    change.object_id,
+                    change.object_type,
+                    change.version
+                ])?;
+            }
+        }
+        tx.commit()?;
+        self.id = id;
+        Ok(true)
+    }
+
+    // Digest of the most recent transaction stored for a network package_id.
+    fn get_cursor_from_db(
+        conn: &Connection,
+        workdir_name: &str,
+        package_id: &str,
+    ) -> rusqlite::Result<Option<String>> {
+        let sql = format!(
+            "SELECT t.digest FROM {0}_sui_tx AS t
+            JOIN {0}_sui_package_instance AS i ON i.id = t.package_instance_id
+            WHERE i.package_id = ?1
+            ORDER BY t.id DESC LIMIT 1",
+            workdir_name
+        );
+        let mut stmt = conn.prepare(&sql)?;
+        let mut rows = stmt.query([package_id])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(row.get(0)?)),
+            None => Ok(None),
+        }
+    }
 }
 
 // Schema: global variables.
 // This table have a single entry.
-const SCHEMA_VERSION: &str = "0.0.1";
+//
+// The schema_version is used to apply the MIGRATIONS on an existing DB file.
+const SCHEMA_VERSION: &str = "0.0.2";
 #[derive(Debug)]
 struct DBSuibaseConfig {
-    id: i32,
     version: String, // x.y.z *schema* version.
-    workdir_name: String,
 }
 
 impl DBTable for DBSuibaseConfig {
@@ -441,7 +935,6 @@ impl DBTable for DBSuibaseConfig {
         namespace: Option<String>,
         _name_suffix: Option<String>,
     ) -> rusqlite::Result<()> {
-        // TODO Recreate the table if not the latest schema version.
         let sql = format!(
             "CREATE TABLE IF NOT EXISTS {0}_{1}_config (
                 id              INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -455,30 +948,137 @@ impl DBTable for DBSuibaseConfig {
     }
 }
 impl DBSuibaseConfig {
-    pub fn new(workdir_name: String) -> Self {
-        Self {
-            id: 0,
-            version: SCHEMA_VERSION.to_string(),
-            workdir_name,
+    // None when the DB was just created (or created prior to versioning).
+    pub fn get_from_db(conn: &Connection) -> rusqlite::Result<Option<Self>> {
+        let mut stmt = conn.prepare("SELECT schema_version FROM all_sui_config WHERE id = 1")?;
+        let mut rows = stmt.query([])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(Self {
+                version: row.get(0)?,
+            })),
+            None => Ok(None),
         }
     }
-    pub fn insert(&mut self, conn: &Connection) -> rusqlite::Result<()> {
-        let sql = format!(
-            "INSERT INTO suibase_globals (version, workdir_name)
-            VALUES ({0}, {1})",
-            self.version, self.workdir_name
-        );
-        let mut stmt = conn.prepare(&sql)?;
-        self.id = stmt.query_row([], |row| row.get(0))?;
+
+    pub fn upsert_in_db(&self, conn: &Connection) -> rusqlite::Result<()> {
+        conn.execute(
+            "INSERT INTO all_sui_config (id, schema_version) VALUES (1, ?1)
+            ON CONFLICT(id) DO UPDATE SET schema_version = excluded.schema_version",
+            [&self.version],
+        )?;
         Ok(())
     }
 }
 
+// Forward migrations (from_version, to_version, migration).
+//
+// The tables are first created with "CREATE TABLE IF NOT EXISTS" at the "0.0.1" schema,
+// then each migration is applied in order up to SCHEMA_VERSION.
+type Migration = fn(&Connection) -> rusqlite::Result<()>;
+const MIGRATIONS: &[(&str, &str, Migration)] = &[("0.0.1", "0.0.2", migrate_add_event_indexes)];
+
+// Name suffix of every SuiEvent table (see open_db). Also used by the webhook_worker.
+pub(crate) fn event_table_suffixes() -> Vec<String> {
+    let mut suffixes: Vec<String> = (basic_types::EVENT_LEVEL_MIN..=basic_types::EVENT_LEVEL_MAX)
+        .map(|level| format!("console_{}", level))
+        .collect();
+    suffixes.push("user_0".to_string());
+    suffixes
+}
+
+// SELECT of the events of one table, read with exported_event_from_row. Also used by
+// the webhook_worker.
+//
+// The caller provides the condition on ?1 and ?2 (e.g. a timestamp or id range).
+// ?3 is an optional package (name or id) and ?4 an optional Move type prefix.
+pub(crate) fn select_events_sql(workdir_name: &str, suffix: &str, range_condition: &str) -> String {
+    let mut sql = format!(
+        "SELECT e.id, e.timestamp, p.package_name, i.package_id, '{1}', e.event_json
+        FROM {0}_sui_event_{1} AS e
+        JOIN {0}_sui_package_instance AS i ON i.id = e.package_instance_id
+        JOIN {0}_sui_package AS p ON p.id = i.parent_id
+        WHERE {2}
+        AND (?3 IS NULL OR p.package_name = ?3 OR i.package_id = ?3)",
+        workdir_name, suffix, range_condition
+    );
+    // Console events have no Move type.
+    sql.push_str(if suffix.starts_with("console_") {
+        " AND ?4 IS NULL"
+    } else {
+        " AND (?4 IS NULL OR json_extract(json_extract(e.event_json, '$.message'), '$.type') LIKE ?4 || '%')"
+    });
+    sql
+}
+
+pub(crate) fn exported_event_from_row(row: &rusqlite::Row) -> rusqlite::Result<ExportedEvent> {
+    Ok(ExportedEvent {
+        id: row.get(0)?,
+        timestamp_ms: row.get(1)?,
+        package_name: row.get(2)?,
+        package_id: row.get(3)?,
+        kind: row.get(4)?,
+        event_json: row.get(5)?,
+    })
+}
+
+// The ?3 parameter of select_events_sql. Package ids are stored without the leading 0x.
+pub(crate) fn package_filter_param(package: Option<&String>) -> Option<String> {
+    package.map(|p| p.trim_start_matches("0x").to_string())
+}
+
+// 0.0.2: Index used by the retention queries (and per package instance queries).
+fn migrate_add_event_indexes(conn: &Connection) -> rusqlite::Result<()> {
+    for workdir_name in common::shared_types::WORKDIRS_KEYS.iter() {
+        for suffix in event_table_suffixes() {
+            let sql = format!(
+                "CREATE INDEX IF NOT EXISTS {0}_sui_event_{1}_instance_idx
+                ON {0}_sui_event_{1} (package_instance_id, timestamp)",
+                workdir_name, suffix
+            );
+            conn.execute(&sql, [])?;
+        }
+    }
+    Ok(())
+}
+
+fn migrate_schema(conn: &Connection) -> rusqlite::Result<()> {
+    let mut version = DBSuibaseConfig::get_from_db(conn)?
+        .map(|config| config.version)
+        .unwrap_or_else(|| "0.0.1".to_string());
+
+    while version != SCHEMA_VERSION {
+        let migration = MIGRATIONS.iter().find(|(from, _, _)| *from == version);
+        let (_, to, migrate) = match migration {
+            Some(migration) => migration,
+            None => {
+                // e.g. DB file from a more recent version. Use it as-is (best effort).
+                log::warn!(
+                    "No DB migration from schema {} to {}",
+                    version,
+                    SCHEMA_VERSION
+                );
+                return Ok(());
+            }
+        };
+        let tx = conn.unchecked_transaction()?;
+        migrate(&tx)?;
+        DBSuibaseConfig {
+            version: to.to_string(),
+        }
+        .upsert_in_db(&tx)?;
+        tx.commit()?;
+        log::info!("DB schema migrated from {} to {}", version, to);
+        version = to.to_string();
+    }
+    Ok(())
+}
+
 #[derive(Clone)]
 pub struct DBWorkerParams {
     globals: Globals,
     event_rx: Arc<Mutex<GenericRx>>,
     event_tx: GenericTx,
+    webhook_worker_tx: GenericTx,
     workdir_idx: WorkdirIdx,
     workdir_name: String,
 }
@@ -488,6 +1088,7 @@ impl DBWorkerParams {
         globals: Globals,
         event_rx: GenericRx,
         event_tx: GenericTx,
+        webhook_worker_tx: GenericTx,
         workdir_idx: WorkdirIdx,
         workdir_name: String,
     ) -> Self {
@@ -495,6 +1096,7 @@ impl DBWorkerParams {
             globals,
             event_rx: Arc::new(Mutex::new(event_rx)),
             event_tx,
+            webhook_worker_tx,
             workdir_idx,
             workdir_name,
         }
@@ -517,10 +1119,55 @@ impl DBWorker {
     }
 }
 
+// Also used by the other connections to the same sqlite.db (e.g. webhook_worker).
+pub(super) const DB_BUSY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+const RETENTION_INTERVAL_SECS: u64 = 3600;
+const VACUUM_INTERVAL_SECS: u64 = 24 * 3600;
+
+// Rows read at once when searching the past events of a "wait_for_event".
+const WAIT_PAGE_ROWS: usize = 1000;
+
+// Shared between the EventWaiter and the detached search of the past events (see
+// process_wait_for_event). The first to answer takes the sender.
+type WaitRespChannel = Arc<std::sync::Mutex<Option<tokio::sync::oneshot::Sender<String>>>>;
+
+fn send_wait_resp(resp_channel: &WaitRespChannel, resp: String) {
+    if let Ok(mut sender) = resp_channel.lock() {
+        if let Some(sender) = sender.take() {
+            let _ = sender.send(resp);
+        }
+    }
+}
+
+// A pending "wait_for_event" request.
+#[derive(Debug)]
+struct EventWaiter {
+    params: EventWaitParams,
+    resp_channel: WaitRespChannel,
+}
+
+impl EventWaiter {
+    // Answered, or the requester gave up (API timeout or disconnect).
+    fn is_done(&self) -> bool {
+        match self.resp_channel.lock() {
+            Ok(sender) => sender.as_ref().is_none_or(|sender| sender.is_closed()),
+            Err(_) => true,
+        }
+    }
+}
+
 #[derive(Debug, Default)]
 struct DBManagement {
     conn: Option<Connection>,
     schema_ok: bool, // Set when all tables have been verified to exist.
+    last_retention: Option<tokio::time::Instant>,
+    last_vacuum: Option<tokio::time::Instant>,
+    purged_since_vacuum: usize,
+    // Event types with a typed view, and those with a layout being requested.
+    event_views: HashSet<String>,
+    event_views_pending: HashSet<String>,
+    event_waiters: Vec<EventWaiter>,
 }
 
 impl DBManagement {
@@ -528,6 +1175,12 @@ impl DBManagement {
         Self {
             conn: None,
             schema_ok: false,
+            last_retention: None,
+            last_vacuum: None,
+            purged_since_vacuum: 0,
+            event_views: HashSet::new(),
+            event_views_pending: HashSet::new(),
+            event_waiters: Vec::new(),
         }
     }
 }
@@ -587,6 +1240,112 @@ impl DBWorkerThread {
         } else {
             log::error!("Unexpected workdir_idx {:?}", msg);
         }
+
+        self.db.event_waiters.retain(|waiter| !waiter.is_done());
+
+        let retention_due = self
+            .db
+            .last_retention
+            .is_none_or(|t| t.elapsed().as_secs() >= RETENTION_INTERVAL_SECS);
+        if retention_due {
+            self.db.last_retention = Some(tokio::time::Instant::now());
+            self.do_db_maintenance().await;
+        }
+    }
+
+    async fn do_db_maintenance(&mut self) {
+        let (max_age_days, max_rows) = {
+            let globals_read_guard = self
+                .params
+                .globals
+                .get_config(self.params.workdir_idx)
+                .read()
+                .await;
+            let config = &*globals_read_guard;
+            (
+                config.user_config.events_max_age_days(),
+                config.user_config.events_max_rows(),
+            )
+        };
+        // Package ids currently in published-data. None until the PackagesPoller did
+        // its first update (nothing is deleted on a fresh start).
+        let published_ids: Option<HashSet<String>> = {
+            let globals_read_guard = self
+                .params
+                .globals
+                .get_packages(self.params.workdir_idx)
+                .read()
+                .await;
+            globals_read_guard.ui.as_ref().map(|ui| {
+                ui.get_data()
+                    .iter_package_instances()
+                    .map(|instance| instance.get_package_id().to_string())
+                    .collect()
+            })
+        };
+        let conn = match self.db.conn.as_ref() {
+            Some(conn) => conn,
+            None => return,
+        };
+        let workdir_name = &self.params.workdir_name;
+
+        let min_timestamp_ms = if max_age_days == 0 {
+            0
+        } else {
+            let now_ms = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0);
+            now_ms.saturating_sub(max_age_days as u64 * 24 * 3600 * 1000)
+        };
+
+        let mut n_purged = 0;
+        for suffix in event_table_suffixes() {
+            let table_name = format!("{}_sui_event_{}", workdir_name, suffix);
+            match SuiEvent::purge_in_db(conn, &table_name, min_timestamp_ms, max_rows) {
+                Ok(n) => n_purged += n,
+                Err(e) => log::error!("Failed to purge {} {:?}", table_name, e),
+            }
+        }
+        // Same columns used for the retention, the object changes are deleted in cascade.
+        let table_name = format!("{}_sui_tx", workdir_name);
+        if let Err(e) = SuiEvent::purge_in_db(conn, &table_name, min_timestamp_ms, max_rows) {
+            log::error!("Failed to purge {} {:?}", table_name, e);
+        }
+        match PackageInstance::delete_unused_in_db(conn, workdir_name) {
+            Ok(0) => {}
+            Ok(n) => log::info!("Deleted {} unused package instances", n),
+            Err(e) => log::error!("Failed to delete unused package instances {:?}", e),
+        }
+        if let Some(published_ids) = &published_ids {
+            match PackageInstance::delete_removed_in_db(conn, workdir_name, published_ids) {
+                Ok(0) => {}
+                Ok(n) => {
+                    log::info!(
+                        "Deleted {} package instances removed from published-data",
+                        n
+                    );
+                    self.db.purged_since_vacuum += n;
+                }
+                Err(e) => log::error!("Failed to delete removed package instances {:?}", e),
+            }
+        }
+        if n_purged > 0 {
+            log::info!("Purged {} events (retention)", n_purged);
+            self.db.purged_since_vacuum += n_purged;
+        }
+
+        let vacuum_due = self
+            .db
+            .last_vacuum
+            .is_none_or(|t| t.elapsed().as_secs() >= VACUUM_INTERVAL_SECS);
+        if vacuum_due && self.db.purged_since_vacuum > 0 {
+            self.db.last_vacuum = Some(tokio::time::Instant::now());
+            self.db.purged_since_vacuum = 0;
+            if let Err(e) = conn.execute("VACUUM", []) {
+                log::error!("Failed to vacuum DB {:?}", e);
+            }
+        }
     }
 
     async fn process_update_msg(&mut self, msg: GenericChannelMsg) {
@@ -633,12 +1392,16 @@ impl DBWorkerThread {
         };
 
         // Producer of add_sui_event should always set the Suibase uuid in msg.data_string.
+        //
+        // For the "event_subscriptions" of suibase.yaml, the uuid is "subscription:{label}"
+        // and the name is the label (so the events are stored under a Package per label).
         let package_uuid = if let Some(package_uuid) = msg.params(0) {
             package_uuid
         } else {
             log::error!("Missing Suibase package UUID in params(0) {:?}", msg);
             return;
         };
+        let is_subscription = package_uuid.starts_with("subscription:");
 
         let package_name = if let Some(package_name) = msg.params(1) {
             package_name
@@ -701,6 +1464,24 @@ impl DBWorkerThread {
         }
         let package_id = package_id[2..].to_string();
 
+        // Backfill cursor of a managed package is per package id. The events of a
+        // subscription can be from any package, so its cursor is per uuid.
+        let cursor_key = if is_subscription {
+            package_uuid.clone()
+        } else {
+            package_id.clone()
+        };
+
+        // Sui EventID of this event (for the backfill cursor).
+        let event_id = result_json.get("id").and_then(|id| {
+            let tx_digest = id.get("txDigest")?.as_str()?;
+            let event_seq = match id.get("eventSeq")? {
+                serde_json::Value::String(s) => s.clone(),
+                v => v.to_string(),
+            };
+            Some((tx_digest.to_string(), event_seq))
+        });
+
         let timestamp_ms: u64 =
             if let Some(timestamp_ms) = result_json.get("timestampMs").and_then(|v| v.as_str()) {
                 timestamp_ms.parse::<u64>().unwrap_or(0)
@@ -785,7 +1566,7 @@ impl DBWorkerThread {
             None,
             package_uuid,
             package_name,
-            package_id,
+            package_id.clone(),
         );
         let (package, package_instance): (Package, PackageInstance) = match objs {
             Some(boxed_tuple) => *boxed_tuple,
@@ -798,9 +1579,11 @@ impl DBWorkerThread {
 
         // Insert event into proper table (should be already created).
         let name_suffix = format!("{}_{}", sub_table_name, event_level);
+        // The sender of the transaction, unless the ConsoleEvent specifies one.
+        let tx_sender = result_json.get("sender").and_then(|v| v.as_str());
         let event_json = if is_console {
             serde_json::json!({
-                "sender": event_sender,
+                "sender": event_sender.or(tx_sender),
                 "message": event_message,
             })
         } else {
@@ -810,9 +1593,8 @@ impl DBWorkerThread {
             } else {
                 format!("Failed to stringify result_json {:?}", result_json)
             };
-            // TODO Find a way to resolve the sender.
             serde_json::json!({
-                "sender": "unknown",
+                "sender": tx_sender.unwrap_or("unknown"),
                 "message": message,
             })
         };
@@ -823,6 +1605,11 @@ impl DBWorkerThread {
             return;
         }
         let event_json = event_json.unwrap();
+        let waited_event = if !is_console && !self.db.event_waiters.is_empty() {
+            Some(event_json.clone())
+        } else {
+            None
+        };
         // Make sure even_json is safe by escaping all double quotes with double-double quotes (SQLite way).
         let event_json = event_json.replace('\"', "\"\"");
         let mut new_sui_event = SuiEvent::new(package_instance.id, timestamp_ms, event_json);
@@ -830,26 +1617,358 @@ impl DBWorkerThread {
             package_instance.insert_event_in_db(conn, &package, name_suffix, &mut new_sui_event)
         {
             log::error!("Failed to insert SuiEvent in DB {:?}", e);
+            return;
+        }
+
+        if let Some(event_json) = waited_event {
+            let event = ExportedEvent {
+                id: new_sui_event.id as i64,
+                timestamp_ms: timestamp_ms as i64,
+                package_name: package.package_name.clone(),
+                package_id: package_id.clone(),
+                kind: format!("user_{}", event_level),
+                event_json,
+            };
+            self.notify_event_waiters(&event);
         }
 
+        if let Some((tx_digest, event_seq)) = event_id {
+            let cursor = EventCursor {
+                package_id: cursor_key,
+                tx_digest,
+                event_seq,
+                timestamp_ms,
+            };
+            if let Err(e) = cursor.upsert_in_db(conn, &workdir_name) {
+                log::error!("Failed to update event cursor in DB {:?}", e);
+            }
+        }
+
+        // Create the typed view on the first event of a type (layout fetched asynchronously).
+        // Retried on a later event when it fails (see process_create_event_view).
+        if !is_console
+            && !self.db.event_views.contains(type_str)
+            && self.db.event_views_pending.insert(type_str.to_string())
+        {
+            tokio::spawn(run_events_decoder(
+                self.params.globals.clone(),
+                self.params.workdir_idx,
+                type_str.to_string(),
+                self.params.event_tx.clone(),
+            ));
+        }
+
+        // Wake up the webhook_worker. Best effort, it also polls on audit.
+        let mut notif = GenericChannelMsg::new();
+        notif.event_id = basic_types::EVENT_EXEC;
+        notif.command = Some("events_persisted".to_string());
+        notif.workdir_idx = Some(self.params.workdir_idx);
+        let _ = self.params.webhook_worker_tx.try_send(notif);
+
         // TODO Broadcast the sequence number increment of this sui_event object to websocket users.
     }
 
-    async fn open_db(&mut self) -> bool {
-        let workdir_path =
-            common::shared_types::get_workdir_paths(self.params.workdir_idx).workdir_root_path();
+    async fn process_create_event_view(&mut self, msg: GenericChannelMsg) {
+        let event_type = match msg.params(0) {
+            Some(event_type) => event_type,
+            None => {
+                log::error!("Missing event type for create_event_view {:?}", msg);
+                return;
+            }
+        };
+        self.db.event_views_pending.remove(&event_type);
+
+        // No layout when the decoder failed (already logged by the decoder).
+        let layout = match msg
+            .data_json
+            .and_then(|data_json| serde_json::from_value::<MoveStructLayout>(data_json).ok())
+        {
+            Some(layout) => layout,
+            None => return,
+        };
+        let conn = match self.db.conn.as_ref() {
+            Some(conn) => conn,
+            None => return,
+        };
+        let workdir_name = &self.params.workdir_name;
+        let events_table = format!("{}_sui_event_user_0", workdir_name);
+        let result = conn.unchecked_transaction().and_then(|tx| {
+            for sql in layout.create_view_sql(workdir_name, &events_table) {
+                tx.execute(&sql, [])?;
+            }
+            tx.commit()
+        });
+        match result {
+            Ok(()) => {
+                log::info!("Created view {}", layout.view_name(workdir_name));
+                self.db.event_views.insert(event_type);
+            }
+            Err(e) => log::error!("Failed to create view for {:?} {:?}", layout, e),
+        }
+    }
+
+    async fn process_get_event_cursor(&mut self, mut msg: GenericChannelMsg) {
+        // Response is the JSON of the cursor, or an empty string when none.
+        let mut resp = String::new();
+        let workdir_name = common::shared_types::WORKDIRS_KEYS[self.params.workdir_idx as usize];
+        if let (Some(conn), Some(package_id)) = (self.db.conn.as_ref(), msg.params(0)) {
+            match EventCursor::get_from_db(conn, workdir_name, &package_id) {
+                Ok(Some(cursor)) => {
+                    resp = serde_json::json!({
+                        "txDigest": cursor.tx_digest,
+                        "eventSeq": cursor.event_seq,
+                    })
+                    .to_string();
+                }
+                Ok(None) => {}
+                Err(e) => log::error!("Failed to get event cursor from DB {:?}", e),
+            }
+        }
+        if let Some(resp_channel) = msg.resp_channel.take() {
+            let _ = resp_channel.send(resp);
+        }
+    }
+
+    async fn process_add_sui_tx(&mut self, msg: GenericChannelMsg) {
+        // params are (package_uuid, package_name, package_id) and data_json a SuiTxSummary.
+        let (package_uuid, package_name, package_id) =
+            match (msg.params(0), msg.params(1), msg.params(2)) {
+                (Some(uuid), Some(name), Some(id)) if !id.is_empty() => (uuid, name, id),
+                _ => {
+                    log::error!("Invalid add_sui_tx params {:?}", msg);
+                    return;
+                }
+            };
+        let summary = match msg
+            .data_json
+            .and_then(|data_json| serde_json::from_value::<SuiTxSummary>(data_json).ok())
+        {
+            Some(summary) => summary,
+            None => {
+                log::error!("Invalid add_sui_tx data for {}", package_name);
+                return;
+            }
+        };
+        let conn = match self.db.conn.as_ref() {
+            Some(conn) => conn,
+            None => {
+                log::error!("No DB connection to handle Sui tx {}", summary.digest);
+                return;
+            }
+        };
+        let workdir_name = self.params.workdir_name.clone();
+        let objs = Package::get_objs_from_db(
+            conn,
+            workdir_name.clone(),
+            None,
+            package_uuid,
+            package_name,
+            package_id,
+        );
+        let package_instance = match objs {
+            Some(boxed_tuple) => boxed_tuple.1,
+            None => {
+                log::error!("Failed to get Package from DB for tx {}", summary.digest);
+                return;
+            }
+        };
+        let mut sui_tx = SuiTx {
+            id: 0,
+            package_instance_id: package_instance.id,
+            summary,
+        };
+        if let Err(e) = sui_tx.insert_in_db(conn, &workdir_name) {
+            log::error!("Failed to insert SuiTx in DB {:?}", e);
+        }
+    }
+
+    async fn process_get_tx_cursor(&mut self, mut msg: GenericChannelMsg) {
+        // Response is the digest, or an empty string when none.
+        let mut resp = String::new();
+        if let (Some(conn), Some(package_id)) = (self.db.conn.as_ref(), msg.params(0)) {
+            match SuiTx::get_cursor_from_db(conn, &self.params.workdir_name, &package_id) {
+                Ok(Some(digest)) => resp = digest,
+                Ok(None) => {}
+                Err(e) => log::error!("Failed to get tx cursor from DB {:?}", e),
+            }
+        }
+        if let Some(resp_channel) = msg.resp_channel.take() {
+            let _ = resp_channel.send(resp);
+        }
+    }
+
+    async fn process_export_events(&mut self, mut msg: GenericChannelMsg) {
+        // Response is the JSON of an EventsExportResult.
+        //
+        // The export is done by a detached task (on a separate read-only connection) which
+        // also sends the response, so the writing of the new events is not blocked for
+        // the duration of a large export.
+        let resp_channel = msg.resp_channel.take();
+        let params = msg
+            .data_json
+            .take()
+            .and_then(|data_json| serde_json::from_value::<EventsExportParams>(data_json).ok());
+        let db_path = self.db_path();
+        let workdir_name = self.params.workdir_name.clone();
+        let workdir_idx = self.params.workdir_idx;
+        tokio::spawn(async move {
+            let result = match params {
+                Some(params) => tokio::task::spawn_blocking(move || {
+                    let mut result = EventsExportResult::default();
+                    let mut export = || -> anyhow::Result<u64> {
+                        let path = resolve_export_path(workdir_idx, &params.path)?;
+                        result.path = path.to_string_lossy().to_string();
+                        let conn = Connection::open_with_flags(
+                            db_path,
+                            rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY,
+                        )?;
+                        conn.busy_timeout(DB_BUSY_TIMEOUT)?;
+                        let mut writer = EventsExportWriter::create(params.format, &path)?;
+                        SuiEvent::export_from_db(&conn, &workdir_name, &params, &mut writer)?;
+                        writer.finish()
+                    };
+                    match export() {
+                        Ok(events) => result.events = events,
+                        Err(e) => result.error = Some(e.to_string()),
+                    }
+                    result
+                })
+                .await
+                .unwrap_or_else(|e| EventsExportResult {
+                    error: Some(format!("export task failed: {}", e)),
+                    ..Default::default()
+                }),
+                None => EventsExportResult {
+                    error: Some("invalid export_events params".to_string()),
+                    ..Default::default()
+                },
+            };
+            if let Some(error) = &result.error {
+                log::warn!("export_events failed: {}", error);
+            }
+            if let Some(resp_channel) = resp_channel {
+                let _ = resp_channel.send(serde_json::to_string(&result).unwrap_or_default());
+            }
+        });
+    }
+
+    async fn process_wait_for_event(&mut self, mut msg: GenericChannelMsg) {
+        // Response is the JSON of the ExportedEvent or {"error": "..."}.
+        let resp_channel = match msg.resp_channel.take() {
+            Some(resp_channel) => resp_channel,
+            None => return, // Nobody waiting.
+        };
+        let params = msg
+            .data_json
+            .take()
+            .and_then(|data_json| serde_json::from_value::<EventWaitParams>(data_json).ok());
+        let params = match params {
+            Some(params) => params,
+            None => {
+                let resp = serde_json::json!({"error": "invalid wait_for_event params"});
+                let _ = resp_channel.send(resp.to_string());
+                return;
+            }
+        };
+        let conn = match self.db.conn.as_ref() {
+            Some(conn) => conn,
+            None => {
+                let resp = serde_json::json!({"error": "no DB connection"});
+                let _ = resp_channel.send(resp.to_string());
+                return;
+            }
+        };
+        // The events written from now on are matched by notify_event_waiters, the past
+        // ones (up to last_id) are searched by a detached task on a separate read-only
+        // connection, so the writing of the new events is not blocked meanwhile.
+        let sql = format!(
+            "SELECT COALESCE(MAX(id), 0) FROM {}_sui_event_user_0",
+            self.params.workdir_name
+        );
+        let last_id: i64 = match conn.query_row(&sql, [], |row| row.get(0)) {
+            Ok(last_id) => last_id,
+            Err(e) => {
+                let resp = serde_json::json!({"error": e.to_string()});
+                let _ = resp_channel.send(resp.to_string());
+                return;
+            }
+        };
+        let resp_channel = Arc::new(std::sync::Mutex::new(Some(resp_channel)));
+        let after_id = params.after_id;
+        let filter = params.filter.clone();
+        self.db.event_waiters.push(EventWaiter {
+            params,
+            resp_channel: Arc::clone(&resp_channel),
+        });
+        let after_id = match after_id {
+            Some(after_id) if after_id < last_id => after_id,
+            _ => return, // No past events to consider.
+        };
+
+        let db_path = self.db_path();
+        let workdir_name = self.params.workdir_name.clone();
+        tokio::spawn(async move {
+            let found = tokio::task::spawn_blocking(move || {
+                let conn = Connection::open_with_flags(
+                    db_path,
+                    rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY,
+                )?;
+                conn.busy_timeout(DB_BUSY_TIMEOUT)?;
+                SuiEvent::find_in_db(&conn, &workdir_name, &filter, after_id, last_id)
+            })
+            .await
+            .unwrap_or_else(|e| Err(anyhow::anyhow!("wait task failed: {}", e)));
+            match found {
+                Ok(Some(event)) => send_wait_resp(&resp_channel, event.to_json().to_string()),
+                Ok(None) => {} // Keep waiting for a new event.
+                Err(e) => {
+                    let resp = serde_json::json!({"error": e.to_string()});
+                    send_wait_resp(&resp_channel, resp.to_string());
+                }
+            }
+        });
+    }
+
+    fn notify_event_waiters(&mut self, event: &ExportedEvent) {
+        let waiters = std::mem::take(&mut self.db.event_waiters);
+        for waiter in waiters {
+            if waiter.is_done() {
+                continue;
+            }
+            if waited_event_matches(&waiter.params.filter, event) {
+                send_wait_resp(&waiter.resp_channel, event.to_json().to_string());
+            } else {
+                self.db.event_waiters.push(waiter);
+            }
+        }
+    }
 
+    // See telemetry.rs
+    fn db_write_span(&self, command: &str) -> tracing::Span {
+        tracing::info_span!(
+            "db_write",
+            workdir_idx = self.params.workdir_idx,
+            command = command,
+        )
+    }
+
+    fn db_path(&self) -> std::path::PathBuf {
+        common::shared_types::get_workdir_paths(self.params.workdir_idx)
+            .workdir_root_path()
+            .join("indexer")
+            .join("sqlite.db")
+    }
+
+    async fn open_db(&mut self) -> bool {
         // Open a DB connection to the sqlite.db file. Will create it if does not exists.
-        let path = workdir_path.join("indexer");
+        let pathname = self.db_path();
+        let path = pathname.parent().unwrap_or(&pathname).to_path_buf();
         if std::fs::create_dir_all(&path).is_err() {
             log::error!("Failed to create indexer directory: {:?}", path);
             return false;
         }
-        let _pathname = path.join("sqlite.db");
-        //let conn = Connection::open(pathname);
-        // TODO For now, develop the concept in-memory and serve data through JSON-RPC.
-        //      Later, if the "load" gets too high, then move to a file-based DB.
-        let conn = Connection::open_in_memory();
+        // File-based so the event cursors survive a daemon restart (needed for backfill).
+        let conn = Connection::open(pathname);
         if conn.is_err() {
             log::error!("Failed to open sqlite database: {:?}", conn);
             return false;
@@ -861,6 +1980,17 @@ impl DBWorkerThread {
             return false;
         }
 
+        // WAL allows the concurrent readers (export, webhook_worker) while events are
+        // written. The busy timeout covers the remaining short lock contentions.
+        if let Err(e) = conn.query_row("PRAGMA journal_mode = WAL;", [], |_| Ok(())) {
+            log::error!("Failed to enable WAL journal mode {:?}", e);
+            return false;
+        }
+        if let Err(e) = conn.busy_timeout(DB_BUSY_TIMEOUT) {
+            log::error!("Failed to set busy timeout {:?}", e);
+            return false;
+        }
+
         // Create some tables in the schema to simplify access from this code later.
         // This is a single row table with frequently used globals.
         if let Err(e) = DBSuibaseConfig::create_table(&conn, "all".to_string(), None, None) {
@@ -924,6 +2054,26 @@ impl DBWorkerThread {
                 log::error!("Failed to create {} user event table {:?}", workdir_name, e);
                 return false;
             }
+
+            if let Err(e) = EventCursor::create_table(&conn, workdir_name.to_string(), None, None) {
+                log::error!(
+                    "Failed to create {} event_cursor table {:?}",
+                    workdir_name,
+                    e
+                );
+                return false;
+            }
+
+            if let Err(e) = SuiTx::create_table(&conn, workdir_name.to_string(), None, None) {
+                log::error!("Failed to create {} tx tables {:?}", workdir_name, e);
+                return false;
+            }
+        }
+
+        // Bring an existing DB file to the latest schema.
+        if let Err(e) = migrate_schema(&conn) {
+            log::error!("Failed to migrate DB schema {:?}", e);
+            return false;
         }
 
         // All success. This is a good DB connection.
@@ -973,7 +2123,22 @@ impl DBWorkerThread {
                             basic_types::EVENT_EXEC => {
                                 if let Some(command) = msg.command() {
                                     if command == "add_sui_event" {
-                                        self.process_add_sui_event(msg).await;
+                                        let span = self.db_write_span(&command);
+                                        self.process_add_sui_event(msg).instrument(span).await;
+                                    } else if command == "get_event_cursor" {
+                                        self.process_get_event_cursor(msg).await;
+                                    } else if command == "add_sui_tx" {
+                                        let span = self.db_write_span(&command);
+                                        self.process_add_sui_tx(msg).instrument(span).await;
+                                    } else if command == "get_tx_cursor" {
+                                        self.process_get_tx_cursor(msg).await;
+                                    } else if command == "create_event_view" {
+                                        let span = self.db_write_span(&command);
+                                        self.process_create_event_view(msg).instrument(span).await;
+                                    } else if command == "export_events" {
+                                        self.process_export_events(msg).await;
+                                    } else if command == "wait_for_event" {
+                                        self.process_wait_for_event(msg).await;
                                     } else {
                                         log::error!("Received a EVENT_EXEC message with unexpected command {}", command);
                                     }
@@ -996,3 +2161,237 @@ impl DBWorkerThread {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // In-memory DB with the tables of open_db (at the "0.0.1" schema).
+    fn open_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("PRAGMA foreign_keys = ON;", []).unwrap();
+        DBSuibaseConfig::create_table(&conn, "all".to_string(), None, None).unwrap();
+        for workdir_name in common::shared_types::WORKDIRS_KEYS.iter() {
+            let workdir_name = workdir_name.to_string();
+            Package::create_table(&conn, workdir_name.clone(), None, None).unwrap();
+            PackageInstance::create_table(&conn, workdir_name.clone(), None, None).unwrap();
+            for suffix in event_table_suffixes() {
+                SuiEvent::create_table(&conn, workdir_name.clone(), None, Some(suffix)).unwrap();
+            }
+            EventCursor::create_table(&conn, workdir_name.clone(), None, None).unwrap();
+            SuiTx::create_table(&conn, workdir_name, None, None).unwrap();
+        }
+        conn
+    }
+
+    fn add_instance(conn: &Connection, uuid: &str, package_id: &str) -> (Package, PackageInstance) {
+        let objs = Package::get_objs_from_db(
+            conn,
+            "localnet".to_string(),
+            None,
+            uuid.to_string(),
+            "demo".to_string(),
+            package_id.to_string(),
+        )
+        .unwrap();
+        *objs
+    }
+
+    fn add_events(conn: &Connection, objs: &(Package, PackageInstance), timestamps: &[u64]) {
+        for timestamp_ms in timestamps {
+            let mut event = SuiEvent::new(objs.1.id, *timestamp_ms, "{}".to_string());
+            objs.1
+                .insert_event_in_db(conn, &objs.0, "user_0".to_string(), &mut event)
+                .unwrap();
+        }
+    }
+
+    fn add_cursor(conn: &Connection, package_id: &str) {
+        EventCursor {
+            package_id: package_id.to_string(),
+            tx_digest: "digest".to_string(),
+            event_seq: "0".to_string(),
+            timestamp_ms: 1,
+        }
+        .upsert_in_db(conn, "localnet")
+        .unwrap();
+    }
+
+    fn count(conn: &Connection, sql: &str) -> u64 {
+        conn.query_row(sql, [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn test_migrate_schema() {
+        let conn = open_test_db();
+        DBSuibaseConfig {
+            version: "0.0.1".to_string(),
+        }
+        .upsert_in_db(&conn)
+        .unwrap();
+
+        migrate_schema(&conn).unwrap();
+
+        let config = DBSuibaseConfig::get_from_db(&conn).unwrap().unwrap();
+        assert_eq!(config.version, SCHEMA_VERSION);
+        for workdir_name in common::shared_types::WORKDIRS_KEYS.iter() {
+            for suffix in event_table_suffixes() {
+                let index_name = format!("{}_sui_event_{}_instance_idx", workdir_name, suffix);
+                let n = conn
+                    .query_row(
+                        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'index' AND name = ?1",
+                        [&index_name],
+                        |row| row.get::<_, u64>(0),
+                    )
+                    .unwrap();
+                assert_eq!(n, 1, "missing {}", index_name);
+            }
+        }
+
+        // Already at the latest schema.
+        migrate_schema(&conn).unwrap();
+    }
+
+    #[test]
+    fn test_purge_in_db() {
+        let conn = open_test_db();
+        let table_name = "localnet_sui_event_user_0";
+        let objs_a = add_instance(&conn, "uuid_a", "aa");
+        let objs_b = add_instance(&conn, "uuid_b", "bb");
+        add_events(&conn, &objs_a, &[1, 2, 3, 4, 5, 6, 7, 8]);
+        add_events(&conn, &objs_b, &[1, 2, 3]);
+
+        // By age.
+        assert_eq!(SuiEvent::purge_in_db(&conn, table_name, 3, 0).unwrap(), 4);
+        assert_eq!(
+            count(
+                &conn,
+                "SELECT MIN(timestamp) FROM localnet_sui_event_user_0"
+            ),
+            3
+        );
+
+        // By rows, per package instance (the most recent are kept).
+        assert_eq!(SuiEvent::purge_in_db(&conn, table_name, 0, 2).unwrap(), 4);
+        let sql = format!(
+            "SELECT MIN(timestamp) FROM {} WHERE package_instance_id = {}",
+            table_name, objs_a.1.id
+        );
+        assert_eq!(count(&conn, &sql), 7);
+        let sql = format!(
+            "SELECT COUNT(*) FROM {} WHERE package_instance_id = {}",
+            table_name, objs_b.1.id
+        );
+        assert_eq!(count(&conn, &sql), 1);
+
+        // No limit.
+        assert_eq!(SuiEvent::purge_in_db(&conn, table_name, 0, 0).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_delete_unused_in_db() {
+        let conn = open_test_db();
+        add_instance(&conn, "uuid_a", "a1");
+        let objs_v2 = add_instance(&conn, "uuid_a", "a2");
+        let objs_v3 = add_instance(&conn, "uuid_a", "a3");
+        add_events(&conn, &objs_v2, &[1]);
+        for package_id in ["a1", "a3"] {
+            add_cursor(&conn, package_id);
+        }
+
+        // v1 is deleted (no events), v2 is kept for its events and v3 is the latest.
+        assert_eq!(
+            PackageInstance::delete_unused_in_db(&conn, "localnet").unwrap(),
+            1
+        );
+        let ids: Vec<u64> = conn
+            .prepare("SELECT id FROM localnet_sui_package_instance ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(ids, vec![objs_v2.1.id, objs_v3.1.id]);
+        assert_eq!(
+            count(
+                &conn,
+                "SELECT COUNT(*) FROM localnet_sui_event_cursor WHERE package_id = 'a1'"
+            ),
+            0
+        );
+        assert_eq!(
+            count(
+                &conn,
+                "SELECT COUNT(*) FROM localnet_sui_event_cursor WHERE package_id = 'a3'"
+            ),
+            1
+        );
+    }
+
+    #[test]
+    fn test_delete_removed_in_db() {
+        let conn = open_test_db();
+        let objs_old = add_instance(&conn, "uuid_a", "a1");
+        let objs_new = add_instance(&conn, "uuid_a", "a2");
+        let objs_gone = add_instance(&conn, "uuid_b", "b1");
+        let objs_sub = add_instance(&conn, "subscription:demo", "subscription:demo");
+        for objs in [&objs_old, &objs_new, &objs_gone, &objs_sub] {
+            add_events(&conn, objs, &[1, 2]);
+        }
+        conn.execute(
+            "INSERT INTO localnet_sui_tx (package_instance_id, digest, timestamp, sender, status, gas_used)
+            VALUES (?1, 'digest', 1, '0x1', 'success', 0)",
+            [objs_old.1.id],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO localnet_sui_object_change (tx_id, change_type, object_id)
+            VALUES (1, 'created', '0x2')",
+            [],
+        )
+        .unwrap();
+        for package_id in ["a1", "b1", "subscription:demo"] {
+            add_cursor(&conn, package_id);
+        }
+
+        // Only a2 is still in published-data.
+        let published_ids: HashSet<String> = ["a2".to_string()].into_iter().collect();
+        assert_eq!(
+            PackageInstance::delete_removed_in_db(&conn, "localnet", &published_ids).unwrap(),
+            2
+        );
+
+        let package_ids: Vec<String> = conn
+            .prepare("SELECT package_id FROM localnet_sui_package_instance ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(package_ids, vec!["a2", "subscription:demo"]);
+        assert_eq!(
+            count(&conn, "SELECT COUNT(*) FROM localnet_sui_event_user_0"),
+            4
+        );
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM localnet_sui_tx"), 0);
+        assert_eq!(
+            count(&conn, "SELECT COUNT(*) FROM localnet_sui_object_change"),
+            0
+        );
+        let cursors: Vec<String> = conn
+            .prepare("SELECT package_id FROM localnet_sui_event_cursor")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(cursors, vec!["subscription:demo"]);
+        // The package of the deleted latest instance no longer points to it.
+        let sql = format!(
+            "SELECT COUNT(*) FROM localnet_sui_package
+            WHERE id = {} AND latest_instance_id IS NULL",
+            objs_gone.0.id
+        );
+        assert_eq!(count(&conn, &sql), 1);
+    }
+}
@@ -0,0 +1,189 @@
+// Short-lived task started by the events_writer_worker.
+//
+// Responsible to:
+//   - Recover the Sui events missed while not subscribed (websocket reconnect, daemon restart).
+//     Done for the published packages and for the "event_subscriptions" of suibase.yaml.
+//   - Query with a paginated "suix_queryEvents", starting after the last event persisted
+//     in the DB (the cursor), through the local proxy.
+//   - Without a cursor (package never stored, new DB), only the most recent events are
+//     fetched (events_initial_backfill in suibase.yaml), not the whole history.
+//   - Forward every event to the events_writer_worker as an "add_sui_event" (so the
+//     same dedup path is used as for the websocket events).
+//
+// The events are forwarded in the same JSON structure as a websocket notification
+// ({"params":{"result":{...event...}}}).
+use crate::shared_types::Globals;
+
+use anyhow::{anyhow, Result};
+use common::basic_types::{self, GenericChannelMsg, GenericTx, WorkdirIdx};
+use serde::{Deserialize, Serialize};
+
+use super::proxy_rpc::{get_proxy_uri, proxy_client, rpc_call};
+
+const BACKFILL_PAGE_LIMIT: u64 = 50;
+// Protection against an unexpectedly large gap (50*200 = 10000 events).
+const BACKFILL_MAX_PAGES: usize = 200;
+
+// Link alias used in "add_sui_event" for events coming from a backfill.
+pub const BACKFILL_SOURCE: &str = "backfill";
+
+// Same format as the Sui EventID.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventCursor {
+    pub tx_digest: String,
+    pub event_seq: String,
+}
+
+#[derive(Clone)]
+pub struct EventsBackfillParams {
+    pub globals: Globals,
+    pub workdir_idx: WorkdirIdx,
+    pub package_uuid: String,
+    pub package_name: String,
+    pub event_filter: serde_json::Value, // Sui EventFilter (e.g. {"Package":"0x..."})
+    pub cursor: Option<EventCursor>,
+    pub events_writer_tx: GenericTx,
+}
+
+struct EventsPage {
+    events: Vec<serde_json::Value>,
+    next_cursor: Option<EventCursor>,
+    has_next_page: bool,
+}
+
+async fn query_events_page(
+    client: &reqwest::Client,
+    uri: &str,
+    event_filter: &serde_json::Value,
+    cursor: &Option<EventCursor>,
+    limit: u64,
+    descending: bool,
+) -> Result<EventsPage> {
+    let result = rpc_call(
+        client,
+        uri,
+        "suix_queryEvents",
+        serde_json::json!([event_filter, cursor, limit, descending]),
+    )
+    .await?;
+
+    Ok(EventsPage {
+        events: result
+            .get("data")
+            .and_then(|v| v.as_array())
+            .cloned()
+            .unwrap_or_default(),
+        next_cursor: result
+            .get("nextCursor")
+            .and_then(|v| serde_json::from_value::<EventCursor>(v.clone()).ok()),
+        has_next_page: result
+            .get("hasNextPage")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false),
+    })
+}
+
+async fn forward_event(params: &EventsBackfillParams, event: serde_json::Value) -> Result<()> {
+    let msg = GenericChannelMsg {
+        event_id: basic_types::EVENT_EXEC,
+        command: Some("add_sui_event".to_string()),
+        params: vec![
+            params.package_uuid.clone(),
+            params.package_name.clone(),
+            BACKFILL_SOURCE.to_string(),
+        ],
+        data_json: Some(serde_json::json!({ "params": { "result": event } })),
+        workdir_idx: Some(params.workdir_idx),
+        resp_channel: None,
+    };
+    params
+        .events_writer_tx
+        .send(msg)
+        .await
+        .map_err(|e| anyhow!("add_sui_event send failed {}", e))
+}
+
+// Most recent events of a package without cursor, forwarded oldest first.
+async fn run_initial_backfill(
+    params: &EventsBackfillParams,
+    client: &reqwest::Client,
+    uri: &str,
+) -> Result<usize> {
+    let max_events = {
+        let globals_read_guard = params.globals.get_config(params.workdir_idx).read().await;
+        let config = &*globals_read_guard;
+        config.user_config.events_initial_backfill() as usize
+    };
+
+    let mut events: Vec<serde_json::Value> = Vec::new();
+    let mut cursor: Option<EventCursor> = None;
+    for _ in 0..BACKFILL_MAX_PAGES {
+        let remaining = max_events.saturating_sub(events.len());
+        if remaining == 0 {
+            break;
+        }
+        let limit = BACKFILL_PAGE_LIMIT.min(remaining as u64);
+        let page =
+            query_events_page(client, uri, &params.event_filter, &cursor, limit, true).await?;
+        events.extend(page.events);
+        match page.next_cursor {
+            Some(next_cursor) if page.has_next_page && Some(&next_cursor) != cursor.as_ref() => {
+                cursor = Some(next_cursor);
+            }
+            _ => break,
+        }
+    }
+    events.truncate(max_events);
+
+    let n_events = events.len();
+    for event in events.into_iter().rev() {
+        forward_event(params, event).await?;
+    }
+    Ok(n_events)
+}
+
+// Returns the number of events forwarded.
+pub async fn run_events_backfill(params: EventsBackfillParams) -> Result<usize> {
+    let uri = get_proxy_uri(&params.globals, params.workdir_idx).await?;
+
+    let client = proxy_client()?;
+
+    if params.cursor.is_none() {
+        return run_initial_backfill(&params, &client, &uri).await;
+    }
+
+    let mut cursor = params.cursor.clone();
+    let mut n_events = 0;
+    for _ in 0..BACKFILL_MAX_PAGES {
+        // Ascending (oldest first).
+        let page = query_events_page(
+            &client,
+            &uri,
+            &params.event_filter,
+            &cursor,
+            BACKFILL_PAGE_LIMIT,
+            false,
+        )
+        .await?;
+
+        for event in page.events {
+            forward_event(&params, event).await?;
+            n_events += 1;
+        }
+
+        match page.next_cursor {
+            Some(next_cursor) if page.has_next_page && Some(&next_cursor) != cursor.as_ref() => {
+                cursor = Some(next_cursor);
+            }
+            _ => return Ok(n_events),
+        }
+    }
+
+    log::warn!(
+        "backfill of package {} stopped after {} events",
+        params.package_name,
+        n_events
+    );
+    Ok(n_events)
+}
@@ -0,0 +1,65 @@
+// Short-lived task started by the db_worker.
+//
+// Responsible to:
+//   - Get the normalized layout of the Move struct of an event type with
+//     "sui_getNormalizedMoveStruct", through the local proxy.
+//   - Send the layout back to the db_worker as a "create_event_view", which
+//     (re)creates the typed view (see shared_types/event_views.rs). Also sent
+//     without layout on failure, so the db_worker can retry later.
+use crate::shared_types::{parse_event_type, Globals, MoveStructLayout};
+
+use super::proxy_rpc::{get_proxy_uri, proxy_client, rpc_call};
+
+use anyhow::{anyhow, Result};
+use common::basic_types::{self, GenericChannelMsg, GenericTx, WorkdirIdx};
+
+pub async fn fetch_move_struct_layout(
+    globals: &Globals,
+    workdir_idx: WorkdirIdx,
+    event_type: &str,
+) -> Result<MoveStructLayout> {
+    let (package_id, module, name) =
+        parse_event_type(event_type).ok_or_else(|| anyhow!("invalid event type {}", event_type))?;
+
+    let uri = get_proxy_uri(globals, workdir_idx).await?;
+    let result = rpc_call(
+        &proxy_client()?,
+        &uri,
+        "sui_getNormalizedMoveStruct",
+        serde_json::json!([package_id, module, name]),
+    )
+    .await?;
+
+    MoveStructLayout::from_normalized_struct(&package_id, &module, &name, &result)
+        .ok_or_else(|| anyhow!("unexpected normalized struct {}", result))
+}
+
+pub async fn run_events_decoder(
+    globals: Globals,
+    workdir_idx: WorkdirIdx,
+    event_type: String,
+    db_worker_tx: GenericTx,
+) {
+    let data_json = match fetch_move_struct_layout(&globals, workdir_idx, &event_type).await {
+        Ok(layout) => match serde_json::to_value(&layout) {
+            Ok(data_json) => Some(data_json),
+            Err(e) => {
+                log::error!("Failed to serialize {:?}: {}", layout, e);
+                None
+            }
+        },
+        Err(e) => {
+            log::warn!("no typed view for {}: {}", event_type, e);
+            None
+        }
+    };
+    let msg = GenericChannelMsg {
+        event_id: basic_types::EVENT_EXEC,
+        command: Some("create_event_view".to_string()),
+        params: vec![event_type],
+        data_json,
+        workdir_idx: Some(workdir_idx),
+        resp_channel: None,
+    };
+    let _ = db_worker_tx.send(msg).await;
+}
@@ -0,0 +1,320 @@
+// Export of the stored Sui events to a file (JSONL, CSV or Parquet).
+//
+// The events are read by the db_worker (one row at the time) and written here, so
+// a large history is never loaded in memory. For Parquet, only one row group is
+// buffered at the time.
+//
+// Requested with the "exportEvents" API or the "suibase-daemon export-events" command.
+//
+// The files are always created in workdirs/<workdir>/exports/ and never overwrite an
+// existing file (the API may be reachable remotely, see proxy_bind_address).
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use common::{basic_types::WorkdirIdx, shared_types::get_workdir_paths};
+use parquet::{
+    data_type::{ByteArray, ByteArrayType, Int64Type},
+    file::{properties::WriterProperties, writer::SerializedFileWriter},
+    schema::parser::parse_message_type,
+};
+use serde::{Deserialize, Serialize};
+
+const EXPORTS_DIR: &str = "exports";
+
+const PARQUET_ROW_GROUP_SIZE: usize = 8192;
+
+const PARQUET_SCHEMA: &str = "
+message event {
+    REQUIRED INT64 id;
+    REQUIRED INT64 timestamp_ms;
+    REQUIRED BYTE_ARRAY package_name (UTF8);
+    REQUIRED BYTE_ARRAY package_id (UTF8);
+    REQUIRED BYTE_ARRAY kind (UTF8);
+    REQUIRED BYTE_ARRAY event_json (UTF8);
+}";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventsExportFormat {
+    Jsonl,
+    Csv,
+    Parquet,
+}
+
+impl std::str::FromStr for EventsExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "jsonl" => Ok(Self::Jsonl),
+            "csv" => Ok(Self::Csv),
+            "parquet" => Ok(Self::Parquet),
+            _ => Err(anyhow!("format must be jsonl, csv or parquet")),
+        }
+    }
+}
+
+// Parameters of an "export_events" command (JSON in GenericChannelMsg::data_json).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventsExportParams {
+    pub format: EventsExportFormat,
+    pub path: String, // File name, relative to workdirs/<workdir>/exports/.
+    // Filters. All are optional.
+    pub package: Option<String>,    // Package name or package id.
+    pub event_type: Option<String>, // Move type prefix. Console events are excluded when set.
+    pub from_ms: Option<u64>,       // Inclusive.
+    pub to_ms: Option<u64>,         // Exclusive.
+}
+
+// Response of an "export_events" command (JSON string on the resp_channel).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EventsExportResult {
+    pub events: u64,
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportedEvent {
+    pub id: i64,
+    pub timestamp_ms: i64,
+    pub package_name: String,
+    pub package_id: String,
+    pub kind: String, // Name suffix of the event table (e.g. "console_3", "user_0").
+    pub event_json: String,
+}
+
+impl ExportedEvent {
+    // Also the body of the webhook deliveries (see webhook_delivery.rs).
+    pub fn to_json(&self) -> serde_json::Value {
+        // The stored event_json is embedded as JSON (not as a string).
+        let event_json: serde_json::Value = serde_json::from_str(&self.event_json)
+            .unwrap_or_else(|_| serde_json::Value::String(self.event_json.clone()));
+        serde_json::json!({
+            "id": self.id,
+            "timestamp_ms": self.timestamp_ms,
+            "package_name": self.package_name,
+            "package_id": self.package_id,
+            "kind": self.kind,
+            "event": event_json,
+        })
+    }
+}
+
+#[derive(Default)]
+struct ParquetColumns {
+    id: Vec<i64>,
+    timestamp_ms: Vec<i64>,
+    package_name: Vec<ByteArray>,
+    package_id: Vec<ByteArray>,
+    kind: Vec<ByteArray>,
+    event_json: Vec<ByteArray>,
+}
+
+enum ExportOutput {
+    Jsonl(BufWriter<File>),
+    Csv(BufWriter<File>),
+    Parquet(Box<SerializedFileWriter<File>>, ParquetColumns),
+}
+
+pub struct EventsExportWriter {
+    output: ExportOutput,
+    events: u64,
+}
+
+// Validate a file name of an export (relative path without "..").
+fn export_relative_path(file_name: &str) -> Result<PathBuf> {
+    let path = Path::new(file_name);
+    let valid = !file_name.trim().is_empty()
+        && path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+    if !valid {
+        return Err(anyhow!(
+            "invalid export file name {:?} (must be relative, without '..')",
+            file_name
+        ));
+    }
+    Ok(path.to_path_buf())
+}
+
+// Path of an export file in workdirs/<workdir>/exports/.
+pub fn resolve_export_path(workdir_idx: WorkdirIdx, file_name: &str) -> Result<PathBuf> {
+    let relative_path = export_relative_path(file_name)?;
+    Ok(get_workdir_paths(workdir_idx)
+        .workdir_root_path()
+        .join(EXPORTS_DIR)
+        .join(relative_path))
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+impl EventsExportWriter {
+    pub fn create(format: EventsExportFormat, path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Never truncate an existing file.
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+        let output = match format {
+            EventsExportFormat::Jsonl => ExportOutput::Jsonl(BufWriter::new(file)),
+            EventsExportFormat::Csv => {
+                let mut writer = BufWriter::new(file);
+                writeln!(
+                    writer,
+                    "id,timestamp_ms,package_name,package_id,kind,event_json"
+                )?;
+                ExportOutput::Csv(writer)
+            }
+            EventsExportFormat::Parquet => {
+                let schema = Arc::new(parse_message_type(PARQUET_SCHEMA)?);
+                let props = Arc::new(WriterProperties::builder().build());
+                let writer = SerializedFileWriter::new(file, schema, props)?;
+                ExportOutput::Parquet(Box::new(writer), ParquetColumns::default())
+            }
+        };
+        Ok(Self { output, events: 0 })
+    }
+
+    pub fn write(&mut self, event: &ExportedEvent) -> Result<()> {
+        match &mut self.output {
+            ExportOutput::Jsonl(writer) => {
+                writeln!(writer, "{}", event.to_json())?;
+            }
+            ExportOutput::Csv(writer) => {
+                writeln!(
+                    writer,
+                    "{},{},{},{},{},{}",
+                    event.id,
+                    event.timestamp_ms,
+                    csv_field(&event.package_name),
+                    csv_field(&event.package_id),
+                    csv_field(&event.kind),
+                    csv_field(&event.event_json)
+                )?;
+            }
+            ExportOutput::Parquet(writer, columns) => {
+                columns.id.push(event.id);
+                columns.timestamp_ms.push(event.timestamp_ms);
+                columns
+                    .package_name
+                    .push(ByteArray::from(event.package_name.as_str()));
+                columns
+                    .package_id
+                    .push(ByteArray::from(event.package_id.as_str()));
+                columns.kind.push(ByteArray::from(event.kind.as_str()));
+                columns
+                    .event_json
+                    .push(ByteArray::from(event.event_json.as_str()));
+                if columns.id.len() >= PARQUET_ROW_GROUP_SIZE {
+                    Self::write_row_group(writer, columns)?;
+                }
+            }
+        }
+        self.events += 1;
+        Ok(())
+    }
+
+    fn write_row_group(
+        writer: &mut SerializedFileWriter<File>,
+        columns: &mut ParquetColumns,
+    ) -> Result<()> {
+        let columns = std::mem::take(columns);
+        let mut row_group = writer.next_row_group()?;
+        let mut idx = 0;
+        while let Some(mut column) = row_group.next_column()? {
+            match idx {
+                0 => column
+                    .typed::<Int64Type>()
+                    .write_batch(&columns.id, None, None)?,
+                1 => column
+                    .typed::<Int64Type>()
+                    .write_batch(&columns.timestamp_ms, None, None)?,
+                2 => column.typed::<ByteArrayType>().write_batch(
+                    &columns.package_name,
+                    None,
+                    None,
+                )?,
+                3 => {
+                    column
+                        .typed::<ByteArrayType>()
+                        .write_batch(&columns.package_id, None, None)?
+                }
+                4 => column
+                    .typed::<ByteArrayType>()
+                    .write_batch(&columns.kind, None, None)?,
+                _ => {
+                    column
+                        .typed::<ByteArrayType>()
+                        .write_batch(&columns.event_json, None, None)?
+                }
+            };
+            column.close()?;
+            idx += 1;
+        }
+        row_group.close()?;
+        Ok(())
+    }
+
+    // Flush and close the file. Returns the number of events written.
+    pub fn finish(self) -> Result<u64> {
+        match self.output {
+            ExportOutput::Jsonl(mut writer) | ExportOutput::Csv(mut writer) => writer.flush()?,
+            ExportOutput::Parquet(mut writer, mut columns) => {
+                if !columns.id.is_empty() {
+                    Self::write_row_group(&mut writer, &mut columns)?;
+                }
+                writer.close()?;
+            }
+        }
+        Ok(self.events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("counter"), "counter");
+        assert_eq!(
+            csv_field(r#"{"a":1,"b":"x"}"#),
+            r#""{""a"":1,""b"":""x""}""#
+        );
+        assert_eq!(
+            "CSV".parse::<EventsExportFormat>().unwrap(),
+            EventsExportFormat::Csv
+        );
+        assert!("xml".parse::<EventsExportFormat>().is_err());
+    }
+
+    #[test]
+    fn test_export_relative_path() {
+        assert_eq!(
+            export_relative_path("events.jsonl").unwrap(),
+            PathBuf::from("events.jsonl")
+        );
+        assert!(export_relative_path("2024/events.csv").is_ok());
+        assert!(export_relative_path("/etc/passwd").is_err());
+        assert!(export_relative_path("../suibase.yaml").is_err());
+        assert!(export_relative_path("a/../../b").is_err());
+        assert!(export_relative_path("./a").is_err());
+        assert!(export_relative_path("").is_err());
+    }
+}
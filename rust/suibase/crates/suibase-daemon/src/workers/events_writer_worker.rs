@@ -2,15 +2,34 @@
 //
 // The dedup results are written to SQLite or a log file.
 //
-// This thread process the data coming from one (may be more later) events_worker child.
+// This thread process the data coming from multiple websocket_worker children (one
+// per link with a "ws" URL). They all subscribe to the same events, so the same event
+// is typically received more than once. This is intended: an event is not lost when
+// the websocket of a single provider drops.
 //
-// The events_worker is responsible to subscribe/unsubscribe events, filter them
+// The websocket_worker is responsible to subscribe/unsubscribe events, filter them
 // and forward the validated data to this events_writer_worker parent.
+//
+// Dedup is done by (subscription uuid, txDigest, eventSeq) within a bounded window of the most recent events.
+//
+// Gaps (e.g. websocket reconnect or daemon restart) are filled by a backfill task started
+// on every subscription (see events_backfill.rs). Its events also go through the dedup.
+//
+// The transactions calling the published packages are polled on audit (see tx_poller.rs)
+// and written by the db_worker next to the events.
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
 use std::sync::Arc;
 
 use crate::{
-    shared_types::Globals,
-    workers::{DBWorker, DBWorkerParams, WebSocketWorker, WebSocketWorkerParams},
+    shared_types::{Globals, SuiEventsDedup, TargetServer},
+    workers::{
+        default_websocket_url,
+        events_backfill::{run_events_backfill, EventCursor, EventsBackfillParams},
+        tx_poller::{run_tx_poll, TxPollParams},
+        DBWorker, DBWorkerParams, LogWorker, LogWorkerParams, WebSocketWorker,
+        WebSocketWorkerParams, WebhookWorker, WebhookWorkerParams,
+    },
 };
 
 use common::basic_types::{
@@ -18,10 +37,17 @@ use common::basic_types::{
 };
 
 use anyhow::Result;
-use axum::async_trait;
+use axum::{async_trait, http::HeaderMap};
 
 use tokio::sync::{mpsc::Sender, Mutex};
-use tokio_graceful_shutdown::{FutureExt, SubsystemBuilder, SubsystemHandle};
+use tokio_graceful_shutdown::{FutureExt, NestedSubsystem, SubsystemBuilder, SubsystemHandle};
+
+// Number of most recent events remembered for dedup. Must cover the delay between the
+// fastest and slowest websocket delivering the same event.
+const DEDUP_WINDOW_SIZE: usize = 10_000;
+
+// Alias used for the built-in websocket when no link has a "ws" URL.
+const DEFAULT_WS_LINK_ALIAS: &str = "default";
 
 #[derive(Clone)]
 pub struct EventsWriterWorkerParams {
@@ -68,11 +94,30 @@ impl EventsWriterWorker {
     }
 }
 
+struct WebSocketWorkerTracking {
+    ws_url: String,
+    extra_headers: Option<Arc<HeaderMap>>,
+    tx: Sender<GenericChannelMsg>,
+    handle: NestedSubsystem<Box<dyn Error + Send + Sync>>,
+}
+
 struct EventsWriterThread {
     task_name: String,
     params: EventsWriterWorkerParams,
-    ws_workers_channel: Vec<Sender<GenericChannelMsg>>,
+    // Key is the link alias.
+    ws_workers: HashMap<String, WebSocketWorkerTracking>,
     db_worker_channel: Option<Sender<GenericChannelMsg>>,
+    webhook_worker_channel: Option<Sender<GenericChannelMsg>>,
+    log_worker_channel: Option<Sender<GenericChannelMsg>>,
+    dedup: SuiEventsDedup,
+    // Cursor key (package id or subscription uuid) with a backfill task running
+    // (at most one per package or subscription).
+    backfills_running: HashSet<String>,
+    // Published packages with transactions tracking. Key is the package id,
+    // value is (package_uuid, package_name).
+    tx_packages: HashMap<String, (String, String)>,
+    // Package id with a tx_poller task running (at most one per package).
+    tx_polls_running: HashSet<String>,
 }
 
 #[async_trait]
@@ -81,8 +126,14 @@ impl Runnable<EventsWriterWorkerParams> for EventsWriterThread {
         Self {
             task_name,
             params,
-            ws_workers_channel: Vec::new(),
+            ws_workers: HashMap::new(),
             db_worker_channel: None,
+            webhook_worker_channel: None,
+            log_worker_channel: None,
+            dedup: SuiEventsDedup::new(DEDUP_WINDOW_SIZE),
+            backfills_running: HashSet::new(),
+            tx_packages: HashMap::new(),
+            tx_polls_running: HashSet::new(),
         }
     }
 
@@ -93,18 +144,36 @@ impl Runnable<EventsWriterWorkerParams> for EventsWriterThread {
             self.params.workdir_name
         );
 
-        // Start a child websocket_worker thread (in future, more than one might be started).
-        let (worker_tx, worker_rx) = tokio::sync::mpsc::channel(MPSC_Q_SIZE);
-        let ws_worker_params = WebSocketWorkerParams::new(
+        // Start one child websocket_worker thread per link.
+        self.update_ws_workers(&subsys).await;
+
+        // Start a single child webhook_worker thread (notified by the db_worker).
+        let (webhook_worker_tx, webhook_worker_rx) = tokio::sync::mpsc::channel(MPSC_Q_SIZE);
+        let webhook_worker_params = WebhookWorkerParams::new(
+            self.params.globals.clone(),
+            webhook_worker_rx,
+            webhook_worker_tx.clone(),
+            self.params.workdir_idx,
+            self.params.workdir_name.clone(),
+        );
+        let webhook_worker = WebhookWorker::new(webhook_worker_params);
+        subsys.start(SubsystemBuilder::new("webhook-worker", |a| {
+            webhook_worker.run(a)
+        }));
+        self.webhook_worker_channel = Some(webhook_worker_tx.clone());
+
+        // Start a single child log_worker thread (console log files).
+        let (log_worker_tx, log_worker_rx) = tokio::sync::mpsc::channel(MPSC_Q_SIZE);
+        let log_worker_params = LogWorkerParams::new(
             self.params.globals.clone(),
-            worker_rx,
-            worker_tx.clone(),
-            self.params.event_tx.clone(),
+            log_worker_rx,
+            log_worker_tx.clone(),
             self.params.workdir_idx,
+            self.params.workdir_name.clone(),
         );
-        let ws_worker = WebSocketWorker::new(ws_worker_params);
-        subsys.start(SubsystemBuilder::new("ws-worker", |a| ws_worker.run(a)));
-        self.ws_workers_channel.push(worker_tx);
+        let log_worker = LogWorker::new(log_worker_params);
+        subsys.start(SubsystemBuilder::new("log-worker", |a| log_worker.run(a)));
+        self.log_worker_channel = Some(log_worker_tx);
 
         // Start a single child db_worker thread.
         let (db_worker_tx, db_worker_rx) = tokio::sync::mpsc::channel(MPSC_Q_SIZE);
@@ -112,6 +181,7 @@ impl Runnable<EventsWriterWorkerParams> for EventsWriterThread {
             self.params.globals.clone(),
             db_worker_rx,
             db_worker_tx.clone(),
+            webhook_worker_tx,
             self.params.workdir_idx,
             self.params.workdir_name.clone(),
         );
@@ -133,9 +203,93 @@ impl Runnable<EventsWriterWorkerParams> for EventsWriterThread {
 }
 
 impl EventsWriterThread {
+    // Returns (alias, ws_url, extra_headers) of every selectable link with a "ws" URL.
+    async fn get_ws_links(&self) -> Vec<(String, String, Option<Arc<HeaderMap>>)> {
+        let mut ws_links: Vec<(String, String, Option<Arc<HeaderMap>>)> = {
+            let globals_read_guard = self
+                .params
+                .globals
+                .get_config(self.params.workdir_idx)
+                .read()
+                .await;
+            let config = &*globals_read_guard;
+            config
+                .user_config
+                .links()
+                .iter()
+                .filter(|(_, link)| link.selectable)
+                .filter_map(|(alias, link)| {
+                    let ws = link.ws.as_ref()?;
+                    let extra_headers = TargetServer::build_extra_headers(link);
+                    Some((alias.clone(), ws.clone(), extra_headers))
+                })
+                .collect()
+        };
+
+        if ws_links.is_empty() {
+            if let Some(ws_url) = default_websocket_url(self.params.workdir_idx) {
+                ws_links.push((DEFAULT_WS_LINK_ALIAS.to_string(), ws_url.to_string(), None));
+            }
+        }
+        ws_links
+    }
+
+    // Start/stop websocket_worker children to match the links in the config.
+    async fn update_ws_workers(&mut self, subsys: &SubsystemHandle) {
+        let ws_links = self.get_ws_links().await;
+
+        // Stop the workers for removed (or modified) links.
+        self.ws_workers.retain(|alias, worker| {
+            let keep = ws_links.iter().any(|(a, ws_url, extra_headers)| {
+                a == alias && *ws_url == worker.ws_url && *extra_headers == worker.extra_headers
+            });
+            if !keep {
+                log::info!("stopping websocket worker for link {}", alias);
+                worker.handle.initiate_shutdown();
+            }
+            keep
+        });
+
+        // Start the workers for new links.
+        for (alias, ws_url, extra_headers) in ws_links {
+            if self.ws_workers.contains_key(&alias) {
+                continue;
+            }
+            log::info!(
+                "starting websocket worker for link {} ({})",
+                alias,
+                self.params.workdir_name
+            );
+            let (worker_tx, worker_rx) = tokio::sync::mpsc::channel(MPSC_Q_SIZE);
+            let ws_worker_params = WebSocketWorkerParams::new(
+                self.params.globals.clone(),
+                worker_rx,
+                worker_tx.clone(),
+                self.params.event_tx.clone(),
+                self.params.workdir_idx,
+                alias.clone(),
+                ws_url.clone(),
+                extra_headers.clone(),
+            );
+            let ws_worker = WebSocketWorker::new(ws_worker_params);
+            let handle = subsys.start(SubsystemBuilder::new(format!("ws-worker-{}", alias), |a| {
+                ws_worker.run(a)
+            }));
+            self.ws_workers.insert(
+                alias,
+                WebSocketWorkerTracking {
+                    ws_url,
+                    extra_headers,
+                    tx: worker_tx,
+                    handle,
+                },
+            );
+        }
+    }
+
     async fn forward_to_children(&mut self, msg: GenericChannelMsg) {
-        // Forward the message to each self.ws_workers_channel.
-        for tx in &self.ws_workers_channel {
+        // Forward the message to each websocket worker.
+        for tx in self.ws_workers.values().map(|worker| &worker.tx) {
             let forward_msg = GenericChannelMsg {
                 event_id: msg.event_id,
                 command: msg.command.clone(),
@@ -146,6 +300,14 @@ impl EventsWriterThread {
             };
             let _ = tx.send(forward_msg).await;
         }
+        // Forward the message to the single self.webhook_worker_channel.
+        if let Some(tx) = &self.webhook_worker_channel {
+            let _ = tx.send(msg.clone()).await;
+        }
+        // Forward the message to the single self.log_worker_channel.
+        if let Some(tx) = &self.log_worker_channel {
+            let _ = tx.send(msg.clone()).await;
+        }
         // Forward the message to the single self.db_worker_channel.
         self.forward_to_db_worker(msg).await;
     }
@@ -159,26 +321,232 @@ impl EventsWriterThread {
                 params: msg.params,
                 data_json: msg.data_json,
                 workdir_idx: msg.workdir_idx,
-                resp_channel: None,
+                resp_channel: msg.resp_channel,
             };
             let _ = tx.send(forward_msg).await;
         }
     }
 
-    async fn process_audit_msg(&mut self, msg: GenericChannelMsg) {
+    async fn process_audit_msg(&mut self, msg: GenericChannelMsg, subsys: &SubsystemHandle) {
+        // Periodic opportunity to follow the links config changes.
+        self.update_ws_workers(subsys).await;
+        self.prune_tx_packages().await;
+        self.start_tx_polls();
         self.forward_to_children(msg).await;
     }
 
+    // Stop tracking the transactions of a package no longer the most recent instance
+    // (e.g. republished or removed).
+    async fn prune_tx_packages(&mut self) {
+        if self.tx_packages.is_empty() {
+            return;
+        }
+        let globals_read_guard = self
+            .params
+            .globals
+            .get_packages(self.params.workdir_idx)
+            .read()
+            .await;
+        let globals = &*globals_read_guard;
+        let Some(ui) = &globals.ui else {
+            return;
+        };
+        let most_recent: HashSet<&str> = ui
+            .get_data()
+            .iter_most_recent_package_instance()
+            .map(|latest| latest.get_package_id())
+            .collect();
+        self.tx_packages
+            .retain(|package_id, _| most_recent.contains(package_id.as_str()));
+    }
+
+    fn start_tx_polls(&mut self) {
+        for (package_id, (package_uuid, package_name)) in &self.tx_packages {
+            if !self.tx_polls_running.insert(package_id.clone()) {
+                continue;
+            }
+            let db_worker_tx = self.db_worker_channel.clone();
+            let globals = self.params.globals.clone();
+            let events_writer_tx = self.params.event_tx.clone();
+            let workdir_idx = self.params.workdir_idx;
+            let package_uuid = package_uuid.clone();
+            let package_name = package_name.clone();
+            let package_id = package_id.clone();
+            tokio::spawn(async move {
+                // Digest of the last persisted transaction (same ordering guarantee as the
+                // backfill cursor).
+                let mut cursor: Option<String> = None;
+                if let Some(db_worker_tx) = db_worker_tx {
+                    let (tx, rx) = tokio::sync::oneshot::channel();
+                    let mut msg = GenericChannelMsg::new();
+                    msg.event_id = basic_types::EVENT_EXEC;
+                    msg.command = Some("get_tx_cursor".to_string());
+                    msg.params = vec![package_id.clone()];
+                    msg.workdir_idx = Some(workdir_idx);
+                    msg.resp_channel = Some(tx);
+                    if db_worker_tx.send(msg).await.is_ok() {
+                        if let Ok(resp) = rx.await {
+                            cursor = Some(resp).filter(|digest| !digest.is_empty());
+                        }
+                    }
+                }
+
+                let params = TxPollParams {
+                    globals,
+                    workdir_idx,
+                    package_uuid,
+                    package_name: package_name.clone(),
+                    package_id: package_id.clone(),
+                    cursor,
+                    events_writer_tx: events_writer_tx.clone(),
+                };
+                match run_tx_poll(params).await {
+                    Ok(n_txs) => {
+                        if n_txs > 0 {
+                            log::info!("tx poll of {} got {} transactions", package_name, n_txs);
+                        }
+                    }
+                    Err(e) => log::warn!("tx poll of {} failed: {}", package_name, e),
+                }
+
+                // Allow the next poll for this package.
+                let mut msg = GenericChannelMsg::new();
+                msg.event_id = basic_types::EVENT_EXEC;
+                msg.command = Some("tx_poll_done".to_string());
+                msg.params = vec![package_id];
+                msg.workdir_idx = Some(workdir_idx);
+                let _ = events_writer_tx.send(msg).await;
+            });
+        }
+    }
+
     async fn process_update_msg(&mut self, msg: GenericChannelMsg) {
         self.forward_to_children(msg).await;
     }
 
     async fn process_add_sui_event(&mut self, msg: GenericChannelMsg) {
-        // TODO Dedup logic to be done here, for now just forward everything since
-        //      suibase currently support only a single websocket worker per workdir.
+        // Forward only the first copy of an event (from whichever websocket is the fastest).
+        let key = match (msg.params(0), msg.data_json.as_ref()) {
+            (Some(uuid), Some(data_json)) => SuiEventsDedup::key_from_json(&uuid, data_json),
+            _ => None,
+        };
+        if let Some(key) = key {
+            if !self.dedup.insert(key) {
+                return;
+            }
+        } else {
+            // Let the DBWorker do the validation and error logging.
+            log::warn!(
+                "add_sui_event without event id from link {:?}",
+                msg.params(2)
+            );
+        }
+        // The console events are also written to the log files.
+        let is_console = msg
+            .data_json
+            .as_ref()
+            .and_then(|data_json| data_json.pointer("/params/result/type"))
+            .and_then(|v| v.as_str())
+            .is_some_and(|type_str| type_str.ends_with("::ConsoleEvent"));
+        if is_console {
+            if let Some(tx) = &self.log_worker_channel {
+                let _ = tx.send(msg.clone()).await;
+            }
+        }
         self.forward_to_db_worker(msg).await;
     }
 
+    async fn process_backfill_events(&mut self, msg: GenericChannelMsg) {
+        // Requested by a websocket_worker on every successful subscription.
+        //
+        // params(2) is the package id (no leading 0x), or the event filter (JSON) for
+        // the "event_subscriptions" of suibase.yaml.
+        let (package_uuid, package_name, filter_param) =
+            match (msg.params(0), msg.params(1), msg.params(2)) {
+                (Some(uuid), Some(name), Some(filter)) if !filter.is_empty() => {
+                    (uuid, name, filter)
+                }
+                _ => {
+                    log::error!("Invalid backfill_events params {:?}", msg);
+                    return;
+                }
+            };
+
+        // The cursor of a subscription is per uuid (see EventCursor in db_worker.rs).
+        let (cursor_key, event_filter) = if package_uuid.starts_with("subscription:") {
+            match serde_json::from_str::<serde_json::Value>(&filter_param) {
+                Ok(event_filter) => (package_uuid.clone(), event_filter),
+                Err(e) => {
+                    log::error!("Invalid backfill_events filter {}: {}", filter_param, e);
+                    return;
+                }
+            }
+        } else {
+            // Transactions are tracked only for the published packages.
+            self.tx_packages.insert(
+                filter_param.clone(),
+                (package_uuid.clone(), package_name.clone()),
+            );
+            let event_filter = serde_json::json!({ "Package": format!("0x{}", filter_param) });
+            (filter_param, event_filter)
+        };
+
+        // The same package is typically subscribed by every websocket worker.
+        if !self.backfills_running.insert(cursor_key.clone()) {
+            return;
+        }
+
+        let db_worker_tx = self.db_worker_channel.clone();
+        let globals = self.params.globals.clone();
+        let events_writer_tx = self.params.event_tx.clone();
+        let workdir_idx = self.params.workdir_idx;
+        tokio::spawn(async move {
+            // Get the cursor of the last persisted event from the DBWorker. Because the
+            // DBWorker process its queue in order, all events already forwarded are included.
+            let mut cursor: Option<EventCursor> = None;
+            if let Some(db_worker_tx) = db_worker_tx {
+                let (tx, rx) = tokio::sync::oneshot::channel();
+                let mut msg = GenericChannelMsg::new();
+                msg.event_id = basic_types::EVENT_EXEC;
+                msg.command = Some("get_event_cursor".to_string());
+                msg.params = vec![cursor_key.clone()];
+                msg.workdir_idx = Some(workdir_idx);
+                msg.resp_channel = Some(tx);
+                if db_worker_tx.send(msg).await.is_ok() {
+                    if let Ok(resp) = rx.await {
+                        cursor = serde_json::from_str::<EventCursor>(&resp).ok();
+                    }
+                }
+            }
+
+            let params = EventsBackfillParams {
+                globals,
+                workdir_idx,
+                package_uuid,
+                package_name: package_name.clone(),
+                event_filter,
+                cursor,
+                events_writer_tx: events_writer_tx.clone(),
+            };
+            match run_events_backfill(params).await {
+                Ok(n_events) => {
+                    if n_events > 0 {
+                        log::info!("backfill of {} got {} events", package_name, n_events);
+                    }
+                }
+                Err(e) => log::warn!("backfill of {} failed: {}", package_name, e),
+            }
+
+            // Allow the next backfill for this package.
+            let mut msg = GenericChannelMsg::new();
+            msg.event_id = basic_types::EVENT_EXEC;
+            msg.command = Some("backfill_done".to_string());
+            msg.params = vec![cursor_key];
+            msg.workdir_idx = Some(workdir_idx);
+            let _ = events_writer_tx.send(msg).await;
+        });
+    }
+
     async fn event_loop(&mut self, subsys: &SubsystemHandle) {
         // Take mutable ownership of the event_rx channel as long this thread is running.
         let event_rx = Arc::clone(&self.params.event_rx);
@@ -191,7 +559,7 @@ impl EventsWriterThread {
                 // Process the message.
                 match msg.event_id {
                     basic_types::EVENT_AUDIT => {
-                        self.process_audit_msg(msg).await;
+                        self.process_audit_msg(msg, subsys).await;
                     }
                     basic_types::EVENT_UPDATE => {
                         self.process_update_msg(msg).await;
@@ -200,6 +568,22 @@ impl EventsWriterThread {
                         if let Some(command) = msg.command() {
                             if command == "add_sui_event" {
                                 self.process_add_sui_event(msg).await;
+                            } else if command == "backfill_events" {
+                                self.process_backfill_events(msg).await;
+                            } else if command == "add_sui_tx"
+                                || command == "export_events"
+                                || command == "wait_for_event"
+                            {
+                                // Done by the db_worker, which responds directly.
+                                self.forward_to_db_worker(msg).await;
+                            } else if command == "backfill_done" {
+                                if let Some(cursor_key) = msg.params(0) {
+                                    self.backfills_running.remove(&cursor_key);
+                                }
+                            } else if command == "tx_poll_done" {
+                                if let Some(package_id) = msg.params(0) {
+                                    self.tx_polls_running.remove(&package_id);
+                                }
                             } else {
                                 log::error!(
                                     "Received a EVENT_EXEC message with unexpected command {}",
@@ -0,0 +1,481 @@
+// Child task of admin_controller
+//
+// One instance for localnet and one for devnet.
+//
+// Responsible to:
+//  - Periodically top-up the "faucet_addresses" (suibase.yaml) to faucet_target_mist.
+//
+// The fundings, also done with the "requestFunds" API (see request_funds), are from:
+//  - the "faucet_funder_address" when configured. A "sui client pay-sui" with one of
+//    its coins (so its key must be in the keystore of the workdir).
+//  - otherwise the faucet (the local sui-faucet process for localnet). A faucet request
+//    transfers a fixed amount, so more than one may be done for a given amount.
+//
+// The rate limits are in the globals (see faucet.rs).
+//
+// The task is auto-restart in case of panic.
+
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use axum::async_trait;
+use common::{
+    basic_types::{AdminControllerTx, Instantiable, WorkdirContext, WorkdirIdx},
+    shared_types::{WORKDIRS_KEYS, WORKDIR_IDX_DEVNET, WORKDIR_IDX_LOCALNET},
+    workers::{PollerWorker, PollingTrait},
+};
+use tokio_graceful_shutdown::SubsystemHandle;
+
+use crate::{
+    admin_controller::AdminController,
+    shared_types::Globals,
+    workers::proxy_rpc::{get_proxy_uri, proxy_client, rpc_call},
+};
+
+const FAUCET_POLL_MIN_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(30);
+
+// Protection against a faucet transferring much less than expected.
+const FAUCET_MAX_REQUESTS_PER_FUNDING: usize = 5;
+
+const FUNDER_GAS_BUDGET: u64 = 10_000_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FundingResult {
+    pub source: String, // "faucet" or the funder address.
+    pub amount: u64,    // MIST transferred.
+    pub tx_digests: Vec<String>,
+}
+
+fn is_valid_address(address: &str) -> bool {
+    let hex = address.trim_start_matches("0x");
+    address.starts_with("0x")
+        && !hex.is_empty()
+        && hex.len() <= 64
+        && hex.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+// Name of the "sui" wrapper of a workdir (e.g. "lsui" for localnet).
+fn sui_wrapper(workdir_idx: WorkdirIdx) -> Option<&'static str> {
+    match workdir_idx {
+        WORKDIR_IDX_LOCALNET => Some("lsui"),
+        WORKDIR_IDX_DEVNET => Some("dsui"),
+        _ => None,
+    }
+}
+
+// From a faucet "/v1/gas" response. Returns the amount and the transaction digests.
+fn parse_faucet_response(resp: &serde_json::Value) -> Result<(u64, Vec<String>)> {
+    if let Some(error) = resp.get("error").filter(|e| !e.is_null()) {
+        return Err(anyhow!("faucet error {}", error));
+    }
+    let transferred = resp
+        .get("transferredGasObjects")
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| anyhow!("unexpected faucet response {}", resp))?;
+    let mut amount = 0;
+    let mut tx_digests: Vec<String> = Vec::new();
+    for object in transferred {
+        amount += object.get("amount").and_then(|v| v.as_u64()).unwrap_or(0);
+        if let Some(digest) = object.get("transferTxDigest").and_then(|v| v.as_str()) {
+            if !tx_digests.iter().any(|d| d == digest) {
+                tx_digests.push(digest.to_string());
+            }
+        }
+    }
+    Ok((amount, tx_digests))
+}
+
+// From the output of a "sui client pay-sui --json". Returns the transaction digest.
+fn parse_pay_sui_output(output: &str) -> Result<String> {
+    let json_start = output
+        .find('{')
+        .ok_or_else(|| anyhow!("pay-sui failed: {}", output.trim()))?;
+    let resp: serde_json::Value = serde_json::from_str(&output[json_start..])
+        .map_err(|e| anyhow!("pay-sui unexpected output ({}): {}", e, output.trim()))?;
+    let status = resp
+        .pointer("/effects/status/status")
+        .and_then(|v| v.as_str());
+    if status != Some("success") {
+        let error = resp
+            .pointer("/effects/status/error")
+            .and_then(|v| v.as_str())
+            .unwrap_or("unknown error");
+        return Err(anyhow!("pay-sui failed: {}", error));
+    }
+    resp.get("digest")
+        .and_then(|v| v.as_str())
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("pay-sui missing digest"))
+}
+
+struct FaucetConfig {
+    faucet_url: Option<String>,
+    funder_address: Option<String>,
+    target_mist: u64,
+    min_interval: Duration,
+    max_per_hour: u32,
+}
+
+async fn get_faucet_config(globals: &Globals, workdir_idx: WorkdirIdx) -> FaucetConfig {
+    let globals_read_guard = globals.get_config(workdir_idx).read().await;
+    let user_config = &globals_read_guard.user_config;
+    let faucet_url = match workdir_idx {
+        WORKDIR_IDX_LOCALNET if user_config.is_sui_faucet_enabled() => Some(format!(
+            "http://{}:{}/v1/gas",
+            user_config.sui_faucet_host_ip(),
+            user_config.sui_faucet_port()
+        )),
+        WORKDIR_IDX_DEVNET => Some("https://faucet.devnet.sui.io/v1/gas".to_string()),
+        _ => None,
+    };
+    FaucetConfig {
+        faucet_url,
+        funder_address: user_config.faucet_funder_address().cloned(),
+        target_mist: user_config.faucet_target_mist(),
+        min_interval: Duration::from_secs(user_config.faucet_min_interval_secs()),
+        max_per_hour: user_config.faucet_max_requests_per_hour(),
+    }
+}
+
+async fn faucet_request(
+    client: &reqwest::Client,
+    faucet_url: &str,
+    address: &str,
+) -> Result<(u64, Vec<String>)> {
+    let request = serde_json::json!({ "FixedAmountRequest": { "recipient": address } });
+    let resp: serde_json::Value = client
+        .post(faucet_url)
+        .header("Content-Type", "application/json")
+        .json(&request)
+        .send()
+        .await?
+        .json()
+        .await?;
+    parse_faucet_response(&resp)
+}
+
+// The result has what was transferred before an error (if any).
+async fn fund_from_faucet(
+    faucet_url: &str,
+    address: &str,
+    amount: Option<u64>,
+) -> (FundingResult, Option<anyhow::Error>) {
+    let mut result = FundingResult {
+        source: "faucet".to_string(),
+        amount: 0,
+        tx_digests: Vec::new(),
+    };
+    let client = match reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .no_proxy()
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => return (result, Some(e.into())),
+    };
+    for _ in 0..FAUCET_MAX_REQUESTS_PER_FUNDING {
+        let (transferred, tx_digests) = match faucet_request(&client, faucet_url, address).await {
+            Ok(transferred) => transferred,
+            Err(e) => return (result, Some(e)),
+        };
+        result.amount += transferred;
+        result.tx_digests.extend(tx_digests);
+        if transferred == 0 || amount.is_none_or(|amount| result.amount >= amount) {
+            break;
+        }
+    }
+    (result, None)
+}
+
+async fn fund_from_funder(
+    globals: &Globals,
+    admctrl_tx: &AdminControllerTx,
+    workdir_idx: WorkdirIdx,
+    funder_address: &str,
+    address: &str,
+    amount: u64,
+) -> Result<FundingResult> {
+    let sui_wrapper =
+        sui_wrapper(workdir_idx).ok_or_else(|| anyhow!("no sui client for this workdir"))?;
+    if !is_valid_address(funder_address) {
+        return Err(anyhow!("invalid faucet_funder_address {}", funder_address));
+    }
+
+    // The largest coin of the funder pays both the amount and the gas.
+    let uri = get_proxy_uri(globals, workdir_idx).await?;
+    let client = proxy_client()?;
+    let coins = rpc_call(
+        &client,
+        &uri,
+        "suix_getCoins",
+        serde_json::json!([funder_address, "0x2::sui::SUI", null, 50]),
+    )
+    .await?;
+    let coin = coins
+        .get("data")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|coin| {
+            let balance = coin.get("balance")?.as_str()?.parse::<u64>().ok()?;
+            Some((balance, coin.get("coinObjectId")?.as_str()?))
+        })
+        .max();
+    let coin_id = match coin {
+        Some((balance, coin_id)) if balance >= amount.saturating_add(FUNDER_GAS_BUDGET) => {
+            coin_id.to_string()
+        }
+        _ => return Err(anyhow!("{} has no coin large enough", funder_address)),
+    };
+    if !is_valid_address(&coin_id) {
+        return Err(anyhow!("unexpected coin id {}", coin_id));
+    }
+
+    let cmd = format!(
+        "{} client pay-sui --input-coins {} --recipients {} --amounts {} --gas-budget {} --json",
+        sui_wrapper, coin_id, address, amount, FUNDER_GAS_BUDGET
+    );
+    let output = AdminController::send_shell_exec(admctrl_tx, workdir_idx, cmd).await?;
+    let tx_digest = parse_pay_sui_output(&output)?;
+    Ok(FundingResult {
+        source: funder_address.to_string(),
+        amount,
+        tx_digests: vec![tx_digest],
+    })
+}
+
+// Fund an address, subject to the rate limits. No amount means one faucet request
+// (or faucet_target_mist from the funder). The funder never pays more than
+// faucet_target_mist per funding.
+pub async fn request_funds(
+    globals: &Globals,
+    admctrl_tx: &AdminControllerTx,
+    workdir_idx: WorkdirIdx,
+    address: &str,
+    amount: Option<u64>,
+) -> Result<FundingResult> {
+    let workdir = WORKDIRS_KEYS[workdir_idx as usize];
+    if sui_wrapper(workdir_idx).is_none() {
+        return Err(anyhow!("faucet not applicable to {}", workdir));
+    }
+    if !is_valid_address(address) {
+        return Err(anyhow!("invalid address {}", address));
+    }
+    let address = address.to_lowercase();
+    let config = get_faucet_config(globals, workdir_idx).await;
+
+    let mut faucet_guard = globals.get_faucet(workdir_idx).lock().await;
+    let faucet = &mut *faucet_guard;
+    let now = std::time::Instant::now();
+    faucet
+        .check_rate_limits(&address, now, config.min_interval, config.max_per_hour)
+        .map_err(|e| anyhow!(e))?;
+
+    let (result, error) = match (&config.funder_address, &config.faucet_url) {
+        (Some(funder_address), _) => {
+            let amount = amount.unwrap_or(config.target_mist);
+            if amount > config.target_mist {
+                return Err(anyhow!(
+                    "amount {} above faucet_target_mist {}",
+                    amount,
+                    config.target_mist
+                ));
+            }
+            let result = fund_from_funder(
+                globals,
+                admctrl_tx,
+                workdir_idx,
+                funder_address,
+                &address,
+                amount,
+            )
+            .await?;
+            (result, None)
+        }
+        (None, Some(faucet_url)) => fund_from_faucet(faucet_url, &address, amount).await,
+        (None, None) => return Err(anyhow!("{} faucet not enabled", workdir)),
+    };
+    // A partial funding (error after some faucet requests) counts for the rate limits.
+    if result.amount == 0 {
+        if let Some(e) = error {
+            return Err(e);
+        }
+    }
+    faucet.record_funding(&address, now);
+    log::info!(
+        "{} funded {} with {} MIST from {}",
+        workdir,
+        address,
+        result.amount,
+        result.source
+    );
+    match error {
+        Some(e) => Err(anyhow!(
+            "{} (after {} MIST transferred, tx {})",
+            e,
+            result.amount,
+            result.tx_digests.join(", ")
+        )),
+        None => Ok(result),
+    }
+}
+
+#[derive(Clone)]
+pub struct FaucetPollerParams {
+    globals: Globals,
+    admctrl_tx: AdminControllerTx,
+    workdir_idx: WorkdirIdx,
+}
+
+impl WorkdirContext for FaucetPollerParams {
+    fn workdir_idx(&self) -> WorkdirIdx {
+        self.workdir_idx
+    }
+}
+
+impl FaucetPollerParams {
+    pub fn new(globals: Globals, admctrl_tx: AdminControllerTx, workdir_idx: WorkdirIdx) -> Self {
+        Self {
+            globals,
+            admctrl_tx,
+            workdir_idx,
+        }
+    }
+}
+
+pub struct FaucetPoller {
+    // "Glue" the specialized PollingTraitObject with its parameters.
+    // The worker does all the background task/events handling.
+    poller: PollerWorker<PollingTraitObject, FaucetPollerParams>,
+}
+
+pub struct PollingTraitObject {
+    params: FaucetPollerParams,
+    last_poll: Option<tokio::time::Instant>,
+}
+
+#[async_trait]
+impl PollingTrait for PollingTraitObject {
+    // This is called by the PollerWorker task.
+    async fn update(&mut self) {
+        if self
+            .last_poll
+            .is_some_and(|last_poll| last_poll.elapsed() < FAUCET_POLL_MIN_INTERVAL)
+        {
+            return;
+        }
+        self.last_poll = Some(tokio::time::Instant::now());
+        self.refill_faucet_addresses().await;
+    }
+}
+
+// This allow the PollerWorker to instantiate the PollingTraitObject.
+impl Instantiable<FaucetPollerParams> for PollingTraitObject {
+    fn new(params: FaucetPollerParams) -> Self {
+        Self {
+            params,
+            last_poll: None,
+        }
+    }
+}
+
+impl FaucetPoller {
+    pub fn new(params: FaucetPollerParams, subsys: &SubsystemHandle) -> Self {
+        let poller =
+            PollerWorker::<PollingTraitObject, FaucetPollerParams>::new(params.clone(), subsys);
+        Self { poller }
+    }
+
+    pub fn get_tx_channel(&self) -> common::basic_types::GenericTx {
+        self.poller.get_tx_channel()
+    }
+}
+
+impl PollingTraitObject {
+    async fn refill_faucet_addresses(&mut self) {
+        let workdir_idx = self.params.workdir_idx;
+        let (addresses, target_mist) = {
+            let globals_read_guard = self.params.globals.get_config(workdir_idx).read().await;
+            let user_config = &globals_read_guard.user_config;
+            (
+                user_config.faucet_addresses().to_vec(),
+                user_config.faucet_target_mist(),
+            )
+        };
+        if addresses.is_empty() || target_mist == 0 {
+            return;
+        }
+
+        // Not an error when the proxy is not running (e.g. workdir stopped).
+        let uri = match get_proxy_uri(&self.params.globals, workdir_idx).await {
+            Ok(uri) => uri,
+            Err(_) => return,
+        };
+        let client = match proxy_client() {
+            Ok(client) => client,
+            Err(_) => return,
+        };
+
+        for address in addresses {
+            let balance = rpc_call(
+                &client,
+                &uri,
+                "suix_getBalance",
+                serde_json::json!([address, "0x2::sui::SUI"]),
+            )
+            .await
+            .ok()
+            .and_then(|result| result.get("totalBalance")?.as_str()?.parse::<u64>().ok());
+            let balance = match balance {
+                Some(balance) => balance,
+                None => continue,
+            };
+            if balance >= target_mist {
+                continue;
+            }
+            let amount = target_mist - balance;
+            if let Err(e) = request_funds(
+                &self.params.globals,
+                &self.params.admctrl_tx,
+                workdir_idx,
+                &address,
+                Some(amount),
+            )
+            .await
+            {
+                log::debug!("auto-refill of {} skipped: {}", address, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_funding_responses() {
+        let faucet_resp = serde_json::json!({
+            "transferredGasObjects": [
+                { "amount": 20000000000u64, "id": "0x5f", "transferTxDigest": "8p5N" },
+                { "amount": 20000000000u64, "id": "0x60", "transferTxDigest": "8p5N" }
+            ],
+            "error": null
+        });
+        assert_eq!(
+            parse_faucet_response(&faucet_resp).unwrap(),
+            (40000000000, vec!["8p5N".to_string()])
+        );
+        assert!(
+            parse_faucet_response(&serde_json::json!({ "error": "Too many requests" })).is_err()
+        );
+
+        let output =
+            "Some warning\n{\"digest\":\"3Xyz\",\"effects\":{\"status\":{\"status\":\"success\"}}}";
+        assert_eq!(parse_pay_sui_output(output).unwrap(), "3Xyz");
+        assert!(parse_pay_sui_output("Error: insufficient gas").is_err());
+
+        assert!(is_valid_address("0xf7ae"));
+        assert!(!is_valid_address("0xf7ae; rm -rf /"));
+    }
+}
@@ -1,18 +1,31 @@
-// Thread for writing and maintaining a log file.
+// Thread for writing and maintaining the console log files of a workdir.
 //
-// The logs are received through a channel.
+// The logs are the console::ConsoleEvent of the packages, received through a channel
+// from the parent events_writer_worker (after dedup).
 //
 // Lines are defined as:
-//   [Time] [Level] "text" objects:[ {"type"="package_id::module::type", "address":"0x123~456", "object"={}}, ... ]
+//   [Time] [Level] [Sender] "text" objects:[ {"type"="package_id::module::type", "address":"0x123~456", "object"={}}, ... ]
 //
 // 'text' can refer to an object with the label "$object:<address>"
 //
 // The log file will then have a JSON representation of these objects in an appended JSON array.
+// The objects are looked up (at the time of the logging) through the local proxy.
+//
+// One file per package, rotated by size (see shared_types/console_log.rs and the
+// console_log_* variables in suibase.yaml). Lines below console_log_level are dropped.
 //
 // The thread is auto-restart in case of panic.
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use crate::shared_types::Globals;
+use crate::shared_types::{
+    console_log_path, format_log_line, level_from_str, object_labels, Globals,
+};
+
+use super::proxy_rpc::{get_proxy_uri, proxy_client, rpc_call};
 
 use common::basic_types::{
     self, AutoThread, GenericChannelMsg, GenericRx, GenericTx, Runnable, WorkdirIdx,
@@ -23,27 +36,6 @@ use axum::async_trait;
 use tokio::sync::Mutex;
 use tokio_graceful_shutdown::{FutureExt, SubsystemHandle};
 
-// Schema: One entry per Package.
-
-#[derive(Debug)]
-struct SuiEvent {
-    id: u64, // Sequence number within this table. Event assumed inserted in chronological order.
-    package_instance_id: u64, // Foreign key into PackageInstance table.
-    timestamp_ms: u64, // milliseconds. Also in results, but put here for sorting convenience.
-    event_json: String, // This is the content of the "result" field (JSON object).
-}
-
-impl SuiEvent {
-    pub fn new(package_instance_id: u64, timestamp_ms: u64, event_json: String) -> Self {
-        Self {
-            id: 0,
-            package_instance_id,
-            timestamp_ms,
-            event_json,
-        }
-    }
-}
-
 #[derive(Clone)]
 pub struct LogWorkerParams {
     globals: Globals,
@@ -78,7 +70,7 @@ pub struct LogWorker {
 impl LogWorker {
     pub fn new(params: LogWorkerParams) -> Self {
         Self {
-            auto_thread: AutoThread::new("DBWorker".to_string(), params),
+            auto_thread: AutoThread::new("LogWorker".to_string(), params),
         }
     }
 
@@ -87,21 +79,45 @@ impl LogWorker {
     }
 }
 
-#[derive(Debug, Default)]
-struct LogManagement {
-    schema_ok: bool, // Set when all tables have been verified to exist.
+// An opened log file (in append mode).
+#[derive(Debug)]
+struct LogFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
 }
 
-impl LogManagement {
-    pub fn new() -> Self {
-        Self { schema_ok: false }
+impl LogFile {
+    fn open(path: PathBuf) -> std::io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self { path, file, size })
+    }
+
+    // {name}.log -> {name}.log.1 -> ... -> {name}.log.{max_files} (deleted).
+    fn rotate(path: &Path, max_files: u32) -> std::io::Result<()> {
+        let rotated = |n: u32| PathBuf::from(format!("{}.{}", path.display(), n));
+        if max_files == 0 {
+            return std::fs::remove_file(path);
+        }
+        let _ = std::fs::remove_file(rotated(max_files));
+        for n in (1..max_files).rev() {
+            let _ = std::fs::rename(rotated(n), rotated(n + 1));
+        }
+        std::fs::rename(path, rotated(1))
     }
 }
 
 struct LogWorkerThread {
     task_name: String,
     params: LogWorkerParams,
-    log: Vec<LogManagement>,
+    files: HashMap<String, LogFile>, // Key is the package name.
 }
 
 #[async_trait]
@@ -110,7 +126,7 @@ impl Runnable<LogWorkerParams> for LogWorkerThread {
         Self {
             task_name,
             params,
-            log: Vec::new(),
+            files: HashMap::new(),
         }
     }
 
@@ -203,7 +219,7 @@ impl LogWorkerThread {
             return;
         };
 
-        let _package_name = if let Some(package_name) = msg.params(1) {
+        let package_name = if let Some(package_name) = msg.params(1) {
             package_name
         } else {
             log::error!("Missing package name in params(1) {:?}", msg);
@@ -257,12 +273,11 @@ impl LogWorkerThread {
                 return;
             };
 
-        // Verify there is an 0x prefix and remove it from package_id.
+        // Verify there is an 0x prefix.
         if !package_id.starts_with("0x") {
             log::error!("Invalid packageId {:?}", data_json);
             return;
         }
-        let _package_id = package_id[2..].to_string();
 
         let timestamp_ms: u64 =
             if let Some(timestamp_ms) = result_json.get("timestampMs").and_then(|v| v.as_str()) {
@@ -284,91 +299,152 @@ impl LogWorkerThread {
             return;
         };
 
-        let (is_console, sub_table_name) = if type_str.ends_with("::ConsoleEvent") {
-            (true, "console")
-        } else {
-            (false, "user")
-        };
-
-        let (event_level, event_message, event_sender) = if !is_console {
-            (0u8, "", None)
-        } else {
-            //   "parsedJson": Object {"level": Number(3), "message": String("X"), "sender": String("0x...")},
+        // Only the console events are logged.
+        if !type_str.ends_with("::ConsoleEvent") {
+            return;
+        }
 
-            let parsed_json = if let Some(parsed_json) =
-                result_json.get("parsedJson").and_then(|v| v.as_object())
-            {
+        //   "parsedJson": Object {"level": Number(3), "message": String("X"), "sender": String("0x...")},
+        let parsed_json =
+            if let Some(parsed_json) = result_json.get("parsedJson").and_then(|v| v.as_object()) {
                 parsed_json
             } else {
                 log::error!("Missing parsedJson {:?}", data_json);
                 return;
             };
 
-            let event_level =
-                if let Some(event_level) = parsed_json.get("level").and_then(|v| v.as_u64()) {
-                    if event_level > basic_types::EVENT_LEVEL_MAX as u64 {
-                        log::error!("Invalid above MAX parsedJson.level {:?}", data_json);
-                        return;
-                    }
-                    if event_level < basic_types::EVENT_LEVEL_MIN as u64 {
-                        log::error!("Invalid below MIN parsedJson.level  {:?}", data_json);
-                        return;
-                    }
-                    event_level as u8
-                } else {
-                    log::error!("Missing parsedJson.level {:?}", data_json);
+        let event_level =
+            if let Some(event_level) = parsed_json.get("level").and_then(|v| v.as_u64()) {
+                if event_level > basic_types::EVENT_LEVEL_MAX as u64 {
+                    log::error!("Invalid above MAX parsedJson.level {:?}", data_json);
                     return;
-                };
-            let event_message = parsed_json.get("message").and_then(|v| v.as_str());
-            if event_message.is_none() {
-                log::error!("Missing parsedJson.message {:?}", data_json);
+                }
+                if event_level < basic_types::EVENT_LEVEL_MIN as u64 {
+                    log::error!("Invalid below MIN parsedJson.level  {:?}", data_json);
+                    return;
+                }
+                event_level as u8
+            } else {
+                log::error!("Missing parsedJson.level {:?}", data_json);
                 return;
             };
-
-            let event_message = event_message.unwrap();
-            let event_sender = parsed_json.get("sender").and_then(|v| v.as_str());
-            // log::info!("parsed_json {:?}", parsed_json);
-
-            (event_level, event_message, event_sender)
-        };
-
-        // Append event into log (should be already created).
-        let _name_suffix = format!("{}_{}", sub_table_name, event_level);
-        let event_json = if is_console {
-            serde_json::json!({
-                "sender": event_sender,
-                "message": event_message,
-            })
-        } else {
-            let res = serde_json::to_string(&result_json);
-            let message = if let Ok(res) = res {
-                res
+        let event_message =
+            if let Some(event_message) = parsed_json.get("message").and_then(|v| v.as_str()) {
+                event_message
             } else {
-                format!("Failed to stringify result_json {:?}", result_json)
+                log::error!("Missing parsedJson.message {:?}", data_json);
+                return;
             };
-            // TODO Find a way to resolve the sender.
-            serde_json::json!({
-                "sender": "unknown",
-                "message": message,
-            })
+
+        // The sender is optional in the ConsoleEvent. Default to the sender of the transaction.
+        let event_sender = parsed_json
+            .get("sender")
+            .or_else(|| result_json.get("sender"))
+            .and_then(|v| v.as_str())
+            .unwrap_or("unknown");
+
+        let (max_level, max_size_mb, max_files) = {
+            let globals_read_guard = self
+                .params
+                .globals
+                .get_config(self.params.workdir_idx)
+                .read()
+                .await;
+            let config = &*globals_read_guard;
+            (
+                level_from_str(config.user_config.console_log_level()),
+                config.user_config.console_log_max_size_mb(),
+                config.user_config.console_log_max_files(),
+            )
         };
-        // Stringify event_json and insert it in DB.
-        let event_json = serde_json::to_string(&event_json);
-        if event_json.is_err() {
-            log::error!("Failed to stringify event_json {:?}", event_json);
+        // Higher level number is more verbose (ERROR=1 ... TRACE=5).
+        if event_level > max_level.unwrap_or(basic_types::EVENT_LEVEL_MAX) {
             return;
         }
-        let event_json = event_json.unwrap();
-        // Make sure even_json is safe by escaping all double quotes with double-double quotes (SQLite way).
-        let _event_json = event_json.replace('\"', "\"\"");
-        // let mut new_sui_event = SuiEvent::new(package_instance.id, timestamp_ms, event_json);
-        /*if let Err(e) =
-            package_instance.insert_event_in_db(conn, &package, name_suffix, &mut new_sui_event)
-        {
-            log::error!("Failed to insert SuiEvent in DB {:?}", e);
-        }*/
 
-        // TODO Broadcast the sequence number increment of this sui_event object to websocket users.
+        let mut objects = Vec::new();
+        for address in object_labels(event_message) {
+            objects.push(self.lookup_object(&address).await);
+        }
+
+        let line = format_log_line(
+            timestamp_ms,
+            event_level,
+            event_sender,
+            event_message,
+            &objects,
+        );
+        if let Err(e) = self.write_line(&package_name, &line, max_size_mb, max_files) {
+            log::error!("Failed to write console log of {}: {}", package_name, e);
+            // Re-open on next line.
+            self.files.remove(&package_name);
+        }
+    }
+
+    // JSON representation of an object for the appended "objects" array.
+    async fn lookup_object(&self, address: &str) -> serde_json::Value {
+        match self.get_object(address).await {
+            Ok(data) => serde_json::json!({
+                "type": data.get("type").cloned().unwrap_or(serde_json::Value::Null),
+                "address": address,
+                "object": data
+                    .pointer("/content/fields")
+                    .cloned()
+                    .unwrap_or(serde_json::Value::Null),
+            }),
+            Err(e) => serde_json::json!({
+                "address": address,
+                "error": e.to_string(),
+            }),
+        }
+    }
+
+    async fn get_object(&self, address: &str) -> anyhow::Result<serde_json::Value> {
+        let uri = get_proxy_uri(&self.params.globals, self.params.workdir_idx).await?;
+        let result = rpc_call(
+            &proxy_client()?,
+            &uri,
+            "sui_getObject",
+            serde_json::json!([address, { "showType": true, "showContent": true }]),
+        )
+        .await?;
+        if let Some(err) = result.get("error") {
+            return Err(anyhow::anyhow!("{}", err));
+        }
+        result
+            .get("data")
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("sui_getObject missing data"))
+    }
+
+    fn write_line(
+        &mut self,
+        package_name: &str,
+        line: &str,
+        max_size_mb: u64,
+        max_files: u32,
+    ) -> std::io::Result<()> {
+        let max_size = max_size_mb.saturating_mul(1024 * 1024);
+        let line_size = line.len() as u64 + 1;
+
+        if let Some(log_file) = self.files.get(package_name) {
+            if max_size > 0 && log_file.size > 0 && log_file.size + line_size > max_size {
+                let path = log_file.path.clone();
+                self.files.remove(package_name);
+                LogFile::rotate(&path, max_files)?;
+            }
+        }
+        let log_file = match self.files.entry(package_name.to_string()) {
+            std::collections::hash_map::Entry::Occupied(entry) => entry.into_mut(),
+            std::collections::hash_map::Entry::Vacant(entry) => entry.insert(LogFile::open(
+                console_log_path(self.params.workdir_idx, package_name),
+            )?),
+        };
+        // Flushed on every line, so the file can be tailed.
+        writeln!(log_file.file, "{}", line)?;
+        log_file.file.flush()?;
+        log_file.size += line_size;
+        Ok(())
     }
 
     async fn event_loop(&mut self, subsys: &SubsystemHandle) {
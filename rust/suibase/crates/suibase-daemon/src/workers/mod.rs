@@ -12,19 +12,35 @@
 //   - Shell command on different workdir can be executed concurrently.
 //
 // flatten everything under "workers" module.
+pub(crate) use self::balance_poller::*;
 pub(crate) use self::cli_poller::*;
 //pub(crate) use self::db_worker::*;
 //pub(crate) use self::events_writer_worker::*;
+//pub(crate) use self::webhook_worker::*;
+pub(crate) use self::events_export::*;
+pub(crate) use self::faucet_poller::*;
+pub(crate) use self::log_worker::*;
 pub(crate) use self::packages_poller::*;
 pub(crate) use self::request_worker::*;
+pub(crate) use self::supervisor_poller::*;
 pub(crate) use self::webserver::*;
 //pub(crate) use self::websocket_worker::*;
 
+mod balance_poller;
 mod cli_poller;
 //mod db_worker;
 //mod events_writer_worker;
+mod events_backfill;
+mod events_decoder;
+mod events_export;
+mod faucet_poller;
 mod log_worker;
 mod packages_poller;
+mod proxy_rpc;
 mod request_worker;
+mod supervisor_poller;
+mod tx_poller;
+mod webhook_delivery;
+//mod webhook_worker;
 mod webserver;
 mod websocket_worker;
@@ -5,6 +5,12 @@
 // Responsible to:
 //  - Periodically and on-demand check published packages
 //    under ~/suibase/workdirs and update globals.
+//  - Detect the upgrades ("sui client upgrade") and link them to the prior
+//    publications of the same package (through their UpgradeCap).
+//  - Analyze the publish-output.json (gas, dependencies) and the build (module
+//    sizes and digests) of every publication.
+//  - Apply the retention of the package instances (packages_keep_last and
+//    packages_prune in suibase.yaml). The tagged instances are always kept.
 //
 // The task is auto-restart in case of panic.
 
@@ -15,12 +21,18 @@ use common::{
     shared_types::WORKDIRS_KEYS,
     workers::{PollerWorker, PollingTrait},
 };
-use std::collections::HashSet;
-use std::path::PathBuf;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
 
 use crate::{
-    api::{PackageInstance, SuiObjectInstance, SuiObjectType},
-    shared_types::{Globals, PackagePath},
+    api::{
+        ModuleSize, PackageInstance, PublishDependency, PublishGas, PublishReport,
+        SuiObjectInstance, SuiObjectType,
+    },
+    shared_types::{expired_package_paths, read_package_tags, Globals, PackagePath},
+    workers::proxy_rpc::{get_proxy_uri, proxy_client, rpc_call},
+    workers::tx_poller::as_u64,
 };
 
 use anyhow::Result;
@@ -94,6 +106,162 @@ impl PackagesPoller {
     }
 }
 
+const UPGRADE_CAP_TYPE: &str = "0x2::package::UpgradeCap";
+
+// Upgrade related info of a publication.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct PublishUpgradeInfo {
+    upgrade_cap: Option<String>,
+    version: Option<u64>,
+    sender: Option<String>,
+}
+
+// From the publish-output.json (the "sui client publish/upgrade --json" output).
+//
+// An original publication creates the UpgradeCap, an upgrade mutates it.
+fn parse_publish_upgrade_info(publish_output: &serde_json::Value) -> PublishUpgradeInfo {
+    let mut info = PublishUpgradeInfo {
+        sender: publish_output
+            .pointer("/transaction/data/sender")
+            .and_then(|v| v.as_str())
+            .map(str::to_string),
+        ..Default::default()
+    };
+    let changes = publish_output
+        .get("objectChanges")
+        .and_then(|v| v.as_array())
+        .map(|v| v.as_slice())
+        .unwrap_or_default();
+    for change in changes {
+        let change_type = change.get("type").and_then(|v| v.as_str());
+        let version = change
+            .get("version")
+            .and_then(|v| v.as_str())
+            .and_then(|v| v.parse::<u64>().ok());
+        match change_type {
+            Some("published") => info.version = version,
+            Some("created") | Some("mutated") => {
+                if change.get("objectType").and_then(|v| v.as_str()) == Some(UPGRADE_CAP_TYPE) {
+                    info.upgrade_cap = change
+                        .get("objectId")
+                        .and_then(|v| v.as_str())
+                        .map(str::to_string);
+                }
+            }
+            _ => {}
+        }
+    }
+    info
+}
+
+fn framework_package_name(package_id: &str) -> Option<&'static str> {
+    let hex = package_id.trim_start_matches("0x").trim_start_matches('0');
+    match hex {
+        "1" => Some("MoveStdlib"),
+        "2" => Some("Sui"),
+        "3" => Some("SuiSystem"),
+        "dee9" => Some("DeepBook"),
+        _ => None,
+    }
+}
+
+// Transaction digest, gas and dependencies from the publish-output.json. The modules
+// are not in the output (see read_bytecode_modules).
+fn parse_publish_report(publish_output: &serde_json::Value) -> PublishReport {
+    let gas = publish_output.pointer("/effects/gasUsed").map(|gas_used| {
+        let field = |name: &str| as_u64(gas_used.get(name)).unwrap_or(0);
+        PublishGas {
+            computation_cost: field("computationCost"),
+            storage_cost: field("storageCost"),
+            storage_rebate: field("storageRebate"),
+            non_refundable_storage_fee: field("nonRefundableStorageFee"),
+            total: field("computationCost") as i64 + field("storageCost") as i64
+                - field("storageRebate") as i64,
+        }
+    });
+
+    // The Publish command is {"Publish": [<dependencies>]}, the Upgrade command is
+    // {"Upgrade": [[<dependencies>], <current package id>, <ticket>]}.
+    let commands = publish_output
+        .pointer("/transaction/data/transaction/transactions")
+        .and_then(|v| v.as_array())
+        .map(|v| v.as_slice())
+        .unwrap_or_default();
+    let dependency_ids = commands.iter().find_map(|command| {
+        if let Some(publish) = command.get("Publish") {
+            return publish.as_array();
+        }
+        command.get("Upgrade")?.get(0)?.as_array()
+    });
+    let dependencies = dependency_ids
+        .map(|ids| {
+            ids.iter()
+                .filter_map(|id| id.as_str())
+                .map(|id| PublishDependency {
+                    package_id: id.to_string(),
+                    name: framework_package_name(id).map(str::to_string),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    PublishReport {
+        tx_digest: publish_output
+            .get("digest")
+            .and_then(|v| v.as_str())
+            .map(str::to_string),
+        gas,
+        dependencies,
+        ..Default::default()
+    }
+}
+
+// Name of an UpgradePolicy (see sui::package).
+fn upgrade_policy_name(policy: u64) -> &'static str {
+    match policy {
+        0 => "compatible",
+        128 => "additive",
+        192 => "dep_only",
+        _ => "unknown",
+    }
+}
+
+// Digest of a package from the digests of its modules (independent of the directory
+// listing order).
+fn package_digest(module_digests: &BTreeMap<String, String>) -> String {
+    let mut hasher = Sha256::new();
+    for (module, digest) in module_digests {
+        hasher.update(format!("{}:{}\n", module, digest));
+    }
+    data_encoding::HEXLOWER.encode(&hasher.finalize())
+}
+
+// Every build/{package_name}/bytecode_modules/*.mv (the dependencies modules are in
+// a sub-directory and are not included). Key is the module name.
+async fn read_bytecode_modules(
+    instance_path: &Path,
+    package_name: &str,
+) -> Result<BTreeMap<String, Vec<u8>>> {
+    let modules_path = instance_path
+        .join("build")
+        .join(package_name)
+        .join("bytecode_modules");
+    let mut modules = BTreeMap::new();
+    let mut entries = tokio::fs::read_dir(&modules_path).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if !entry.file_type().await?.is_file()
+            || path.extension().and_then(|e| e.to_str()) != Some("mv")
+        {
+            continue;
+        }
+        if let Some(module) = path.file_stem().and_then(|s| s.to_str()) {
+            modules.insert(module.to_string(), tokio::fs::read(&path).await?);
+        }
+    }
+    Ok(modules)
+}
+
 struct PackagesPollerWorkerTask {
     task_name: String,
     params: PackagesPollerParams,
@@ -183,12 +351,114 @@ impl PollingTraitObject {
             }
         }
 
+        // Upgrade lineage and owner from publish-output.json (best-effort, the file may
+        // be from an older version without JSON output).
+        let publish_output = tokio::fs::read_to_string(
+            package_path
+                .get_path(published_data_path)
+                .join("publish-output.json"),
+        )
+        .await
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok());
+        let mut upgrade_info = publish_output
+            .as_ref()
+            .map(parse_publish_upgrade_info)
+            .unwrap_or_default();
+        if upgrade_info.upgrade_cap.is_none() {
+            // Original publication, the UpgradeCap is among the created objects.
+            upgrade_info.upgrade_cap = top.as_array().and_then(|created_objects| {
+                created_objects
+                    .iter()
+                    .find(|o| o.get("type").and_then(|v| v.as_str()) == Some(UPGRADE_CAP_TYPE))
+                    .and_then(|o| o.get("objectId").and_then(|v| v.as_str()))
+                    .map(str::to_string)
+            });
+        }
+        let policy = match &upgrade_info.upgrade_cap {
+            Some(upgrade_cap) => self.get_upgrade_policy(upgrade_cap).await,
+            None => None,
+        };
+
+        // Source and bytecode identification (best-effort, see getDeployments).
+        let instance_path = package_path.get_path(published_data_path);
+        let commit = tokio::fs::read_to_string(instance_path.join("source.json"))
+            .await
+            .ok()
+            .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+            .and_then(|source| source.get("commit")?.as_str().map(str::to_string));
+        let modules = read_bytecode_modules(&instance_path, package_path.get_package_name())
+            .await
+            .unwrap_or_default();
+        let module_digests: BTreeMap<String, String> = modules
+            .iter()
+            .map(|(module, bytecode)| {
+                let digest = data_encoding::HEXLOWER.encode(&Sha256::digest(bytecode));
+                (module.clone(), digest)
+            })
+            .collect();
+
+        let publish_report = publish_output.as_ref().map(|publish_output| {
+            let mut report = parse_publish_report(publish_output);
+            report.modules = modules
+                .iter()
+                .map(|(module, bytecode)| ModuleSize {
+                    name: module.clone(),
+                    size: bytecode.len() as u64,
+                })
+                .collect();
+            report.total_size = report.modules.iter().map(|m| m.size).sum();
+            report
+        });
+
+        let tags = read_package_tags(&instance_path).await;
+
         let mut ret_value = PackageInstance::new(package_id.to_string(), package_path);
         ret_value.set_init_objects(objects);
-        // TODO ret_value.set_package_owner
+        ret_value.set_tags(tags);
+        if let Some(commit) = commit {
+            ret_value.set_source_commit(commit);
+        }
+        if !module_digests.is_empty() {
+            ret_value.set_bytecode_digests(package_digest(&module_digests), module_digests);
+        }
+        if let Some(publish_report) = publish_report {
+            ret_value.set_publish_report(publish_report);
+        }
+        if let Some(sender) = upgrade_info.sender {
+            ret_value.set_package_owner(sender);
+        }
+        ret_value.set_upgrade_info(upgrade_info.upgrade_cap, upgrade_info.version, policy);
         Ok(ret_value)
     }
 
+    // Current policy of an UpgradeCap. "immutable" when the cap was destroyed
+    // (sui::package::make_immutable). None if the network can't be reached.
+    //
+    // Read once when the package instance is created.
+    async fn get_upgrade_policy(&self, upgrade_cap: &str) -> Option<String> {
+        let uri = get_proxy_uri(&self.params.globals, self.params.workdir_idx)
+            .await
+            .ok()?;
+        let result = rpc_call(
+            &proxy_client().ok()?,
+            &uri,
+            "sui_getObject",
+            serde_json::json!([upgrade_cap, { "showContent": true }]),
+        )
+        .await
+        .ok()?;
+        if result.pointer("/error/code").and_then(|v| v.as_str()) == Some("deleted") {
+            return Some("immutable".to_string());
+        }
+        let policy = result.pointer("/data/content/fields/policy")?;
+        let policy = match policy {
+            serde_json::Value::String(s) => s.parse::<u64>().ok()?,
+            v => v.as_u64()?,
+        };
+        Some(upgrade_policy_name(policy).to_string())
+    }
+
     async fn update_globals_workdir_packages(&mut self) {
         let workdir_idx = self.params.workdir_idx;
         let workdir = WORKDIRS_KEYS[workdir_idx as usize].to_string();
@@ -217,7 +487,7 @@ impl PollingTraitObject {
             }
         };
 
-        let all_published_packages: HashSet<PackagePath> =
+        let mut all_published_packages: HashSet<PackagePath> =
             match Self::get_all_published_packages(published_data_path.clone()).await {
                 Ok(packages) => packages,
                 Err(e) => {
@@ -240,8 +510,29 @@ impl PollingTraitObject {
                 }
             };
 
+        // Retention. The expired instances are excluded as if not on the filesystem
+        // (so also removed from the UI).
+        let (keep_last, prune) = {
+            let globals_read_guard = self.params.globals.get_config(workdir_idx).read().await;
+            let config = &*globals_read_guard;
+            (
+                config.user_config.packages_keep_last() as usize,
+                config.user_config.packages_prune().to_string(),
+            )
+        };
+        if keep_last > 0 {
+            for package_path in expired_package_paths(&all_published_packages, keep_last) {
+                let instance_path = package_path.get_path(&published_data_path);
+                if !read_package_tags(&instance_path).await.is_empty() {
+                    continue;
+                }
+                all_published_packages.remove(&package_path);
+                self.prune_package_instance(&package_path, &published_data_path, &prune)
+                    .await;
+            }
+        }
+
         // Step 2
-        // TODO Limit package instances in UI + package instance tagging to preserve in UI.
         let mut to_be_removed: Vec<PackagePath> = Vec::new();
         let mut to_be_added: Vec<PackagePath> = Vec::new();
         let no_change_resp_header = {
@@ -336,6 +627,8 @@ impl PollingTraitObject {
                     }
                 }
                 if at_least_one_ui_change {
+                    ui.get_mut_data().update_upgrade_lineage();
+                    ui.get_mut_data().resolve_dependency_names();
                     ui.inc_uuid();
                 }
             }
@@ -359,6 +652,50 @@ impl PollingTraitObject {
         }
     }
 
+    // Archive (into published-data-archive) or delete an expired package instance
+    // directory. Nothing is done when prune is "none".
+    async fn prune_package_instance(
+        &self,
+        package_path: &PackagePath,
+        published_data_path: &PathBuf,
+        prune: &str,
+    ) {
+        let instance_path = package_path.get_path(published_data_path);
+        let result = match prune {
+            "archive" => {
+                let archive_path = package_path
+                    .get_path(&published_data_path.with_file_name("published-data-archive"));
+                if let Some(parent) = archive_path.parent() {
+                    let _ = tokio::fs::create_dir_all(parent).await;
+                }
+                tokio::fs::rename(&instance_path, &archive_path).await
+            }
+            "delete" => tokio::fs::remove_dir_all(&instance_path).await,
+            _ => return,
+        };
+        match result {
+            Ok(()) => {
+                log::info!("{} package instance {}", prune, instance_path.display());
+                // Remove the uuid and name directories when now empty (fails otherwise).
+                if let Some(uuid_path) = instance_path.parent() {
+                    if tokio::fs::remove_dir(uuid_path).await.is_ok() {
+                        if let Some(name_path) = uuid_path.parent() {
+                            let _ = tokio::fs::remove_dir(name_path).await;
+                        }
+                    }
+                }
+            }
+            Err(e) => {
+                log_safe!(format!(
+                    "Failed to {} {}: {}",
+                    prune,
+                    instance_path.display(),
+                    e
+                ));
+            }
+        }
+    }
+
     async fn get_published_data_path(&self, workdir_idx: WorkdirIdx) -> Result<PathBuf> {
         let workdir_paths = common::shared_types::get_workdir_paths(workdir_idx);
         let workdir_path = workdir_paths.workdir_root_path();
@@ -470,3 +807,79 @@ impl PollingTraitObject {
         }
     }*/
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_publish_upgrade_info() {
+        let upgrade_output = serde_json::json!({
+            "transaction": { "data": { "sender": "0xf7ae" } },
+            "objectChanges": [
+                { "type": "mutated", "objectId": "0x3a43", "objectType": "0x2::package::UpgradeCap", "version": "12" },
+                { "type": "mutated", "objectId": "0x5f", "objectType": "0x2::coin::Coin<0x2::sui::SUI>", "version": "12" },
+                { "type": "published", "packageId": "0xe066", "version": "2" }
+            ]
+        });
+        assert_eq!(
+            parse_publish_upgrade_info(&upgrade_output),
+            PublishUpgradeInfo {
+                upgrade_cap: Some("0x3a43".to_string()),
+                version: Some(2),
+                sender: Some("0xf7ae".to_string()),
+            }
+        );
+        assert_eq!(
+            parse_publish_upgrade_info(&serde_json::json!({})),
+            PublishUpgradeInfo::default()
+        );
+        assert_eq!(upgrade_policy_name(128), "additive");
+    }
+
+    #[test]
+    fn test_parse_publish_report() {
+        let publish_output = serde_json::json!({
+            "digest": "8p5N",
+            "transaction": { "data": { "transaction": {
+                "kind": "ProgrammableTransaction",
+                "transactions": [
+                    { "Publish": ["0x0000000000000000000000000000000000000000000000000000000000000001", "0x2", "0xe065"] },
+                    { "TransferObjects": [[{ "Result": 0 }], { "Input": 0 }] }
+                ]
+            } } },
+            "effects": { "gasUsed": {
+                "computationCost": "1000000",
+                "storageCost": "9872400",
+                "storageRebate": "978120",
+                "nonRefundableStorageFee": "9880"
+            } }
+        });
+        let report = parse_publish_report(&publish_output);
+        assert_eq!(report.tx_digest.as_deref(), Some("8p5N"));
+        assert_eq!(report.gas.unwrap().total, 9894280);
+        let names: Vec<Option<&str>> = report
+            .dependencies
+            .iter()
+            .map(|d| d.name.as_deref())
+            .collect();
+        assert_eq!(names, vec![Some("MoveStdlib"), Some("Sui"), None]);
+
+        let upgrade_output = serde_json::json!({ "transaction": { "data": { "transaction": {
+            "transactions": [{ "Upgrade": [["0x1", "0x2"], "0xe065", { "Result": 0 }] }]
+        } } } });
+        assert_eq!(parse_publish_report(&upgrade_output).dependencies.len(), 2);
+    }
+
+    #[test]
+    fn test_package_digest() {
+        let mut module_digests = BTreeMap::new();
+        module_digests.insert("counter".to_string(), "ab".to_string());
+        let digest = package_digest(&module_digests);
+        assert_eq!(digest.len(), 64);
+        module_digests.insert("logger".to_string(), "cd".to_string());
+        assert_ne!(package_digest(&module_digests), digest);
+        module_digests.remove("logger");
+        assert_eq!(package_digest(&module_digests), digest);
+    }
+}
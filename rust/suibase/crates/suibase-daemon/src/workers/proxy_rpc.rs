@@ -0,0 +1,59 @@
+// JSON-RPC helpers for the workers querying the network (e.g. events_backfill,
+// tx_poller, balance_poller).
+//
+// The calls go through the local proxy of the workdir (and its selection of the
+// best RPC server).
+use crate::shared_types::Globals;
+
+use anyhow::{anyhow, Result};
+use common::basic_types::WorkdirIdx;
+
+pub(super) async fn get_proxy_uri(globals: &Globals, workdir_idx: WorkdirIdx) -> Result<String> {
+    let workdir_name = common::shared_types::WORKDIRS_KEYS[workdir_idx as usize];
+    let globals_read_guard = globals.proxy.read().await;
+    let globals_proxy = &*globals_read_guard;
+    globals_proxy
+        .find_input_port_by_name(workdir_name)
+        .map(|input_port| input_port.local_uri())
+        .ok_or_else(|| anyhow!("no proxy port for {}", workdir_name))
+}
+
+pub(super) fn proxy_client() -> Result<reqwest::Client> {
+    // The proxy may use a self-signed certificate (see tls.rs).
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(10))
+        .no_proxy()
+        .danger_accept_invalid_certs(true)
+        .build()?;
+    Ok(client)
+}
+
+// JSON-RPC call through the proxy. Returns the "result".
+pub(super) async fn rpc_call(
+    client: &reqwest::Client,
+    uri: &str,
+    method: &str,
+    params: serde_json::Value,
+) -> Result<serde_json::Value> {
+    let request = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": method,
+        "params": params,
+    });
+    let resp: serde_json::Value = client
+        .post(uri)
+        .header("Content-Type", "application/json")
+        .json(&request)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    if let Some(err) = resp.get("error") {
+        return Err(anyhow!("{} error {}", method, err));
+    }
+    resp.get("result")
+        .cloned()
+        .ok_or_else(|| anyhow!("{} missing result {}", method, resp))
+}
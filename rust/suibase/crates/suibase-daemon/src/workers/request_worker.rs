@@ -1,4 +1,5 @@
 use crate::network_monitor::NetmonMsg;
+use crate::shared_types::GlobalsProxyMT;
 
 use anyhow::Result;
 use tokio_graceful_shutdown::{FutureExt, SubsystemHandle};
@@ -9,22 +10,38 @@ const SERVER_CHECK_REQUEST_BODY: &str =
     "{\"jsonrpc\":\"2.0\",\"method\":\"suix_getLatestSuiSystemState\",\"id\":1,\"params\":[\"\"]}";
 
 pub struct RequestWorker {
+    globals: GlobalsProxyMT,
     netmon_rx: NetMonRx,
     client: reqwest::Client,
 }
 
 impl RequestWorker {
-    pub fn new(netmon_rx: NetMonRx) -> Self {
+    pub fn new(globals: GlobalsProxyMT, netmon_rx: NetMonRx) -> Self {
+        // The requests are only toward this process own proxy ports, which
+        // may be using a self-signed certificate.
+        let client = reqwest::Client::builder()
+            .danger_accept_invalid_certs(true)
+            .build()
+            .unwrap_or_default();
         Self {
+            globals,
             netmon_rx,
-            client: reqwest::Client::new(),
+            client,
         }
     }
 
     async fn do_request(&mut self, msg: NetmonMsg) {
         let server_idx = msg.server_idx().to_string();
 
-        let uri = format!("http://localhost:{}", msg.para16()[0]);
+        // Scheme and host depend on the port TLS and bind address settings.
+        let uri = {
+            let globals_read_guard = self.globals.read().await;
+            let globals = &*globals_read_guard;
+            match globals.input_ports.get(msg.port_idx()) {
+                Some(input_port) => input_port.local_uri(),
+                None => format!("http://localhost:{}", msg.para16()[0]),
+            }
+        };
         let _ = self
             .client
             .request(reqwest::Method::POST, uri)
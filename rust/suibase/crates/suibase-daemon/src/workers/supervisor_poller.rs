@@ -0,0 +1,322 @@
+// Child task of admin_controller
+//
+// One instance per workdir with local services. Only localnet has local processes
+// (sui node and faucet). The indexer (db_worker), explorer and proxy are subsystems
+// of this daemon, already auto-restarted on panic, so they are not supervised here.
+//
+// Responsible to:
+//  - Restart the local services shown "NOT RUNNING" in the workdir status (see
+//    cli_poller.rs) while the user request is "start".
+//  - Restart policy, backoff and crash loop detection (see supervisor.rs).
+//  - Continuously capture the output of the services (their log file has both
+//    stdout and stderr) and the output of the restart commands.
+//
+// The restart is a "<workdir> start --daemoncall" (starts only what is missing).
+//
+// The task is auto-restart in case of panic.
+
+use std::{collections::HashMap, io::SeekFrom, time::Duration};
+
+use axum::async_trait;
+use common::{
+    basic_types::{AdminControllerTx, GenericTx, Instantiable, WorkdirContext, WorkdirIdx},
+    shared_types::{get_workdir_paths, WORKDIRS_KEYS, WORKDIR_IDX_LOCALNET},
+    workers::{PollerWorker, PollingTrait},
+};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_graceful_shutdown::SubsystemHandle;
+
+use crate::{
+    admin_controller::AdminController,
+    shared_types::{Globals, RestartPolicy},
+};
+
+const SUPERVISOR_POLL_MIN_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(5);
+
+// Max read from a log file per poll (the older output is skipped).
+const LOG_READ_MAX_BYTES: u64 = 64 * 1024;
+
+pub struct SupervisedServiceDef {
+    pub label: &'static str,    // As in the workdir status (StatusService label).
+    pub log_file: &'static str, // Relative to the workdir root.
+}
+
+const LOCALNET_SERVICES: [SupervisedServiceDef; 2] = [
+    SupervisedServiceDef {
+        label: "Localnet process",
+        log_file: "config/sui-process.log",
+    },
+    SupervisedServiceDef {
+        label: "Faucet process",
+        log_file: "config/sui-faucet-process.log",
+    },
+];
+
+pub fn supervised_services(workdir_idx: WorkdirIdx) -> &'static [SupervisedServiceDef] {
+    match workdir_idx {
+        WORKDIR_IDX_LOCALNET => &LOCALNET_SERVICES,
+        _ => &[],
+    }
+}
+
+// Complete lines appended to a log file since the offset, which is then moved after
+// the last newline. The offset restarts at zero when the file is re-created.
+async fn read_log_new_lines(
+    workdir_idx: WorkdirIdx,
+    log_file: &str,
+    offset: &mut u64,
+) -> std::io::Result<Vec<String>> {
+    let path = get_workdir_paths(workdir_idx)
+        .workdir_root_path()
+        .join(log_file);
+    let mut file = tokio::fs::File::open(&path).await?;
+    let len = file.metadata().await?.len();
+    if len < *offset {
+        *offset = 0;
+    }
+    let start = (*offset).max(len.saturating_sub(LOG_READ_MAX_BYTES));
+    if start >= len {
+        return Ok(Vec::new());
+    }
+    file.seek(SeekFrom::Start(start)).await?;
+    let mut buf = Vec::with_capacity((len - start) as usize);
+    file.take(len - start).read_to_end(&mut buf).await?;
+
+    // When output was skipped, the first line is partial.
+    let skipped = start > *offset;
+    let complete = match buf.iter().rposition(|b| *b == b'\n') {
+        Some(pos) => pos + 1,
+        None if skipped => buf.len(),
+        None => return Ok(Vec::new()),
+    };
+    *offset = start + complete as u64;
+    Ok(String::from_utf8_lossy(&buf[..complete])
+        .lines()
+        .skip(usize::from(skipped))
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| format!("[{}] {}", log_file, line))
+        .collect())
+}
+
+#[derive(Clone)]
+pub struct SupervisorPollerParams {
+    globals: Globals,
+    admctrl_tx: AdminControllerTx, // For exec shell messages
+    workdir_idx: WorkdirIdx,
+}
+
+impl WorkdirContext for SupervisorPollerParams {
+    fn workdir_idx(&self) -> WorkdirIdx {
+        self.workdir_idx
+    }
+}
+
+impl SupervisorPollerParams {
+    pub fn new(globals: Globals, admctrl_tx: AdminControllerTx, workdir_idx: WorkdirIdx) -> Self {
+        Self {
+            globals,
+            admctrl_tx,
+            workdir_idx,
+        }
+    }
+}
+
+pub struct SupervisorPoller {
+    // "Glue" the specialized PollingTraitObject with its parameters.
+    // The worker does all the background task/events handling.
+    poller: PollerWorker<PollingTraitObject, SupervisorPollerParams>,
+}
+
+pub struct PollingTraitObject {
+    params: SupervisorPollerParams,
+    last_poll: Option<tokio::time::Instant>,
+    log_offsets: HashMap<&'static str, u64>, // Key is the service label.
+}
+
+#[async_trait]
+impl PollingTrait for PollingTraitObject {
+    // This is called by the PollerWorker task.
+    async fn update(&mut self) {
+        // Prevent burst of checks.
+        if self
+            .last_poll
+            .is_some_and(|last_poll| last_poll.elapsed() < SUPERVISOR_POLL_MIN_INTERVAL)
+        {
+            return;
+        }
+        self.last_poll = Some(tokio::time::Instant::now());
+        self.supervise().await;
+    }
+}
+
+// This allow the PollerWorker to instantiate the PollingTraitObject.
+impl Instantiable<SupervisorPollerParams> for PollingTraitObject {
+    fn new(params: SupervisorPollerParams) -> Self {
+        Self {
+            params,
+            last_poll: None,
+            log_offsets: HashMap::new(),
+        }
+    }
+}
+
+impl SupervisorPoller {
+    pub fn new(params: SupervisorPollerParams, subsys: &SubsystemHandle) -> Self {
+        let poller =
+            PollerWorker::<PollingTraitObject, SupervisorPollerParams>::new(params.clone(), subsys);
+        Self { poller }
+    }
+
+    pub fn get_tx_channel(&self) -> GenericTx {
+        self.poller.get_tx_channel()
+    }
+}
+
+impl PollingTraitObject {
+    async fn capture_output(&mut self, defs: &[SupervisedServiceDef]) {
+        let workdir_idx = self.params.workdir_idx;
+        let mut captured = Vec::new();
+        for def in defs {
+            let offset = self.log_offsets.entry(def.label).or_insert(0);
+            // Missing file when the service was never started.
+            if let Ok(lines) = read_log_new_lines(workdir_idx, def.log_file, offset).await {
+                if !lines.is_empty() {
+                    captured.push((def.label, lines));
+                }
+            }
+        }
+        if captured.is_empty() {
+            return;
+        }
+        let mut supervisor_guard = self.params.globals.get_supervisor(workdir_idx).lock().await;
+        let supervisor = &mut *supervisor_guard;
+        for (label, lines) in captured {
+            supervisor.append_output(label, &lines);
+        }
+    }
+
+    async fn supervise(&mut self) {
+        let workdir_idx = self.params.workdir_idx;
+        let workdir = WORKDIRS_KEYS[workdir_idx as usize];
+        let defs = supervised_services(workdir_idx);
+        if defs.is_empty() {
+            return;
+        }
+
+        let (user_request_start, policy, max_backoff, crash_loop_restarts) = {
+            let globals_read_guard = self.params.globals.get_config(workdir_idx).read().await;
+            let user_config = &globals_read_guard.user_config;
+            (
+                user_config.is_user_request_start(),
+                RestartPolicy::from_config(user_config.supervisor_restart_policy()),
+                Duration::from_secs(user_config.supervisor_max_backoff_secs()),
+                user_config.supervisor_crash_loop_restarts(),
+            )
+        };
+
+        // Also done before a restart, which re-creates the log files.
+        if user_request_start {
+            self.capture_output(defs).await;
+        }
+
+        // Services (label, status, pid) from the latest workdir status.
+        let services: Vec<(String, String, Option<u64>)> = {
+            let globals_read_guard = self.params.globals.get_status(workdir_idx).read().await;
+            let globals = &*globals_read_guard;
+            match globals
+                .ui
+                .as_ref()
+                .and_then(|ui| ui.get_data().services.as_ref())
+            {
+                Some(services) => services
+                    .iter()
+                    .filter_map(|s| Some((s.label.clone(), s.status.clone()?, s.pid)))
+                    .collect(),
+                None => Vec::new(),
+            }
+        };
+
+        let now = std::time::Instant::now();
+        let due: Vec<&SupervisedServiceDef> = {
+            let mut supervisor_guard = self.params.globals.get_supervisor(workdir_idx).lock().await;
+            let supervisor = &mut *supervisor_guard;
+            if !user_request_start {
+                supervisor.stop_all();
+                return;
+            }
+            let mut due = Vec::new();
+            for def in defs {
+                // Not supervised when disabled (or not in the status).
+                let Some((_, status, pid)) = services.iter().find(|(l, _, _)| l == def.label)
+                else {
+                    continue;
+                };
+                if status == "DISABLED" {
+                    continue;
+                }
+                supervisor.observe(def.label, status == "OK", *pid, now);
+                if status == "NOT RUNNING" && supervisor.restart_due(def.label, policy, now) {
+                    due.push(def);
+                }
+            }
+            due
+        };
+        if due.is_empty() {
+            return;
+        }
+
+        let labels: Vec<&str> = due.iter().map(|def| def.label).collect();
+        log::warn!("{} restarting {}", workdir, labels.join(", "));
+        let (outcome, cmd_output) = match AdminController::send_shell_exec(
+            &self.params.admctrl_tx,
+            workdir_idx,
+            format!("{} start --daemoncall", workdir),
+        )
+        .await
+        {
+            Ok(cmd_resp) => {
+                let cmd_resp = common::utils::remove_ascii_color_code(&cmd_resp);
+                let error = cmd_resp
+                    .lines()
+                    .find(|line| line.trim_start().starts_with("Error:"))
+                    .map(|line| line.trim().to_string());
+                (error.unwrap_or_else(|| "OK".to_string()), cmd_resp)
+            }
+            Err(e) => (format!("Error: {e}"), String::new()),
+        };
+
+        // Refresh the status now (instead of waiting for next audit).
+        let _ = AdminController::send_event_update(&self.params.admctrl_tx, workdir_idx).await;
+
+        let now = std::time::Instant::now();
+        let mut supervisor_guard = self.params.globals.get_supervisor(workdir_idx).lock().await;
+        let supervisor = &mut *supervisor_guard;
+        let output: Vec<String> = cmd_output
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| format!("[{} start] {}", workdir, line))
+            .collect();
+        for def in &due {
+            // The log of the new process is read from its start.
+            self.log_offsets.insert(def.label, 0);
+            let crash_loop = supervisor.record_restart(
+                def.label,
+                "NOT RUNNING",
+                &outcome,
+                &output,
+                now,
+                crash_loop_restarts,
+                max_backoff,
+            );
+            if crash_loop {
+                log::error!(
+                    "{} {} in a crash loop. Not restarted until '{} stop' and '{} start'",
+                    workdir,
+                    def.label,
+                    workdir,
+                    workdir
+                );
+            }
+        }
+    }
+}
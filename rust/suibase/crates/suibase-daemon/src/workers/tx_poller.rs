@@ -0,0 +1,237 @@
+// Short-lived task started by the events_writer_worker (on audit, one per package).
+//
+// Responsible to:
+//   - Query the transactions calling a published package with a paginated
+//     "suix_queryTransactionBlocks" (MoveFunction filter), starting after the last
+//     transaction persisted in the DB (the cursor), through the local proxy.
+//   - Forward a summary of every transaction (effects status, gas used, object changes)
+//     to the events_writer_worker as an "add_sui_tx".
+//
+// The db_worker stores them in the {workdir}_sui_tx and {workdir}_sui_object_change tables.
+use crate::shared_types::Globals;
+
+use anyhow::{anyhow, Result};
+use common::basic_types::{self, GenericChannelMsg, GenericTx, WorkdirIdx};
+use serde::{Deserialize, Serialize};
+
+use super::proxy_rpc::{get_proxy_uri, proxy_client, rpc_call};
+
+const TX_POLL_PAGE_LIMIT: u64 = 50;
+// Protection against an unexpectedly large gap (50*20 = 1000 transactions per poll).
+// The next poll continues from where this one stopped.
+const TX_POLL_MAX_PAGES: usize = 20;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SuiObjectChange {
+    pub change_type: String, // "created", "mutated", "deleted", "published"...
+    pub object_id: String,
+    pub object_type: Option<String>,
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SuiTxSummary {
+    pub digest: String,
+    pub timestamp_ms: u64,
+    pub checkpoint: Option<u64>,
+    pub sender: String,
+    pub status: String, // "success" or "failure"
+    pub error: Option<String>,
+    // computationCost + storageCost - storageRebate (MIST). Negative when the
+    // transaction frees more storage than it uses.
+    pub gas_used: i64,
+    pub object_changes: Vec<SuiObjectChange>,
+}
+
+// Also used by packages_poller.rs
+pub(super) fn as_u64(v: Option<&serde_json::Value>) -> Option<u64> {
+    // Sui JSON-RPC returns the u64 as strings.
+    match v? {
+        serde_json::Value::String(s) => s.parse().ok(),
+        v => v.as_u64(),
+    }
+}
+
+impl SuiTxSummary {
+    // From a SuiTransactionBlockResponse (with showInput, showEffects and showObjectChanges).
+    pub fn from_tx_block(tx: &serde_json::Value) -> Option<Self> {
+        let digest = tx.get("digest")?.as_str()?.to_string();
+        let effects = tx.get("effects")?;
+        let status = effects.pointer("/status/status")?.as_str()?.to_string();
+        let error = effects
+            .pointer("/status/error")
+            .and_then(|v| v.as_str())
+            .map(str::to_string);
+        let gas = effects.get("gasUsed");
+        let gas_field = |name: &str| as_u64(gas.and_then(|g| g.get(name))).unwrap_or(0) as i64;
+        let gas_used =
+            gas_field("computationCost") + gas_field("storageCost") - gas_field("storageRebate");
+
+        let object_changes = tx
+            .get("objectChanges")
+            .and_then(|v| v.as_array())
+            .map(|changes| {
+                changes
+                    .iter()
+                    .filter_map(|change| {
+                        // A "published" change has a packageId instead of an objectId.
+                        let object_id = change
+                            .get("objectId")
+                            .or_else(|| change.get("packageId"))?
+                            .as_str()?;
+                        Some(SuiObjectChange {
+                            change_type: change.get("type")?.as_str()?.to_string(),
+                            object_id: object_id.to_string(),
+                            object_type: change
+                                .get("objectType")
+                                .and_then(|v| v.as_str())
+                                .map(str::to_string),
+                            version: change
+                                .get("version")
+                                .and_then(|v| v.as_str())
+                                .map(str::to_string),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Some(Self {
+            digest,
+            timestamp_ms: as_u64(tx.get("timestampMs")).unwrap_or(0),
+            checkpoint: as_u64(tx.get("checkpoint")),
+            sender: tx
+                .pointer("/transaction/data/sender")
+                .and_then(|v| v.as_str())
+                .unwrap_or("unknown")
+                .to_string(),
+            status,
+            error,
+            gas_used,
+            object_changes,
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct TxPollParams {
+    pub globals: Globals,
+    pub workdir_idx: WorkdirIdx,
+    pub package_uuid: String,
+    pub package_name: String,
+    pub package_id: String,     // No leading 0x
+    pub cursor: Option<String>, // Digest of the last transaction persisted.
+    pub events_writer_tx: GenericTx,
+}
+
+// Returns the number of transactions forwarded.
+pub async fn run_tx_poll(params: TxPollParams) -> Result<usize> {
+    let uri = get_proxy_uri(&params.globals, params.workdir_idx).await?;
+
+    let client = proxy_client()?;
+
+    let mut cursor = params.cursor.clone();
+    let mut n_txs = 0;
+    for _ in 0..TX_POLL_MAX_PAGES {
+        let result = rpc_call(
+            &client,
+            &uri,
+            "suix_queryTransactionBlocks",
+            serde_json::json!([
+                {
+                    "filter": { "MoveFunction": { "package": format!("0x{}", params.package_id) } },
+                    "options": { "showInput": true, "showEffects": true, "showObjectChanges": true }
+                },
+                cursor,
+                TX_POLL_PAGE_LIMIT,
+                false // Ascending (oldest first).
+            ]),
+        )
+        .await?;
+
+        if let Some(txs) = result.get("data").and_then(|v| v.as_array()) {
+            for tx in txs {
+                let summary = match SuiTxSummary::from_tx_block(tx) {
+                    Some(summary) => summary,
+                    None => {
+                        log::warn!("Unexpected transaction block format {}", tx);
+                        continue;
+                    }
+                };
+                let msg = GenericChannelMsg {
+                    event_id: basic_types::EVENT_EXEC,
+                    command: Some("add_sui_tx".to_string()),
+                    params: vec![
+                        params.package_uuid.clone(),
+                        params.package_name.clone(),
+                        params.package_id.clone(),
+                    ],
+                    data_json: serde_json::to_value(&summary).ok(),
+                    workdir_idx: Some(params.workdir_idx),
+                    resp_channel: None,
+                };
+                params
+                    .events_writer_tx
+                    .send(msg)
+                    .await
+                    .map_err(|e| anyhow!("add_sui_tx send failed {}", e))?;
+                n_txs += 1;
+            }
+        }
+
+        let has_next_page = result
+            .get("hasNextPage")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let next_cursor = result
+            .get("nextCursor")
+            .and_then(|v| v.as_str())
+            .map(str::to_string);
+
+        match next_cursor {
+            Some(next_cursor) if has_next_page && Some(&next_cursor) != cursor.as_ref() => {
+                cursor = Some(next_cursor);
+            }
+            _ => return Ok(n_txs),
+        }
+    }
+    Ok(n_txs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tx_summary_from_tx_block() {
+        let tx = serde_json::json!({
+            "digest": "8p5N",
+            "timestampMs": "1703895010111",
+            "checkpoint": "42",
+            "transaction": { "data": { "sender": "0xf7ae" } },
+            "effects": {
+                "status": { "status": "success" },
+                "gasUsed": {
+                    "computationCost": "1000000",
+                    "storageCost": "2432000",
+                    "storageRebate": "978120",
+                    "nonRefundableStorageFee": "9880"
+                }
+            },
+            "objectChanges": [
+                { "type": "mutated", "objectId": "0x5f", "objectType": "0x2::coin::Coin<0x2::sui::SUI>", "version": "7" },
+                { "type": "published", "packageId": "0xe065", "version": "1" }
+            ]
+        });
+        let summary = SuiTxSummary::from_tx_block(&tx).unwrap();
+        assert_eq!(summary.timestamp_ms, 1703895010111);
+        assert_eq!(summary.checkpoint, Some(42));
+        assert_eq!(summary.sender, "0xf7ae");
+        assert_eq!(summary.gas_used, 2453880);
+        assert_eq!(summary.object_changes.len(), 2);
+        assert_eq!(summary.object_changes[1].object_id, "0xe065");
+        assert_eq!(summary.object_changes[1].object_type, None);
+
+        assert!(SuiTxSummary::from_tx_block(&serde_json::json!({ "digest": "8p5N" })).is_none());
+    }
+}
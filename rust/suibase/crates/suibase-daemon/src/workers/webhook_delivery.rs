@@ -0,0 +1,98 @@
+// POST of a single stored event to a webhook (see "webhooks" in suibase.yaml).
+//
+// Used by the webhook_worker, which is responsible for the ordering, the retries
+// and the delivery cursor (at-least-once delivery).
+//
+// Headers of every delivery:
+//   X-Suibase-Event-Id:  "{kind}:{id}" unique per workdir. Same value on retries, so
+//                        the receiver can ignore duplicates.
+//   X-Suibase-Signature: "sha256={hex}" HMAC-SHA256 of the body. Only when the webhook
+//                        has a secret.
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use common::shared_types::Webhook;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use super::ExportedEvent;
+
+pub const WEBHOOK_EVENT_ID_HEADER: &str = "X-Suibase-Event-Id";
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "X-Suibase-Signature";
+
+// Failed attempts before an event is moved to the dead-letter table.
+pub const WEBHOOK_MAX_ATTEMPTS: u32 = 10;
+
+const WEBHOOK_BACKOFF_MAX_SECS: u64 = 300;
+
+pub fn webhook_client() -> Result<reqwest::Client> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .no_proxy()
+        .build()?;
+    Ok(client)
+}
+
+pub fn webhook_signature(secret: &str, body: &[u8]) -> String {
+    // HMAC accepts a key of any length.
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+    mac.update(body);
+    let digest = mac.finalize().into_bytes();
+    format!("sha256={}", data_encoding::HEXLOWER.encode(&digest))
+}
+
+// Delay before the next attempt, after `attempts` consecutive failures.
+pub fn webhook_backoff(attempts: u32) -> Duration {
+    let secs = 1u64
+        .checked_shl(attempts.saturating_sub(1))
+        .unwrap_or(u64::MAX)
+        .min(WEBHOOK_BACKOFF_MAX_SECS);
+    Duration::from_secs(secs)
+}
+
+pub async fn deliver_webhook(
+    client: &reqwest::Client,
+    webhook: &Webhook,
+    workdir_name: &str,
+    event: &ExportedEvent,
+) -> Result<()> {
+    let mut body = event.to_json();
+    body["workdir"] = serde_json::Value::String(workdir_name.to_string());
+    body["webhook"] = serde_json::Value::String(webhook.name.clone());
+    let body = body.to_string();
+
+    let mut request = client
+        .post(&webhook.url)
+        .header("Content-Type", "application/json")
+        .header(
+            WEBHOOK_EVENT_ID_HEADER,
+            format!("{}:{}", event.kind, event.id),
+        );
+    if let Some(secret) = &webhook.secret {
+        request = request.header(
+            WEBHOOK_SIGNATURE_HEADER,
+            webhook_signature(secret.expose(), body.as_bytes()),
+        );
+    }
+    let resp = request.body(body).send().await?;
+    if !resp.status().is_success() {
+        return Err(anyhow!("{} responded {}", webhook.url, resp.status()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_webhook_signature_and_backoff() {
+        assert_eq!(
+            webhook_signature("key", b"The quick brown fox jumps over the lazy dog"),
+            "sha256=f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+        assert_eq!(webhook_backoff(1), Duration::from_secs(1));
+        assert_eq!(webhook_backoff(4), Duration::from_secs(8));
+        assert_eq!(webhook_backoff(100), Duration::from_secs(300));
+    }
+}
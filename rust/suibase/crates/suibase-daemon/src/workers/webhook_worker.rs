@@ -0,0 +1,698 @@
+// Child thread of events_writer_worker (alongside the db_worker).
+//
+// Responsible to:
+//   - POST every event persisted by the db_worker to the webhooks configured
+//     in suibase.yaml (see webhook_delivery.rs).
+//   - Maintain a delivery cursor per (webhook, event table) in the sqlite.db file. The
+//     cursor moves only after a successful delivery (at-least-once).
+//   - Retry a failed delivery with an exponential backoff. The events of a webhook
+//     are delivered in order, so a failing event blocks the ones after it.
+//   - After WEBHOOK_MAX_ATTEMPTS, move the event to a dead-letter table and continue.
+//
+// Woken up by the db_worker ("events_persisted") after every write, and on audit.
+//
+// A new webhook starts with the events persisted after it was first seen (the
+// history is not replayed).
+//
+// The thread is auto-restart in case of panic.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::shared_types::Globals;
+use crate::workers::db_worker::{
+    event_table_suffixes, exported_event_from_row, package_filter_param, select_events_sql,
+    DB_BUSY_TIMEOUT,
+};
+use crate::workers::webhook_delivery::{
+    deliver_webhook, webhook_backoff, webhook_client, WEBHOOK_MAX_ATTEMPTS,
+};
+use crate::workers::ExportedEvent;
+
+use common::basic_types::{
+    self, AutoThread, DBTable, GenericChannelMsg, GenericRx, GenericTx, Runnable, WorkdirIdx,
+};
+use common::shared_types::Webhook;
+
+use rusqlite::Connection;
+
+use axum::async_trait;
+
+use tokio::sync::Mutex;
+use tokio_graceful_shutdown::{FutureExt, SubsystemHandle};
+
+// Max events read from a table per webhook per pass.
+const WEBHOOK_BATCH_SIZE: i64 = 100;
+
+// Schema: One entry per (webhook name, event table).
+//
+// Id of the last event delivered (or dead-lettered).
+struct WebhookCursor;
+
+impl DBTable for WebhookCursor {
+    fn create_table(
+        conn: &Connection,
+        workdir_name: String,
+        namespace: Option<String>,
+        _name_suffix: Option<String>,
+    ) -> rusqlite::Result<()> {
+        let sql = format!(
+            "CREATE TABLE IF NOT EXISTS {0}_{1}_webhook_cursor (
+                webhook_name    TEXT NOT NULL,
+                event_table     TEXT NOT NULL,
+                last_event_id   INTEGER NOT NULL,
+                PRIMARY KEY (webhook_name, event_table)
+            )",
+            workdir_name,
+            namespace.unwrap_or_else(|| "sui".to_string())
+        );
+        conn.execute(&sql, [])?;
+        Ok(())
+    }
+}
+
+impl WebhookCursor {
+    fn get_from_db(
+        conn: &Connection,
+        workdir_name: &str,
+        webhook_name: &str,
+        event_table: &str,
+    ) -> rusqlite::Result<Option<i64>> {
+        let sql = format!(
+            "SELECT last_event_id FROM {0}_sui_webhook_cursor
+            WHERE webhook_name = ?1 AND event_table = ?2",
+            workdir_name
+        );
+        let mut stmt = conn.prepare(&sql)?;
+        let mut rows = stmt.query([webhook_name, event_table])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(row.get(0)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn upsert_in_db(
+        conn: &Connection,
+        workdir_name: &str,
+        webhook_name: &str,
+        event_table: &str,
+        last_event_id: i64,
+    ) -> rusqlite::Result<()> {
+        let sql = format!(
+            "INSERT INTO {0}_sui_webhook_cursor (webhook_name, event_table, last_event_id)
+            VALUES (?1, ?2, ?3)
+            ON CONFLICT(webhook_name, event_table) DO UPDATE SET
+                last_event_id = excluded.last_event_id",
+            workdir_name
+        );
+        conn.execute(
+            &sql,
+            rusqlite::params![webhook_name, event_table, last_event_id],
+        )?;
+        Ok(())
+    }
+}
+
+// Schema: One entry per event that could not be delivered.
+struct WebhookDeadLetter;
+
+impl DBTable for WebhookDeadLetter {
+    fn create_table(
+        conn: &Connection,
+        workdir_name: String,
+        namespace: Option<String>,
+        _name_suffix: Option<String>,
+    ) -> rusqlite::Result<()> {
+        let sql = format!(
+            "CREATE TABLE IF NOT EXISTS {0}_{1}_webhook_dead_letter (
+                id              INTEGER PRIMARY KEY AUTOINCREMENT,
+                webhook_name    TEXT NOT NULL,
+                event_table     TEXT NOT NULL,
+                event_id        INTEGER NOT NULL,
+                attempts        INTEGER NOT NULL,
+                last_error      TEXT NOT NULL,
+                timestamp       INTEGER NOT NULL
+            )",
+            workdir_name,
+            namespace.unwrap_or_else(|| "sui".to_string())
+        );
+        conn.execute(&sql, [])?;
+        Ok(())
+    }
+}
+
+impl WebhookDeadLetter {
+    fn insert_in_db(
+        conn: &Connection,
+        workdir_name: &str,
+        webhook_name: &str,
+        event_table: &str,
+        event_id: i64,
+        attempts: u32,
+        last_error: &str,
+    ) -> rusqlite::Result<()> {
+        let now_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or(0);
+        let sql = format!(
+            "INSERT INTO {0}_sui_webhook_dead_letter
+            (webhook_name, event_table, event_id, attempts, last_error, timestamp)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            workdir_name
+        );
+        conn.execute(
+            &sql,
+            rusqlite::params![
+                webhook_name,
+                event_table,
+                event_id,
+                attempts,
+                last_error,
+                now_ms
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+// Highest event id of a table (0 when empty).
+fn max_event_id(conn: &Connection, event_table: &str) -> rusqlite::Result<i64> {
+    let sql = format!("SELECT COALESCE(MAX(id), 0) FROM {}", event_table);
+    conn.query_row(&sql, [], |row| row.get(0))
+}
+
+// Events after the cursor (up to max_id) that match the filters of the webhook.
+fn get_pending_events(
+    conn: &Connection,
+    workdir_name: &str,
+    suffix: &str,
+    webhook: &Webhook,
+    after_id: i64,
+    max_id: i64,
+) -> rusqlite::Result<Vec<ExportedEvent>> {
+    let sql = format!(
+        "{} ORDER BY e.id LIMIT ?5",
+        select_events_sql(workdir_name, suffix, "e.id > ?1 AND e.id <= ?2")
+    );
+    let package = package_filter_param(webhook.package.as_ref());
+
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(
+        rusqlite::params![
+            after_id,
+            max_id,
+            package,
+            webhook.event_type,
+            WEBHOOK_BATCH_SIZE
+        ],
+        exported_event_from_row,
+    )?;
+    rows.collect()
+}
+
+#[derive(Clone)]
+pub struct WebhookWorkerParams {
+    globals: Globals,
+    event_rx: Arc<Mutex<GenericRx>>,
+    event_tx: GenericTx,
+    workdir_idx: WorkdirIdx,
+    workdir_name: String,
+}
+
+impl WebhookWorkerParams {
+    pub fn new(
+        globals: Globals,
+        event_rx: GenericRx,
+        event_tx: GenericTx,
+        workdir_idx: WorkdirIdx,
+        workdir_name: String,
+    ) -> Self {
+        Self {
+            globals,
+            event_rx: Arc::new(Mutex::new(event_rx)),
+            event_tx,
+            workdir_idx,
+            workdir_name,
+        }
+    }
+}
+
+pub struct WebhookWorker {
+    auto_thread: AutoThread<WebhookWorkerThread, WebhookWorkerParams>,
+}
+
+impl WebhookWorker {
+    pub fn new(params: WebhookWorkerParams) -> Self {
+        Self {
+            auto_thread: AutoThread::new("WebhookWorker".to_string(), params),
+        }
+    }
+
+    pub async fn run(self, subsys: SubsystemHandle) -> anyhow::Result<()> {
+        self.auto_thread.run(subsys).await
+    }
+}
+
+// Consecutive failures of a webhook (for the event at its cursor).
+#[derive(Debug)]
+struct WebhookRetry {
+    attempts: u32,
+    next_attempt: tokio::time::Instant,
+}
+
+struct WebhookWorkerThread {
+    task_name: String,
+    params: WebhookWorkerParams,
+    conn: Option<Connection>,
+    client: Option<reqwest::Client>,
+    retries: HashMap<String, WebhookRetry>, // Key is the webhook name.
+}
+
+#[async_trait]
+impl Runnable<WebhookWorkerParams> for WebhookWorkerThread {
+    fn new(task_name: String, params: WebhookWorkerParams) -> Self {
+        Self {
+            task_name,
+            params,
+            conn: None,
+            client: None,
+            retries: HashMap::new(),
+        }
+    }
+
+    async fn run(mut self, subsys: SubsystemHandle) -> anyhow::Result<()> {
+        log::info!(
+            "{} started for {}",
+            self.task_name,
+            self.params.workdir_name
+        );
+
+        match self.event_loop(&subsys).cancel_on_shutdown(&subsys).await {
+            Ok(()) => {
+                log::info!("normal thread exit (2)");
+                Ok(())
+            }
+            Err(_cancelled_by_shutdown) => {
+                log::info!("normal thread exit (1)");
+                Ok(())
+            }
+        }
+    }
+}
+
+impl WebhookWorkerThread {
+    fn open_db(&mut self) -> bool {
+        let path = common::shared_types::get_workdir_paths(self.params.workdir_idx)
+            .workdir_root_path()
+            .join("indexer");
+        if std::fs::create_dir_all(&path).is_err() {
+            log::error!("Failed to create indexer directory: {:?}", path);
+            return false;
+        }
+        // Same file as the db_worker (which writes the events).
+        let conn = match Connection::open(path.join("sqlite.db")) {
+            Ok(conn) => conn,
+            Err(e) => {
+                log::error!("Failed to open sqlite database: {:?}", e);
+                return false;
+            }
+        };
+        if let Err(e) = conn.busy_timeout(DB_BUSY_TIMEOUT) {
+            log::error!("Failed to set busy timeout {:?}", e);
+            return false;
+        }
+        let workdir_name = self.params.workdir_name.clone();
+        if let Err(e) = WebhookCursor::create_table(&conn, workdir_name.clone(), None, None) {
+            log::error!(
+                "Failed to create {} webhook_cursor table {:?}",
+                workdir_name,
+                e
+            );
+            return false;
+        }
+        if let Err(e) = WebhookDeadLetter::create_table(&conn, workdir_name.clone(), None, None) {
+            log::error!(
+                "Failed to create {} webhook_dead_letter table {:?}",
+                workdir_name,
+                e
+            );
+            return false;
+        }
+        self.conn = Some(conn);
+        true
+    }
+
+    async fn deliver_all(&mut self) {
+        let webhooks: Vec<Webhook> = {
+            let globals_read_guard = self
+                .params
+                .globals
+                .get_config(self.params.workdir_idx)
+                .read()
+                .await;
+            let config = &*globals_read_guard;
+            config.user_config.webhooks().values().cloned().collect()
+        };
+
+        // Forget the retries of removed webhooks.
+        self.retries
+            .retain(|name, _| webhooks.iter().any(|webhook| webhook.name == *name));
+
+        for webhook in webhooks {
+            let due = self
+                .retries
+                .get(&webhook.name)
+                .is_none_or(|retry| tokio::time::Instant::now() >= retry.next_attempt);
+            if due {
+                if let Err(e) = self.deliver_webhook_pending(&webhook).await {
+                    // e.g. event tables not yet created by the db_worker.
+                    log::debug!("webhook {} not delivered: {}", webhook.name, e);
+                }
+            }
+        }
+    }
+
+    async fn deliver_webhook_pending(&mut self, webhook: &Webhook) -> anyhow::Result<()> {
+        let client = match &self.client {
+            Some(client) => client.clone(),
+            None => {
+                let client = webhook_client()?;
+                self.client = Some(client.clone());
+                client
+            }
+        };
+        let workdir_name = self.params.workdir_name.clone();
+
+        for suffix in event_table_suffixes() {
+            let event_table = format!("{}_sui_event_{}", workdir_name, suffix);
+            let (cursor, max_id, events) = {
+                let conn = self
+                    .conn
+                    .as_ref()
+                    .ok_or_else(|| anyhow::anyhow!("DB not open"))?;
+                let max_id = max_event_id(conn, &event_table)?;
+                let cursor =
+                    WebhookCursor::get_from_db(conn, &workdir_name, &webhook.name, &event_table)?;
+                let cursor = match cursor {
+                    Some(cursor) => cursor,
+                    None => {
+                        // New webhook. Start with the events persisted from now on.
+                        WebhookCursor::upsert_in_db(
+                            conn,
+                            &workdir_name,
+                            &webhook.name,
+                            &event_table,
+                            max_id,
+                        )?;
+                        continue;
+                    }
+                };
+                let events =
+                    get_pending_events(conn, &workdir_name, &suffix, webhook, cursor, max_id)?;
+                (cursor, max_id, events)
+            };
+
+            let mut last_id = cursor;
+            for event in &events {
+                match deliver_webhook(&client, webhook, &workdir_name, event).await {
+                    Ok(()) => {
+                        self.retries.remove(&webhook.name);
+                    }
+                    Err(e) => {
+                        if !self.report_failure(webhook, &event_table, event, &e.to_string())? {
+                            // Retry later, starting with this event.
+                            self.save_cursor(webhook, &event_table, cursor, last_id)?;
+                            return Ok(());
+                        }
+                    }
+                }
+                last_id = event.id;
+            }
+            // The events not matching the filters are skipped at once.
+            if events.len() < WEBHOOK_BATCH_SIZE as usize {
+                last_id = max_id;
+            }
+            self.save_cursor(webhook, &event_table, cursor, last_id)?;
+        }
+        Ok(())
+    }
+
+    fn save_cursor(
+        &self,
+        webhook: &Webhook,
+        event_table: &str,
+        cursor: i64,
+        last_id: i64,
+    ) -> anyhow::Result<()> {
+        if last_id > cursor {
+            if let Some(conn) = self.conn.as_ref() {
+                WebhookCursor::upsert_in_db(
+                    conn,
+                    &self.params.workdir_name,
+                    &webhook.name,
+                    event_table,
+                    last_id,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    // Returns true when the event is given up (moved to the dead-letter table).
+    fn report_failure(
+        &mut self,
+        webhook: &Webhook,
+        event_table: &str,
+        event: &ExportedEvent,
+        error: &str,
+    ) -> anyhow::Result<bool> {
+        let retry = self
+            .retries
+            .entry(webhook.name.clone())
+            .or_insert(WebhookRetry {
+                attempts: 0,
+                next_attempt: tokio::time::Instant::now(),
+            });
+        retry.attempts += 1;
+        if retry.attempts < WEBHOOK_MAX_ATTEMPTS {
+            retry.next_attempt = tokio::time::Instant::now() + webhook_backoff(retry.attempts);
+            log::warn!(
+                "webhook {} delivery of {}:{} failed (attempt {}): {}",
+                webhook.name,
+                event.kind,
+                event.id,
+                retry.attempts,
+                error
+            );
+            return Ok(false);
+        }
+        let attempts = retry.attempts;
+        self.retries.remove(&webhook.name);
+        log::error!(
+            "webhook {} gave up delivery of {}:{} after {} attempts: {}",
+            webhook.name,
+            event.kind,
+            event.id,
+            attempts,
+            error
+        );
+        if let Some(conn) = self.conn.as_ref() {
+            WebhookDeadLetter::insert_in_db(
+                conn,
+                &self.params.workdir_name,
+                &webhook.name,
+                event_table,
+                event.id,
+                attempts,
+                error,
+            )?;
+        }
+        Ok(true)
+    }
+
+    async fn event_loop(&mut self, subsys: &SubsystemHandle) {
+        // Take mutable ownership of the event_rx channel as long this thread is running.
+        let event_rx = Arc::clone(&self.params.event_rx);
+        let mut event_rx = event_rx.lock().await;
+
+        if self.conn.is_none() && !self.open_db() {
+            // Delay of 5 seconds before retrying.
+            tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+            return;
+        }
+
+        while !subsys.is_shutdown_requested() {
+            if let Some(msg) = event_rx.recv().await {
+                common::mpsc_q_check!(event_rx);
+                match msg.event_id {
+                    basic_types::EVENT_AUDIT | basic_types::EVENT_UPDATE => {
+                        self.deliver_all().await;
+                    }
+                    basic_types::EVENT_EXEC => {
+                        if msg.command().as_deref() == Some("events_persisted") {
+                            self.deliver_all().await;
+                        } else {
+                            log::error!(
+                                "Received a EVENT_EXEC message with unexpected command {:?}",
+                                msg.command()
+                            );
+                        }
+                    }
+                    _ => {
+                        // Consume unexpected messages.
+                        log::error!("Unexpected event_id {:?}", msg);
+                    }
+                }
+            } else {
+                // Channel closed or shutdown requested.
+                log::info!("Received a None internal message");
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    // Same columns as the db_worker tables used by select_events_sql.
+    fn open_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE localnet_sui_package (id INTEGER PRIMARY KEY, package_name TEXT);
+            CREATE TABLE localnet_sui_package_instance
+                (id INTEGER PRIMARY KEY, parent_id INTEGER, package_id TEXT);
+            INSERT INTO localnet_sui_package VALUES (1, 'demo');
+            INSERT INTO localnet_sui_package_instance VALUES (1, 1, 'e065');",
+        )
+        .unwrap();
+        for suffix in event_table_suffixes() {
+            let sql = format!(
+                "CREATE TABLE localnet_sui_event_{} (id INTEGER PRIMARY KEY AUTOINCREMENT,
+                package_instance_id INTEGER, timestamp INTEGER, event_json TEXT)",
+                suffix
+            );
+            conn.execute(&sql, []).unwrap();
+        }
+        WebhookCursor::create_table(&conn, "localnet".to_string(), None, None).unwrap();
+        WebhookDeadLetter::create_table(&conn, "localnet".to_string(), None, None).unwrap();
+        conn
+    }
+
+    // Answers 200 to every request.
+    async fn start_webhook_server() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = Vec::new();
+                let mut buf = [0u8; 4096];
+                loop {
+                    let n = stream.read(&mut buf).await.unwrap_or(0);
+                    request.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    let complete = text.find("\r\n\r\n").is_some_and(|header_end| {
+                        let content_length = text[..header_end]
+                            .lines()
+                            .find_map(|line| {
+                                let (name, value) = line.split_once(':')?;
+                                if name.eq_ignore_ascii_case("content-length") {
+                                    value.trim().parse::<usize>().ok()
+                                } else {
+                                    None
+                                }
+                            })
+                            .unwrap_or(0);
+                        request.len() >= header_end + 4 + content_length
+                    });
+                    if n == 0 || complete {
+                        break;
+                    }
+                }
+                let _ = stream
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                    .await;
+            }
+        });
+        url
+    }
+
+    fn webhook_cursor(conn: &Connection) -> Option<i64> {
+        WebhookCursor::get_from_db(conn, "localnet", "hook", "localnet_sui_event_user_0").unwrap()
+    }
+
+    fn dead_letters(conn: &Connection) -> Vec<i64> {
+        conn.prepare("SELECT event_id FROM localnet_sui_webhook_dead_letter ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_deliver_webhook_pending() {
+        let (event_tx, event_rx) = tokio::sync::mpsc::channel(10);
+        let params = WebhookWorkerParams::new(
+            Globals::new(),
+            event_rx,
+            event_tx,
+            common::shared_types::WORKDIR_IDX_LOCALNET,
+            "localnet".to_string(),
+        );
+        let mut worker = WebhookWorkerThread::new("test".to_string(), params);
+        worker.conn = Some(open_test_db());
+
+        // Nothing listening on this port.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let unreachable_url = format!("http://{}/", listener.local_addr().unwrap());
+        drop(listener);
+        let mut webhook = Webhook {
+            name: "hook".to_string(),
+            url: unreachable_url,
+            package: None,
+            event_type: None,
+            secret: None,
+        };
+
+        // A new webhook starts after the events already persisted.
+        worker.deliver_webhook_pending(&webhook).await.unwrap();
+        assert_eq!(webhook_cursor(worker.conn.as_ref().unwrap()), Some(0));
+        for timestamp in [1, 2] {
+            worker
+                .conn
+                .as_ref()
+                .unwrap()
+                .execute(
+                    "INSERT INTO localnet_sui_event_user_0
+                    (package_instance_id, timestamp, event_json) VALUES (1, ?1, '{}')",
+                    [timestamp],
+                )
+                .unwrap();
+        }
+
+        // The cursor does not move while the delivery fails.
+        for attempts in 1..WEBHOOK_MAX_ATTEMPTS {
+            worker.deliver_webhook_pending(&webhook).await.unwrap();
+            assert_eq!(worker.retries["hook"].attempts, attempts);
+            assert_eq!(webhook_cursor(worker.conn.as_ref().unwrap()), Some(0));
+        }
+        assert!(dead_letters(worker.conn.as_ref().unwrap()).is_empty());
+
+        // Given up: dead-lettered, and the next event is attempted.
+        worker.deliver_webhook_pending(&webhook).await.unwrap();
+        assert_eq!(dead_letters(worker.conn.as_ref().unwrap()), vec![1]);
+        assert_eq!(webhook_cursor(worker.conn.as_ref().unwrap()), Some(1));
+        assert_eq!(worker.retries["hook"].attempts, 1);
+
+        // Delivered.
+        webhook.url = start_webhook_server().await;
+        worker.deliver_webhook_pending(&webhook).await.unwrap();
+        assert_eq!(webhook_cursor(worker.conn.as_ref().unwrap()), Some(2));
+        assert!(worker.retries.is_empty());
+        assert_eq!(dead_letters(worker.conn.as_ref().unwrap()), vec![1]);
+    }
+}
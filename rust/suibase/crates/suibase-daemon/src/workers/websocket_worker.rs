@@ -1,10 +1,18 @@
 // Child thread of events_writer_worker
 //
+// There is one instance per link with a "ws" URL (see suibase.yaml). All instances
+// subscribe to the same events and the parent does the dedup.
+//
 // Responsible to:
-//   - websocket auto-reconnect for a single server.
+//   - websocket auto-reconnect for a single server (with the custom headers and
+//     auth of the link, see TargetServer::build_extra_headers).
 //   - keep alive the connection with Ping
 //   - subscribe/unsubscribe to Sui events, filter and forward the
 //     validated data to its parent thread.
+//   - subscriptions are for the packages published with Suibase, and for the
+//     "event_subscriptions" of suibase.yaml (MoveEventType, MoveModule, Sender...).
+//   - on every successful subscription (packages and "event_subscriptions"), request
+//     the parent to backfill the events possibly missed while not subscribed.
 //
 // The thread is auto-restart in case of panic.
 
@@ -12,6 +20,8 @@ use std::{collections::HashMap, sync::Arc};
 
 use crate::shared_types::Globals;
 
+use axum::http::HeaderMap;
+
 use common::shared_types::{
     WORKDIRS_KEYS, WORKDIR_IDX_DEVNET, WORKDIR_IDX_LOCALNET, WORKDIR_IDX_MAINNET,
     WORKDIR_IDX_TESTNET,
@@ -33,7 +43,11 @@ use futures::{
 };
 use tokio::{net::TcpStream, sync::Mutex};
 use tokio_graceful_shutdown::{FutureExt, SubsystemHandle};
-use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{self, client::IntoClientRequest, handshake::client::Request, Message},
+    MaybeTlsStream, WebSocketStream,
+};
 
 use common::basic_types::remove_generic_event_dups;
 use common::workers::{SubscriptionTracking, SubscriptionTrackingState};
@@ -46,6 +60,9 @@ pub struct WebSocketWorkerParams {
     events_writer_tx: GenericTx, // To send message to parent EventsWriterWorker.
     workdir_idx: WorkdirIdx,
     workdir_name: String,
+    link_alias: String, // Link providing the ws_url (forwarded with every event).
+    ws_url: String,
+    extra_headers: Option<Arc<HeaderMap>>, // Custom headers and auth of the link.
 }
 
 impl WebSocketWorkerParams {
@@ -55,6 +72,9 @@ impl WebSocketWorkerParams {
         event_tx: GenericTx,
         events_writer_tx: GenericTx,
         workdir_idx: WorkdirIdx,
+        link_alias: String,
+        ws_url: String,
+        extra_headers: Option<Arc<HeaderMap>>,
     ) -> Self {
         Self {
             globals,
@@ -63,8 +83,36 @@ impl WebSocketWorkerParams {
             events_writer_tx,
             workdir_idx,
             workdir_name: WORKDIRS_KEYS[workdir_idx as usize].to_string(),
+            link_alias,
+            ws_url,
+            extra_headers,
+        }
+    }
+}
+
+// Websocket handshake request with the extra headers of the link.
+fn build_ws_request(
+    ws_url: &str,
+    extra_headers: Option<&HeaderMap>,
+) -> Result<Request, tungstenite::Error> {
+    let mut request = ws_url.into_client_request()?;
+    if let Some(extra_headers) = extra_headers {
+        for (name, value) in extra_headers.iter() {
+            request.headers_mut().insert(name, value.clone());
         }
     }
+    Ok(request)
+}
+
+// Used when no link with a "ws" URL is configured for the workdir.
+pub fn default_websocket_url(workdir_idx: WorkdirIdx) -> Option<&'static str> {
+    match workdir_idx {
+        WORKDIR_IDX_LOCALNET => Some("ws://localhost:9000"),
+        WORKDIR_IDX_DEVNET => Some("wss://fullnode.devnet.sui.io:443"),
+        WORKDIR_IDX_TESTNET => Some("wss://fullnode.testnet.sui.io:443"),
+        WORKDIR_IDX_MAINNET => Some("wss://fullnode.mainnet.sui.io:443"),
+        _ => None,
+    }
 }
 
 pub struct WebSocketWorker {
@@ -144,13 +192,63 @@ impl Runnable<WebSocketWorkerParams> for WebSocketWorkerThread {
 }
 
 impl WebSocketWorkerThread {
-    fn subscribe_request_format(id: u64, package_id: &str) -> String {
+    fn subscribe_request_format(id: u64, event_filter: &str) -> String {
         format!(
-            r#"{{"jsonrpc":"2.0","method":"suix_subscribeEvent","id":{},"params":[{{"Package":"{}"}}]}}"#,
-            id, package_id
+            r#"{{"jsonrpc":"2.0","method":"suix_subscribeEvent","id":{},"params":[{}]}}"#,
+            id, event_filter
         )
     }
 
+    fn package_event_filter(package_id: &str) -> String {
+        format!(r#"{{"Package":"{}"}}"#, package_id) // Must not have leading 0x
+    }
+
+    // Add/remove the trackers for the "event_subscriptions" in suibase.yaml.
+    //
+    // The key in package_subs is "{uuid}:{event_filter}", so a modified filter
+    // is handled as a removal followed by an addition.
+    async fn sync_event_subscriptions(&mut self) {
+        let subscriptions: Vec<(String, String)> = {
+            let globals_read_guard = self
+                .params
+                .globals
+                .get_config(self.params.workdir_idx)
+                .read()
+                .await;
+            let config = &*globals_read_guard;
+            config
+                .user_config
+                .event_subscriptions()
+                .values()
+                .map(|sub| (sub.label.clone(), sub.filter.to_json().to_string()))
+                .collect()
+        };
+
+        let mut keys = Vec::with_capacity(subscriptions.len());
+        for (label, event_filter) in subscriptions {
+            let tracker = SubscriptionTracking::new_for_event_subscription(label, event_filter);
+            let key = format!("{}:{}", tracker.uuid(), tracker.event_filter().unwrap());
+            if !self.package_subs.contains_key(&key) {
+                self.package_subs.insert(key.clone(), tracker);
+            }
+            keys.push(key);
+        }
+
+        self.package_subs.retain(|key, tracker| {
+            if tracker.label().is_none() || keys.contains(key) {
+                return true;
+            }
+            if tracker.can_be_deleted() {
+                log::info!("Deleting tracking for subscription {}", key);
+                return false;
+            }
+            if !tracker.is_remove_requested() {
+                tracker.report_remove_request();
+            }
+            true
+        });
+    }
+
     fn unsubscribe_request_format(id: u64, unsubscribe_id: &str) -> String {
         format!(
             r#"{{"jsonrpc":"2.0","method":"suix_unsubscribeEvent","id":{},"params":[{}]}}"#,
@@ -189,6 +287,7 @@ impl WebSocketWorkerThread {
 
         // Check for expected response (correlate using the JSON-RPC id).
         let mut trig_audit_event = false;
+        let mut trig_backfill: Option<Vec<String>> = None;
         let mut correlated_msg = false;
         if msg_seq_number != 0 {
             for package in self.package_subs.values_mut() {
@@ -215,6 +314,22 @@ impl WebSocketWorkerThread {
                         let unsubscribe_id = result.unwrap();
                         package.report_subscribing_response(unsubscribe_id.to_string());
                         trig_audit_event = true;
+                        // Backfill is done for the managed packages (with their package id)
+                        // and for the "event_subscriptions" (with their event filter).
+                        let backfill_filter = if package.is_managed_package() {
+                            package.package_filter().cloned()
+                        } else if package.label().is_some() {
+                            package.event_filter().cloned()
+                        } else {
+                            None
+                        };
+                        if let Some(backfill_filter) = backfill_filter {
+                            trig_backfill = Some(vec![
+                                package.uuid().clone(),
+                                package.name().clone(),
+                                backfill_filter,
+                            ]);
+                        }
                         break;
                     }
                 } else if state == &SubscriptionTrackingState::Unsubscribing
@@ -298,6 +413,10 @@ impl WebSocketWorkerThread {
                 {
                     package_uuid = Some(tracker.uuid().clone());
                     package_name = Some(tracker.name().clone());
+                    if tracker.label().is_some() {
+                        // Configured subscription. Events can be from any package.
+                        break;
+                    }
                     // While we are here... do a sanity check that packageId field
                     // match what is in PackageTrackingState.
                     let package_id = result["packageId"].as_str();
@@ -378,7 +497,7 @@ impl WebSocketWorkerThread {
             let msg = GenericChannelMsg {
                 event_id: basic_types::EVENT_EXEC,
                 command: Some("add_sui_event".to_string()),
-                params: vec![package_uuid, package_name],
+                params: vec![package_uuid, package_name, self.params.link_alias.clone()],
                 data_json: Some(json_msg),
                 workdir_idx: Some(self.params.workdir_idx),
                 resp_channel: None,
@@ -391,6 +510,25 @@ impl WebSocketWorkerThread {
             }
         }
 
+        if let Some(params) = trig_backfill {
+            // Events may have been missed while not subscribed. The parent
+            // does the backfill (once for all websocket workers).
+            let msg = GenericChannelMsg {
+                event_id: basic_types::EVENT_EXEC,
+                command: Some("backfill_events".to_string()),
+                params,
+                data_json: None,
+                workdir_idx: Some(self.params.workdir_idx),
+                resp_channel: None,
+            };
+            if self.params.events_writer_tx.send(msg).await.is_err() {
+                log::error!(
+                    "Failed to backfill_events for workdir_idx={}",
+                    self.params.workdir_idx
+                );
+            }
+        }
+
         if trig_audit_event {
             let msg = GenericChannelMsg {
                 event_id: basic_types::EVENT_AUDIT,
@@ -471,6 +609,9 @@ impl WebSocketWorkerThread {
                 // Transition package to Unsubscribing state when no longer in the config.
                 // Remove the package tracking once unsubscription confirmed (or timeout).
                 self.package_subs.retain(|package_id, package_tracking| {
+                    if package_tracking.label().is_some() {
+                        return true; // See sync_event_subscriptions()
+                    }
                     let is_most_recent = wp_resp
                         .is_most_recent(package_tracking.uuid(), package_tracking.timestamp());
                     if !is_most_recent {
@@ -488,6 +629,8 @@ impl WebSocketWorkerThread {
             }
         } // End of reader lock.
 
+        self.sync_event_subscriptions().await;
+
         let websocket = &mut self.websocket;
         let packages = &mut self.package_subs;
 
@@ -675,21 +818,24 @@ impl WebSocketWorkerThread {
         }
 
         if send_subscribe_message {
-            if tracker.package_filter().is_none() {
+            let event_filter = if let Some(event_filter) = tracker.event_filter() {
+                event_filter.clone()
+            } else if let Some(package_id) = tracker.package_filter() {
+                Self::package_event_filter(package_id)
+            } else {
                 log::error!("Missing package_filter in SubscriptionTracking");
                 return false;
-            }
-            let package_id = tracker.package_filter().cloned().unwrap_or_default();
+            };
 
             // Check if retrying and log error only on first retry and once in a while after.
             if tracker.request_retry() % 3 == 1 {
-                log::error!("Failed to subscribe package_id={}", package_id);
+                log::error!("Failed to subscribe filter={}", event_filter);
             }
             websocket.seq_number += 1;
             tracker.report_subscribing_request(websocket.seq_number);
             let msg = Message::Text(Self::subscribe_request_format(
                 websocket.seq_number,
-                &package_id, // Must not have leading 0x
+                &event_filter,
             ));
 
             if let Some(ref mut write) = websocket.write {
@@ -807,22 +953,13 @@ impl WebSocketWorkerThread {
     }
 
     async fn open_websocket(&mut self) -> bool {
-        // Open a websocket connection to the server for this workdir.
-
-        // TODO Change this to the actual server URL from the config.
-        // For now, use hard coded Mysten Labs servers...
-        let socket_url = match self.params.workdir_idx {
-            WORKDIR_IDX_LOCALNET => "ws://localhost:9000",
-            WORKDIR_IDX_DEVNET => "wss://fullnode.devnet.sui.io:443",
-            WORKDIR_IDX_TESTNET => "wss://fullnode.testnet.sui.io:443",
-            WORKDIR_IDX_MAINNET => "wss://fullnode.mainnet.sui.io:443",
-            _ => {
-                log::error!("Unexpected workdir_idx {:?}", self.params.workdir_idx);
-                return false;
-            }
-        };
-
-        match connect_async(socket_url).await {
+        // Open a websocket connection to the server of this link.
+        let result =
+            match build_ws_request(&self.params.ws_url, self.params.extra_headers.as_deref()) {
+                Ok(request) => connect_async(request).await,
+                Err(e) => Err(e),
+            };
+        match result {
             Ok((ws_stream, _response)) => {
                 let (write, read) = ws_stream.split();
                 self.websocket.write = Some(write);
@@ -832,7 +969,10 @@ impl WebSocketWorkerThread {
                 if !e.to_string().contains("Connection refused") {
                     // "Connection refused" is annoying when localnet is not running, so ignore it.
                     // TODO Make this more "aware" about if localnet should be running or not.
-                    log_safe!(format!("connect_async error: {:?}", e));
+                    log_safe!(format!(
+                        "connect_async error for link {}: {:?}",
+                        self.params.link_alias, e
+                    ));
                 }
                 self.websocket.write = None;
                 self.websocket.read = None;
@@ -912,3 +1052,22 @@ impl WebSocketWorkerThread {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_build_ws_request() {
+        let mut extra_headers = HeaderMap::new();
+        extra_headers.insert("x-api-key", HeaderValue::from_static("key"));
+        let request = build_ws_request("wss://my-provider.com/sui", Some(&extra_headers)).unwrap();
+        assert_eq!(request.uri(), "wss://my-provider.com/sui");
+        assert_eq!(request.headers()["x-api-key"], "key");
+        // The websocket handshake headers are kept.
+        assert!(request.headers().contains_key("sec-websocket-key"));
+
+        assert!(build_ws_request("not a url", None).is_err());
+    }
+}
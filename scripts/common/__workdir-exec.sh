@@ -349,6 +349,24 @@ workdir_exec() {
       shift
     done
     ;; # End parsing links
+  status)
+    while [[ "$#" -gt 0 ]]; do
+      case $1 in
+      --debug) DEBUG_PARAM=true ;;
+      --daemoncall) IS_DAEMON_CALL=true ;;
+      --json) JSON_PARAM=true ;;
+      --precompiled | --nobinary)
+        echo "Option '$1' not compatible with '$CMD_REQ' command"
+        exit 1
+        ;;
+      *)
+        echo "Unknown parameter passed: $1"
+        exit 1
+        ;;
+      esac
+      shift
+    done
+    ;; # End parsing status
   *)
     while [[ "$#" -gt 0 ]]; do
       case $1 in
@@ -674,35 +692,65 @@ workdir_exec() {
       else
         _SUPPORT_FAUCET=true
       fi
+    fi
 
-      # Overall status: STOPPED or OK/DEGRADED/DOWN
-      echo -n "localnet "
-      if [ "$_USER_REQUEST" = "stop" ]; then
-        echo_red "STOPPED"
-      else
-        if [ -z "$SUI_PROCESS_PID" ]; then
-          echo_red "DOWN"
-        else
-          local _DEGRADED=false
-          if $_SUPPORT_FAUCET && [ -z "$SUI_FAUCET_PROCESS_PID" ]; then
-            _DEGRADED=true
-          fi
-          if $_SUPPORT_PROXY && [ -z "$SUIBASE_DAEMON_PID" ]; then
-            _DEGRADED=true
-          fi
-          if $_SUPPORT_SUI_EXPLORER && [ -z "$SUIBASE_DAEMON_PID" ]; then
-            _DEGRADED=true
-          fi
-          if $_SUPPORT_DTP && [ -z "$DTP_DAEMON_PID" ]; then
-            _DEGRADED=true
-          fi
-          if [ "$_DEGRADED" = true ]; then
-            echo_yellow "DEGRADED"
-          else
-            echo_green "OK"
-          fi
+    # Overall status: STOPPED or OK/DEGRADED/DOWN (same for the text and JSON output).
+    local _STATUS
+    if [ "$_USER_REQUEST" = "stop" ]; then
+      _STATUS="STOPPED"
+    elif ! $is_local; then
+      _STATUS="OK"
+    elif [ -z "$SUI_PROCESS_PID" ]; then
+      _STATUS="DOWN"
+    elif { $_SUPPORT_FAUCET && [ -z "$SUI_FAUCET_PROCESS_PID" ]; } ||
+      { $_SUPPORT_PROXY && [ -z "$SUIBASE_DAEMON_PID" ]; } ||
+      { $_SUPPORT_SUI_EXPLORER && [ -z "$SUIBASE_DAEMON_PID" ]; } ||
+      { $_SUPPORT_DTP && [ -z "$DTP_DAEMON_PID" ]; }; then
+      _STATUS="DEGRADED"
+    else
+      _STATUS="OK"
+    fi
+
+    if [ "$JSON_PARAM" = true ]; then
+      # Structured output (used by the suibase-daemon).
+      local _SERVICES=""
+      if [ ! "$_USER_REQUEST" = "stop" ]; then
+        if $is_local; then
+          _SERVICES="$_SERVICES,$(json_process "Localnet process" true "$SUI_PROCESS_PID" "$SUI_VERSION")"
+          _SERVICES="$_SERVICES,$(json_process "Faucet process" "$_SUPPORT_FAUCET" "$SUI_FAUCET_PROCESS_PID")"
+        fi
+        if [ "${CFG_dtp_enabled:?}" = "true" ]; then
+          _SERVICES="$_SERVICES,$(json_process "DTP services" "$_SUPPORT_DTP" "$DTP_DAEMON_PID")"
+        fi
+        if $_SHOW_PROXY; then
+          _SERVICES="$_SERVICES,$(json_process "Proxy server" "$_SUPPORT_PROXY" "$SUIBASE_DAEMON_PID")"
+        fi
+        if [ "$_SUPPORT_PROXY" = true ]; then
+          _SERVICES="$_SERVICES,{\"label\":\"Multi-link RPC\",\"status\":\"$(json_escape "$_MLINK_STATUS")\"}"
         fi
       fi
+
+      echo -n "{\"workdir\":\"$(json_escape "$WORKDIR")\",\"status\":\"$_STATUS\""
+      echo -n ",\"userRequest\":\"$(json_escape "$_USER_REQUEST")\""
+      echo -n ",\"clientVersion\":\"$(json_escape "$SUI_VERSION")\""
+      echo -n ",\"asuiSelection\":\"$(json_escape "$ACTIVE_WORKDIR")\""
+      echo ",\"services\":[${_SERVICES#,}]}"
+      exit
+    fi
+
+    if $is_local; then
+      echo -n "localnet "
+      case $_STATUS in
+      "OK")
+        echo_green "OK"
+        ;;
+      "DEGRADED")
+        echo_yellow "DEGRADED"
+        ;;
+      *)
+        echo_red "$_STATUS"
+        ;;
+      esac
       echo
 
       # Individual process status
@@ -725,14 +773,13 @@ workdir_exec() {
     fi
 
     if ! $is_local; then
-      # Overall status: STOPPED or OK/DEGRADED/DOWN
       echo -n "$WORKDIR "
-      if [ "$_USER_REQUEST" = "stop" ]; then
+      if [ "$_STATUS" = "STOPPED" ]; then
         echo -n "services "
         echo_red "STOPPED"
         echo
       else
-        echo_green "OK"
+        echo_green "$_STATUS"
         echo
         echo "---"
       fi
@@ -1484,3 +1531,43 @@ echo_process() {
   fi
 }
 export -f echo_process
+
+json_escape() {
+  # Escape a string to be inside double quotes in a JSON output.
+  local _STR=$1
+  _STR=${_STR//\\/\\\\}
+  _STR=${_STR//\"/\\\"}
+  _STR=${_STR//$'\t'/\\t}
+  _STR=${_STR//$'\r'/\\r}
+  _STR=${_STR//$'\n'/\\n}
+  echo -n "$_STR"
+}
+export -f json_escape
+
+json_process() {
+  # JSON counterpart of echo_process (see "status --json").
+  local _LABEL=$1
+  local _IS_SUPPORTED=$2
+  local _PID=$3
+  local _VERSION=$4
+
+  echo -n "{\"label\":\"$(json_escape "$_LABEL")\","
+  if ! $_IS_SUPPORTED; then
+    echo -n "\"status\":\"DISABLED\"}"
+  elif [ -z "$_PID" ]; then
+    echo -n "\"status\":\"NOT RUNNING\"}"
+  else
+    # Elapsed time since the process started ([[dd-]hh:]mm:ss).
+    local _ETIME
+    _ETIME=$(ps -o etime= -p "$_PID" 2>/dev/null | tr -d '[:space:]')
+    echo -n "\"status\":\"OK\",\"pid\":$_PID"
+    if [ -n "$_ETIME" ]; then
+      echo -n ",\"etime\":\"$(json_escape "$_ETIME")\""
+    fi
+    if [ -n "$_VERSION" ]; then
+      echo -n ",\"version\":\"$(json_escape "$_VERSION")\""
+    fi
+    echo -n "}"
+  fi
+}
+export -f json_process